@@ -0,0 +1,576 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::io::{ErrorKind, Write};
+use std::process::{Child, Command, Output, Stdio};
+
+use super::commit::staged_scope;
+use super::lint::{allowed_scopes, allowed_types, lint_message, Violation};
+use crate::config::{AiConfig, Config, SubjectCase};
+use crate::git::GitBackend;
+
+/// Lock files and other generated files whose changes say little about a
+/// commit. When a diff has to be cut down, they are only listed.
+const GENERATED_FILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "go.sum",
+    "composer.lock",
+    "Gemfile.lock",
+];
+
+/// Seconds to wait for an HTTP provider before giving up.
+const REQUEST_TIMEOUT: &str = "120";
+
+/// What a provider writes a commit message from.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    /// The message format and the allowed types and scopes.
+    pub instructions: String,
+    /// The staged diff, cut down to `ai.max_diff_chars`.
+    pub diff: String,
+    /// Whether `diff` is a summary of a larger diff.
+    pub truncated: bool,
+}
+
+/// Writes commit messages, e.g. by asking a language model.
+pub trait MessageProvider {
+    fn generate(&self, prompt: &Prompt) -> Result<String>;
+}
+
+impl<F: Fn(&Prompt) -> Result<String>> MessageProvider for F {
+    fn generate(&self, prompt: &Prompt) -> Result<String> {
+        self(prompt)
+    }
+}
+
+/// A generated message and the commit rules it breaks.
+#[derive(Debug, Clone)]
+pub struct GeneratedMessage {
+    pub message: String,
+    pub violations: Vec<Violation>,
+}
+
+impl GeneratedMessage {
+    /// Checks `message` against the `[lint]` rules when
+    /// `commits.conventional` is on.
+    pub fn new(message: String, config: &Config) -> Self {
+        let violations = if config.commits.conventional {
+            lint_message(&message, config)
+        } else {
+            Vec::new()
+        };
+        Self {
+            message,
+            violations,
+        }
+    }
+}
+
+/// The provider `ai.provider` selects.
+pub fn provider(ai: &AiConfig) -> Result<Box<dyn MessageProvider>> {
+    match ai.provider.as_str() {
+        "openai" => Ok(Box::new(OpenAiProvider {
+            endpoint: ai.endpoint.trim_end_matches('/').to_string(),
+            model: ai.model.clone(),
+            api_key: ai
+                .api_key
+                .clone()
+                .or_else(|| std::env::var("OPENAI_API_KEY").ok())
+                .filter(|key| !key.is_empty()),
+        })),
+        "command" => {
+            let command = ai
+                .command
+                .clone()
+                .context("ai.provider is 'command' but ai.command is not set")?;
+            Ok(Box::new(CommandProvider { command }))
+        }
+        other => bail!("Unknown AI provider '{}'; use 'openai' or 'command'", other),
+    }
+}
+
+/// Builds the prompt for the staged changes, or for the whole amended
+/// commit when `amend` is set.
+pub fn prompt(
+    repo: &dyn GitBackend,
+    config: &Config,
+    ai: &AiConfig,
+    amend: bool,
+) -> Result<Prompt> {
+    let diff = repo.staged_diff(amend)?;
+    if diff.trim().is_empty() {
+        bail!("Nothing is staged; stage changes with 'git add' first");
+    }
+    let scope = staged_scope(repo, config)?;
+    let fitted = fit_diff(&diff, ai.max_diff_chars);
+
+    Ok(Prompt {
+        instructions: instructions(config, scope.as_deref()),
+        truncated: fitted != diff,
+        diff: fitted,
+    })
+}
+
+/// Asks `provider` for a message and checks it against the commit rules.
+pub fn generate(
+    provider: &dyn MessageProvider,
+    prompt: &Prompt,
+    config: &Config,
+) -> Result<GeneratedMessage> {
+    let message = clean_message(&provider.generate(prompt)?);
+    if message.is_empty() {
+        bail!("The AI provider returned an empty message");
+    }
+    Ok(GeneratedMessage::new(message, config))
+}
+
+fn instructions(config: &Config, scope: Option<&str>) -> String {
+    let mut lines = Vec::new();
+    if !config.commits.conventional {
+        lines.push(
+            "Write a git commit message for the diff: a short summary line in the imperative \
+             mood, then a blank line and a body explaining what changed and why, if needed."
+                .to_string(),
+        );
+        lines.push("Reply with the commit message only.".to_string());
+        return lines.join("\n");
+    }
+
+    let rules = &config.lint;
+    lines.push(
+        "Write a git commit message for the diff, following Conventional Commits:".to_string(),
+    );
+    lines.push("- The first line is `type(scope): description`; the scope is optional".to_string());
+    if rules.header_max_length > 0 {
+        lines.push(format!(
+            "- The first line has at most {} characters",
+            rules.header_max_length
+        ));
+    }
+    let case = match rules.subject_case {
+        SubjectCase::Lower => ", starting with a lowercase letter",
+        SubjectCase::Sentence => ", starting with an uppercase letter",
+        SubjectCase::Any => "",
+    };
+    lines.push(format!(
+        "- The description is in the imperative mood{} and has no trailing period",
+        case
+    ));
+    lines.push(
+        "- A body, after a blank line, explains what changed and why when that is not obvious"
+            .to_string(),
+    );
+    lines.push(
+        "- Only for incompatible changes, end with a `BREAKING CHANGE: <description>` footer"
+            .to_string(),
+    );
+
+    lines.push("Types:".to_string());
+    for name in allowed_types(config) {
+        let description = config
+            .commits
+            .types
+            .iter()
+            .find(|kind| kind.name == name)
+            .map(|kind| kind.description.as_str())
+            .filter(|description| !description.is_empty());
+        match description {
+            Some(description) => lines.push(format!("- {}: {}", name, description)),
+            None => lines.push(format!("- {}", name)),
+        }
+    }
+
+    let scopes = allowed_scopes(config);
+    if !scopes.is_empty() {
+        lines.push(format!(
+            "Use one of these scopes, or none: {}",
+            scopes.join(", ")
+        ));
+    }
+    if let Some(scope) = scope {
+        lines.push(format!(
+            "Most staged files belong to the scope '{}'.",
+            scope
+        ));
+    }
+    lines.push("Reply with the commit message only.".to_string());
+    lines.join("\n")
+}
+
+/// Cuts `diff` down to about `max_chars` characters. A larger diff is
+/// replaced by a list of every changed file with its added and removed line
+/// counts, followed by as much of each file's changes as fits, split evenly.
+/// Generated files such as lock files are only listed.
+pub fn fit_diff(diff: &str, max_chars: usize) -> String {
+    if diff.chars().count() <= max_chars {
+        return diff.to_string();
+    }
+
+    let files = split_files(diff);
+    let mut summary = String::from("The diff is too large to include in full. Changed files:\n");
+    for (path, section) in &files {
+        let (added, removed) = count_changes(section);
+        summary.push_str(&format!("  {} (+{} -{})\n", path, added, removed));
+    }
+    summary.push('\n');
+
+    let shown: Vec<&(&str, &str)> = files
+        .iter()
+        .filter(|(path, _)| !is_generated(path))
+        .collect();
+    let mut budget = max_chars.saturating_sub(summary.chars().count());
+    let mut fitted = summary;
+    for (index, (_, section)) in shown.iter().enumerate() {
+        let share = budget / (shown.len() - index);
+        let part = truncate_lines(section, share);
+        budget = budget.saturating_sub(part.chars().count());
+        fitted.push_str(&part);
+    }
+    fitted
+}
+
+/// Splits a patch into `(path, section)` pairs, one per `diff --git` header.
+fn split_files(diff: &str) -> Vec<(&str, &str)> {
+    let mut starts: Vec<usize> = diff
+        .match_indices("diff --git ")
+        .map(|(index, _)| index)
+        .filter(|&index| index == 0 || diff.as_bytes()[index - 1] == b'\n')
+        .collect();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(diff.len());
+            let section = &diff[start..end];
+            let header = section.lines().next().unwrap_or_default();
+            let path = header.rsplit_once(" b/").map_or(header, |(_, path)| path);
+            (path, section)
+        })
+        .collect()
+}
+
+fn count_changes(section: &str) -> (usize, usize) {
+    section.lines().fold((0, 0), |(added, removed), line| {
+        if line.starts_with('+') && !line.starts_with("+++") {
+            (added + 1, removed)
+        } else if line.starts_with('-') && !line.starts_with("---") {
+            (added, removed + 1)
+        } else {
+            (added, removed)
+        }
+    })
+}
+
+fn is_generated(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    GENERATED_FILES.contains(&name)
+}
+
+/// The whole lines of `section` that fit in `max_chars`, with a note about
+/// the lines left out.
+fn truncate_lines(section: &str, max_chars: usize) -> String {
+    if section.chars().count() <= max_chars {
+        return section.to_string();
+    }
+
+    let mut kept = String::new();
+    let mut used = 0;
+    let mut lines = section.split_inclusive('\n');
+    for line in lines.by_ref() {
+        let length = line.chars().count();
+        if used + length > max_chars {
+            let omitted = 1 + lines.count();
+            kept.push_str(&format!("[... {} more lines]\n", omitted));
+            return kept;
+        }
+        used += length;
+        kept.push_str(line);
+    }
+    kept
+}
+
+/// Removes what models tend to wrap a message in: surrounding whitespace,
+/// Markdown code fences and quotes.
+pub fn clean_message(raw: &str) -> String {
+    let mut message = raw.trim().replace("\r\n", "\n");
+
+    if message.starts_with("```") {
+        let without_open = message.split_once('\n').map_or("", |(_, rest)| rest);
+        message = without_open
+            .trim_end()
+            .trim_end_matches("```")
+            .trim()
+            .to_string();
+    }
+
+    for quote in ['"', '\'', '`'] {
+        if message.len() > 1 && message.starts_with(quote) && message.ends_with(quote) {
+            message = message[1..message.len() - 1].trim().to_string();
+        }
+    }
+    message
+}
+
+/// Sends the prompt to an OpenAI-compatible `/chat/completions` endpoint,
+/// which covers OpenAI and local servers such as Ollama, llama.cpp and vLLM.
+/// Requests are made with `curl`, fed its options on stdin so the API key
+/// does not show up in the process list.
+pub struct OpenAiProvider {
+    /// Base URL, such as `https://api.openai.com/v1`.
+    pub endpoint: String,
+    pub model: String,
+    pub api_key: Option<String>,
+}
+
+impl MessageProvider for OpenAiProvider {
+    fn generate(&self, prompt: &Prompt) -> Result<String> {
+        let url = format!("{}/chat/completions", self.endpoint);
+        which::which("curl").with_context(|| {
+            format!(
+                "curl is needed to reach {}; install it or set ai.provider = \"command\"",
+                url
+            )
+        })?;
+
+        let body = chat_request(&self.model, prompt).to_string();
+        let mut options = format!("url = \"{}\"\n", curl_escape(&url));
+        options.push_str("header = \"Content-Type: application/json\"\n");
+        if let Some(key) = &self.api_key {
+            options.push_str(&format!(
+                "header = \"Authorization: Bearer {}\"\n",
+                curl_escape(key)
+            ));
+        }
+        options.push_str(&format!("data-binary = \"{}\"\n", curl_escape(&body)));
+
+        let child = Command::new("curl")
+            .args(["--silent", "--show-error", "--max-time", REQUEST_TIMEOUT])
+            .args(["--write-out", "\n%{http_code}", "--config", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run curl")?;
+        let output = feed(child, options.into_bytes())?;
+        if !output.status.success() {
+            bail!(
+                "Could not reach {}: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (body, status) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+        let status: u16 = status.trim().parse().unwrap_or_default();
+        if !(200..300).contains(&status) {
+            bail!("{} answered {}: {}", url, status, error_message(body));
+        }
+        parse_chat_response(body)
+    }
+}
+
+/// The `/chat/completions` request body for `prompt`.
+pub fn chat_request(model: &str, prompt: &Prompt) -> Value {
+    json!({
+        "model": model,
+        "messages": [
+            { "role": "system", "content": prompt.instructions },
+            { "role": "user", "content": prompt.diff },
+        ],
+    })
+}
+
+/// The message in a `/chat/completions` response.
+pub fn parse_chat_response(body: &str) -> Result<String> {
+    let response: Value =
+        serde_json::from_str(body).context("The AI provider's answer is not JSON")?;
+    response["choices"][0]["message"]["content"]
+        .as_str()
+        .map(String::from)
+        .context("The AI provider's answer contains no message")
+}
+
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|value| value["error"]["message"].as_str().map(String::from))
+        .unwrap_or_else(|| body.trim().chars().take(200).collect())
+}
+
+/// Quotes a value for a curl config file.
+fn curl_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+/// Pipes the diff to a shell command, with the instructions in
+/// `GWF_AI_PROMPT`, and takes what it prints as the message. Any tool that
+/// talks to a model can be plugged in this way.
+pub struct CommandProvider {
+    pub command: String,
+}
+
+impl MessageProvider for CommandProvider {
+    fn generate(&self, prompt: &Prompt) -> Result<String> {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("GWF_AI_PROMPT", &prompt.instructions)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("Failed to run '{}'", self.command))?;
+
+        let output = feed(child, prompt.diff.clone().into_bytes())?;
+        if !output.status.success() {
+            bail!("'{}' failed", self.command);
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Writes `input` to the child's stdin while its output is collected, so
+/// neither side blocks on a full pipe.
+fn feed(mut child: Child, input: Vec<u8>) -> Result<Output> {
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+
+    // Programs that do not read all of their input close the pipe early.
+    match writer.join() {
+        Ok(Err(error)) if error.kind() != ErrorKind::BrokenPipe => Err(error.into()),
+        _ => Ok(output),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::MemoryRepo;
+
+    fn file_diff(path: &str, lines: usize) -> String {
+        let mut diff = format!(
+            "diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n@@ -1 +1,{1} @@\n",
+            path, lines
+        );
+        for line in 0..lines {
+            diff.push_str(&format!("+line {}\n", line));
+        }
+        diff
+    }
+
+    #[test]
+    fn test_fit_diff() {
+        let small = file_diff("src/lib.rs", 3);
+        assert_eq!(fit_diff(&small, 1000), small);
+
+        let diff = [
+            file_diff("src/api.rs", 200),
+            file_diff("Cargo.lock", 500),
+            file_diff("src/cli.rs", 5),
+        ]
+        .concat();
+        let fitted = fit_diff(&diff, 1500);
+
+        assert!(fitted.chars().count() <= 1500);
+        assert!(fitted.contains("  src/api.rs (+200 -0)\n"));
+        assert!(fitted.contains("  Cargo.lock (+500 -0)\n"));
+        assert!(fitted.contains("more lines]"));
+        assert!(!fitted.contains("diff --git a/Cargo.lock"));
+        // The small file fits completely in what the large one leaves.
+        assert!(fitted.ends_with(&file_diff("src/cli.rs", 5)));
+    }
+
+    #[test]
+    fn test_clean_message() {
+        assert_eq!(clean_message("  feat: add login\n"), "feat: add login");
+        assert_eq!(
+            clean_message("```text\nfix: handle empty input\n\nDetails.\n```"),
+            "fix: handle empty input\n\nDetails."
+        );
+        assert_eq!(clean_message("\"docs: fix typo\""), "docs: fix typo");
+    }
+
+    #[test]
+    fn test_chat_request_and_response() {
+        let prompt = Prompt {
+            instructions: "Write \"it\"".to_string(),
+            diff: "diff --git a/x b/x\n".to_string(),
+            truncated: false,
+        };
+        let request = chat_request("llama3", &prompt);
+        assert_eq!(request["model"], "llama3");
+        assert_eq!(request["messages"][1]["content"], "diff --git a/x b/x\n");
+
+        let body = r#"{"choices":[{"message":{"role":"assistant","content":"feat: add x"}}]}"#;
+        assert_eq!(parse_chat_response(body).unwrap(), "feat: add x");
+        assert!(parse_chat_response(r#"{"choices":[]}"#).is_err());
+        assert_eq!(
+            error_message(r#"{"error":{"message":"Invalid API key"}}"#),
+            "Invalid API key"
+        );
+        assert_eq!(curl_escape("a\"b\\c\n"), "a\\\"b\\\\c\\n");
+    }
+
+    #[test]
+    fn test_generate_checks_the_message() {
+        let repo = MemoryRepo::new();
+        let mut config = Config::default();
+        config
+            .commits
+            .scope_paths
+            .insert("src/**".to_string(), "core".to_string());
+        let ai = AiConfig::default();
+
+        assert!(prompt(&repo, &config, &ai, false).is_err());
+
+        repo.stage(&["src/lib.rs"]);
+        let prompt = prompt(&repo, &config, &ai, false).unwrap();
+        assert!(prompt.diff.contains("src/lib.rs"));
+        assert!(prompt
+            .instructions
+            .contains("- revert: Reverts a previous commit"));
+        assert!(prompt.instructions.contains("the scope 'core'"));
+
+        let provider = |_: &Prompt| Ok("```\nfeat(core): add lib\n```".to_string());
+        let generated = generate(&provider, &prompt, &config).unwrap();
+        assert_eq!(generated.message, "feat(core): add lib");
+        assert!(generated.violations.is_empty());
+
+        let provider = |_: &Prompt| Ok("Added the lib".to_string());
+        let generated = generate(&provider, &prompt, &config).unwrap();
+        assert_eq!(generated.violations[0].rule, "header-format");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_provider() {
+        let provider = CommandProvider {
+            command: r#"test -n "$GWF_AI_PROMPT" && printf 'docs: %s' "$(head -c 4)""#.to_string(),
+        };
+        let prompt = Prompt {
+            instructions: "Write a message".to_string(),
+            diff: "diff --git a/README.md b/README.md\n".to_string(),
+            truncated: false,
+        };
+        assert_eq!(provider.generate(&prompt).unwrap(), "docs: diff");
+
+        let failing = CommandProvider {
+            command: "exit 3".to_string(),
+        };
+        assert!(failing.generate(&prompt).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use super::fetch::{fetch, FetchReporter};
+use crate::config::Config;
+use crate::git::{GitBackend, GitError, WorktreeInfo};
+
+#[derive(Debug, Clone, Default)]
+pub struct CleanupOptions {
+    /// Only report what would be deleted.
+    pub dry_run: bool,
+    /// Fetch `remote.push` afterwards, pruning with `sync.prune_on_fetch`.
+    pub prune_remote: bool,
+}
+
+/// A local branch that is merged into the main branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedBranch {
+    pub name: String,
+    /// The linked worktree it is checked out in, removed along with it.
+    pub worktree: Option<WorktreeInfo>,
+}
+
+#[derive(Debug, Default)]
+pub struct CleanupReport {
+    /// Branches merged into the main branch, except protected branches and
+    /// the current one.
+    pub merged: Vec<MergedBranch>,
+    pub deleted: Vec<String>,
+    pub removed_worktrees: Vec<PathBuf>,
+    /// Branches that were kept because they or their worktree could not be
+    /// removed.
+    pub failed: Vec<(String, GitError)>,
+    /// Whether `remote.push` was fetched.
+    pub pruned: bool,
+}
+
+/// The branches `cleanup` would delete.
+pub fn merged_branches(repo: &dyn GitBackend, config: &Config) -> Result<Vec<MergedBranch>> {
+    // A branch that is being rebased or bisected must not disappear.
+    let state = repo.state()?;
+    state.ensure_idle()?;
+    if state.is_unborn() {
+        return Ok(Vec::new());
+    }
+
+    let worktrees = repo.worktrees()?;
+    let mut merged = Vec::new();
+    for branch in repo.list_branches(false)? {
+        if config.cleanup.protect_branches.contains(&branch) || state.branch() == Some(&branch) {
+            continue;
+        }
+
+        if repo.is_branch_merged(&branch, &config.workflows.main_branch)? {
+            let worktree = worktrees
+                .iter()
+                .find(|worktree| worktree.branch.as_deref() == Some(&branch))
+                .cloned();
+            merged.push(MergedBranch {
+                name: branch,
+                worktree,
+            });
+        }
+    }
+
+    Ok(merged)
+}
+
+/// Deletes the local branches merged into the main branch, and the
+/// worktrees they are checked out in. The remote is only pruned when there
+/// was something to delete.
+pub fn cleanup(
+    repo: &dyn GitBackend,
+    config: &Config,
+    options: &CleanupOptions,
+    reporter: &mut dyn FetchReporter,
+) -> Result<CleanupReport> {
+    let mut report = CleanupReport {
+        merged: merged_branches(repo, config)?,
+        ..Default::default()
+    };
+    if report.merged.is_empty() || options.dry_run {
+        return Ok(report);
+    }
+
+    for branch in &report.merged {
+        if let Some(worktree) = &branch.worktree {
+            match repo.remove_worktree(&worktree.name, false) {
+                Ok(removed) => report.removed_worktrees.push(removed.path),
+                Err(e) => {
+                    report.failed.push((branch.name.clone(), e));
+                    continue;
+                }
+            }
+        }
+
+        match repo.delete_branch(&branch.name) {
+            Ok(()) => report.deleted.push(branch.name.clone()),
+            Err(e) => report.failed.push((branch.name.clone(), e)),
+        }
+    }
+
+    if options.prune_remote {
+        let remote = config.remote.push.as_str();
+        fetch(repo, &[remote], config.sync.prune_on_fetch, reporter)?;
+        report.pruned = true;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{MemoryRepo, Operation};
+    use std::path::Path;
+
+    fn repo() -> MemoryRepo {
+        let repo = MemoryRepo::new();
+        repo.commit_on("main", "Initial commit");
+        repo.create_branch("develop", None).unwrap();
+        repo.create_branch("feat/merged", None).unwrap();
+        repo.create_branch("feat/open", None).unwrap();
+        repo.commit_on("feat/open", "Work in progress");
+        repo
+    }
+
+    fn run(repo: &MemoryRepo, options: CleanupOptions) -> Result<CleanupReport> {
+        cleanup(repo, &Config::default(), &options, &mut ())
+    }
+
+    #[test]
+    fn test_deletes_merged_branches() {
+        let repo = repo();
+
+        let report = run(&repo, CleanupOptions::default()).unwrap();
+
+        assert_eq!(report.deleted, ["feat/merged"]);
+        assert!(repo.branch_tip("feat/merged").is_none());
+        assert!(repo.branch_tip("feat/open").is_some());
+        assert!(repo.branch_tip("develop").is_some());
+        assert!(repo.branch_tip("main").is_some());
+    }
+
+    #[test]
+    fn test_removes_worktrees_of_deleted_branches() {
+        let repo = repo();
+        repo.add_worktree("feat/merged", Path::new("/work/api-feat-merged"))
+            .unwrap();
+        repo.add_worktree("feat/open", Path::new("/work/api-feat-open"))
+            .unwrap();
+
+        let report = run(&repo, CleanupOptions::default()).unwrap();
+
+        assert_eq!(
+            report.removed_worktrees,
+            [PathBuf::from("/work/api-feat-merged")]
+        );
+        assert!(repo.branch_tip("feat/merged").is_none());
+        let remaining: Vec<_> = repo
+            .worktrees()
+            .unwrap()
+            .into_iter()
+            .map(|worktree| worktree.branch)
+            .collect();
+        assert_eq!(remaining, vec![Some("feat/open".to_string())]);
+    }
+
+    #[test]
+    fn test_dry_run_deletes_nothing() {
+        let repo = repo();
+
+        let report = run(
+            &repo,
+            CleanupOptions {
+                dry_run: true,
+                prune_remote: true,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(report.merged.len(), 1);
+        assert!(report.deleted.is_empty());
+        assert!(!report.pruned);
+        assert!(repo.branch_tip("feat/merged").is_some());
+    }
+
+    #[test]
+    fn test_refuses_during_rebase_and_skips_unborn() {
+        let repo = repo();
+        repo.set_operation(Some(Operation::Rebase {
+            branch: Some("feat/merged".to_string()),
+        }));
+
+        let error = run(&repo, CleanupOptions::default()).unwrap_err();
+        assert!(error.to_string().contains("rebase of 'feat/merged'"));
+        assert!(repo.branch_tip("feat/merged").is_some());
+
+        let fresh = MemoryRepo::new();
+        assert!(run(&fresh, CleanupOptions::default())
+            .unwrap()
+            .merged
+            .is_empty());
+    }
+}
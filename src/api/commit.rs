@@ -0,0 +1,172 @@
+use anyhow::Result;
+use git2::Oid;
+use glob::{MatchOptions, Pattern};
+use std::collections::BTreeMap;
+
+use crate::config::Config;
+use crate::git::{CommitOptions, GitBackend};
+
+/// The parts of a Conventional Commits message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    pub kind: String,
+    pub scope: Option<String>,
+    pub description: String,
+    pub body: Option<String>,
+    /// Description of a breaking change, added as a `BREAKING CHANGE:`
+    /// footer.
+    pub breaking: Option<String>,
+}
+
+impl ConventionalCommit {
+    /// The full commit message, `type(scope): description` followed by the
+    /// body and footer.
+    pub fn message(&self) -> String {
+        let mut message = match &self.scope {
+            Some(scope) => format!("{}({}): {}", self.kind, scope, self.description),
+            None => format!("{}: {}", self.kind, self.description),
+        };
+
+        if let Some(body) = &self.body {
+            message.push_str(&format!("\n\n{}", body));
+        }
+
+        if let Some(breaking) = &self.breaking {
+            message.push_str(&format!("\n\nBREAKING CHANGE: {}", breaking));
+        }
+
+        message
+    }
+}
+
+/// Commits the staged changes with `message`, signing as
+/// `commits.sign_commits` says.
+pub fn commit(repo: &dyn GitBackend, config: &Config, message: &str, amend: bool) -> Result<Oid> {
+    let options = CommitOptions {
+        amend,
+        sign: config.commits.sign_commits,
+        signing_key: config.commits.gpg_key.clone(),
+        ..Default::default()
+    };
+
+    Ok(repo.commit(message, &options)?)
+}
+
+/// The scope `commits.scope_paths` gives most of the staged files, if any.
+pub fn staged_scope(repo: &dyn GitBackend, config: &Config) -> Result<Option<String>> {
+    Ok(scope_for_paths(config, &repo.staged_files()?))
+}
+
+/// The scope `commits.scope_paths` gives most of `paths`. Ties go to the
+/// scope that sorts first; paths no glob matches are ignored.
+pub fn scope_for_paths(config: &Config, paths: &[String]) -> Option<String> {
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    let patterns: Vec<(Pattern, &String)> = config
+        .commits
+        .scope_paths
+        .iter()
+        .filter_map(|(glob, scope)| Pattern::new(glob).ok().map(|pattern| (pattern, scope)))
+        .collect();
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for path in paths {
+        let scope = patterns
+            .iter()
+            .filter(|(pattern, _)| pattern.matches_with(path, options))
+            .max_by_key(|(pattern, _)| pattern.as_str().len())
+            .map(|(_, scope)| scope.as_str());
+        if let Some(scope) = scope {
+            *counts.entry(scope).or_default() += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(scope, _)| scope.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::MemoryRepo;
+
+    #[test]
+    fn test_conventional_message() {
+        let mut commit = ConventionalCommit {
+            kind: "feat".to_string(),
+            description: "add login".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(commit.message(), "feat: add login");
+
+        commit.scope = Some("auth".to_string());
+        commit.body = Some("Uses OAuth.".to_string());
+        commit.breaking = Some("sessions are reset".to_string());
+        assert_eq!(
+            commit.message(),
+            "feat(auth): add login\n\nUses OAuth.\n\nBREAKING CHANGE: sessions are reset"
+        );
+    }
+
+    #[test]
+    fn test_scope_for_paths() {
+        let mut config = Config::default();
+        for (glob, scope) in [
+            ("crates/api/**", "api"),
+            ("crates/api/src/db/**", "db"),
+            ("crates/web/**", "web"),
+            ("docs/*.md", "docs"),
+        ] {
+            config
+                .commits
+                .scope_paths
+                .insert(glob.to_string(), scope.to_string());
+        }
+        let scope = |paths: &[&str]| {
+            let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+            scope_for_paths(&config, &paths)
+        };
+
+        assert_eq!(scope(&["crates/api/src/lib.rs"]).as_deref(), Some("api"));
+        assert_eq!(scope(&["crates/api/src/db/pool.rs"]).as_deref(), Some("db"));
+        assert_eq!(
+            scope(&[
+                "crates/web/a.ts",
+                "crates/web/b.ts",
+                "crates/api/c.rs",
+                "README.md"
+            ])
+            .as_deref(),
+            Some("web")
+        );
+        assert_eq!(
+            scope(&["crates/web/a.ts", "crates/api/c.rs"]).as_deref(),
+            Some("api")
+        );
+        assert_eq!(scope(&["docs/guide/setup.md"]), None);
+        assert_eq!(scope(&[]), None);
+    }
+
+    #[test]
+    fn test_staged_scope() {
+        let repo = MemoryRepo::new();
+        let mut config = Config::default();
+        config
+            .commits
+            .scope_paths
+            .insert("crates/api/**".to_string(), "api".to_string());
+
+        repo.stage(&["crates/api/src/routes.rs", "Cargo.lock"]);
+        assert_eq!(
+            staged_scope(&repo, &config).unwrap().as_deref(),
+            Some("api")
+        );
+        repo.stage(&["Cargo.lock"]);
+        assert_eq!(staged_scope(&repo, &config).unwrap(), None);
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+
+use super::worktree::{start_branch, Checkout, StartedBranch};
+use crate::config::Config;
+use crate::git::{GitBackend, PushOptions};
+
+#[derive(Debug, Clone, Default)]
+pub struct FeatureOptions {
+    /// Name of the feature, without `workflows.feature_branch_prefix`.
+    pub name: String,
+    /// Branch to start from instead of the develop or main branch.
+    pub from: Option<String>,
+    /// Push the branch to `remote.push` and track it.
+    pub push: bool,
+    pub checkout: Checkout,
+}
+
+#[derive(Debug)]
+pub struct FeatureReport {
+    pub branch: StartedBranch,
+    /// The remote the branch was pushed to.
+    pub pushed_to: Option<String>,
+}
+
+/// Creates a feature branch from the develop branch, or the main branch
+/// without one, and checks it out.
+pub fn start_feature(
+    repo: &mut dyn GitBackend,
+    config: &Config,
+    options: &FeatureOptions,
+) -> Result<FeatureReport> {
+    let base = options.from.clone().unwrap_or_else(|| {
+        config
+            .workflows
+            .develop_branch
+            .clone()
+            .unwrap_or_else(|| config.workflows.main_branch.clone())
+    });
+    let name = format!("{}{}", config.workflows.feature_branch_prefix, options.name);
+    let branch = start_branch(repo, config, name, base, &options.checkout)?;
+
+    let mut pushed_to = None;
+    if options.push {
+        let remote = &config.remote.push;
+        repo.push(
+            remote,
+            &[format!("refs/heads/{}", branch.name)],
+            &PushOptions {
+                set_upstream: true,
+                ..Default::default()
+            },
+        )?;
+        pushed_to = Some(remote.clone());
+    }
+
+    Ok(FeatureReport { branch, pushed_to })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::CheckedOut;
+    use crate::git::MemoryRepo;
+
+    #[test]
+    fn test_starts_from_develop_and_pushes() {
+        let mut repo = MemoryRepo::new();
+        repo.add_remote("origin", "git@example.com:acme/api.git");
+        repo.commit_on("main", "Initial commit");
+        repo.create_branch("develop", None).unwrap();
+        let config = Config::default();
+
+        let report = start_feature(
+            &mut repo,
+            &config,
+            &FeatureOptions {
+                name: "login".to_string(),
+                push: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let branch = format!("{}login", config.workflows.feature_branch_prefix);
+        assert_eq!(report.branch.name, branch);
+        assert_eq!(report.branch.base, "develop");
+        assert!(matches!(report.branch.checkout, CheckedOut::Switched(_)));
+        assert_eq!(repo.current_branch().unwrap(), branch);
+        assert_eq!(report.pushed_to.as_deref(), Some("origin"));
+        assert_eq!(repo.upstream(&branch), Some(format!("origin/{}", branch)));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::git::{FetchProgress, GitBackend, Result};
+
+/// Told about the fetches an operation runs, e.g. to draw progress bars.
+/// Every method does nothing by default, and `()` ignores fetches.
+pub trait FetchReporter {
+    fn started(&mut self, _remote: &str) {}
+    fn progress(&mut self, _remote: &str, _progress: FetchProgress) {}
+    fn finished(&mut self, _remote: &str, _result: &Result<()>) {}
+}
+
+impl FetchReporter for () {}
+
+/// Fetches each of `remotes` in turn, stopping at the first that fails.
+pub fn fetch(
+    repo: &dyn GitBackend,
+    remotes: &[&str],
+    prune: bool,
+    reporter: &mut dyn FetchReporter,
+) -> Result<()> {
+    for &remote in remotes {
+        reporter.started(remote);
+        let result = repo.fetch(remote, prune, &mut |progress: FetchProgress| {
+            reporter.progress(remote, progress)
+        });
+        reporter.finished(remote, &result);
+        result?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{GitError, MemoryRepo};
+
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl FetchReporter for Recorder {
+        fn started(&mut self, remote: &str) {
+            self.0.push(format!("start {}", remote));
+        }
+
+        fn finished(&mut self, remote: &str, result: &Result<()>) {
+            self.0.push(format!("{} {}", remote, result.is_ok()));
+        }
+    }
+
+    #[test]
+    fn test_fetch_stops_at_missing_remote() {
+        let repo = MemoryRepo::new();
+        repo.add_remote("origin", "git@example.com:acme/api.git");
+        let mut recorder = Recorder::default();
+
+        let result = fetch(&repo, &["origin", "upstream", "fork"], false, &mut recorder);
+
+        assert!(matches!(
+            result,
+            Err(GitError::RemoteNotFound(remote)) if remote == "upstream"
+        ));
+        assert_eq!(
+            recorder.0,
+            [
+                "start origin",
+                "origin true",
+                "start upstream",
+                "upstream false"
+            ]
+        );
+    }
+}
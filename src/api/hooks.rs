@@ -0,0 +1,422 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use super::lint::lint_message;
+use crate::config::{Config, HooksConfig};
+use crate::git::commit::is_executable;
+use crate::git::GitRepo;
+
+/// First line after the shebang of every hook gwf installs; hooks without
+/// it belong to someone else and are chained instead of replaced.
+const SHIM_MARKER: &str = "# Installed by gwf";
+
+/// Suffix an existing hook is renamed with when gwf installs its own.
+const CHAINED_SUFFIX: &str = ".pre-gwf";
+
+/// Hooks git feeds data on stdin, which `run_hook` passes on to every
+/// command it runs.
+pub const STDIN_HOOKS: &[&str] = &["pre-push"];
+
+/// What `install_hooks` or `uninstall_hooks` changed.
+#[derive(Debug, Default)]
+pub struct HookChanges {
+    /// The directory the hooks live in.
+    pub dir: PathBuf,
+    /// `core.hooksPath` as set or unset by this change, from `hooks.path`.
+    pub hooks_path: Option<String>,
+    /// Hooks that now dispatch to `gwf hooks run`.
+    pub installed: Vec<&'static str>,
+    /// Existing hooks that were kept and now run before gwf's commands.
+    pub chained: Vec<&'static str>,
+    /// Hooks gwf no longer handles, because they were uninstalled or have
+    /// no commands configured anymore.
+    pub removed: Vec<&'static str>,
+    /// Chained hooks put back in place of gwf's.
+    pub restored: Vec<&'static str>,
+}
+
+/// Installs a shim for every hook with commands in `[hooks]`, pointing
+/// `core.hooksPath` at `hooks.path` first if it is set. Existing hooks are
+/// renamed and run by the shim before the configured commands. This works on
+/// a `GitRepo`, since it writes into the repository's hooks directory.
+pub fn install_hooks(repo: &GitRepo, config: &Config) -> Result<HookChanges> {
+    let mut changes = HookChanges::default();
+    if let Some(path) = &config.hooks.path {
+        repo.set_config_string("core.hooksPath", path)?;
+        changes.hooks_path = Some(path.clone());
+    }
+    changes.dir = repo.hooks_dir();
+    fs::create_dir_all(&changes.dir)
+        .with_context(|| format!("Failed to create {}", changes.dir.display()))?;
+
+    for &hook in HooksConfig::NAMES {
+        let path = changes.dir.join(hook);
+        let configured = !config.hooks.commands(hook).unwrap_or_default().is_empty();
+        if !configured {
+            if is_shim(&path) {
+                remove_shim(&path, hook, &mut changes)?;
+            }
+            continue;
+        }
+
+        if path.exists() && !is_shim(&path) {
+            let chained = chained_path(&path);
+            if chained.exists() {
+                bail!(
+                    "Both {} and {} exist; remove one before installing gwf's hook",
+                    path.display(),
+                    chained.display()
+                );
+            }
+            fs::rename(&path, &chained)?;
+            changes.chained.push(hook);
+        }
+        write_shim(&path, hook)?;
+        changes.installed.push(hook);
+    }
+
+    Ok(changes)
+}
+
+/// Removes gwf's hooks and puts back the hooks they chained. `core.hooksPath`
+/// is unset if it still points at `hooks.path`.
+pub fn uninstall_hooks(repo: &GitRepo, config: &Config) -> Result<HookChanges> {
+    let mut changes = HookChanges {
+        dir: repo.hooks_dir(),
+        ..Default::default()
+    };
+
+    for &hook in HooksConfig::NAMES {
+        let path = changes.dir.join(hook);
+        if is_shim(&path) {
+            remove_shim(&path, hook, &mut changes)?;
+        }
+    }
+
+    if let Some(path) = &config.hooks.path {
+        if repo.config_string("core.hooksPath").as_ref() == Some(path) {
+            repo.unset_config("core.hooksPath")?;
+            changes.hooks_path = Some(path.clone());
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Runs the hook git invoked as `hook`: first any chained hook, then the
+/// commands in `[hooks]`, stopping at the first that fails. `stdin` is what
+/// git passed to the hook.
+pub fn run_hook(
+    repo: &GitRepo,
+    config: &Config,
+    hook: &str,
+    args: &[String],
+    stdin: &[u8],
+) -> Result<()> {
+    let Some(commands) = config.hooks.commands(hook) else {
+        bail!(
+            "gwf does not manage the {} hook; it handles {}",
+            hook,
+            HooksConfig::NAMES.join(", ")
+        );
+    };
+    let workdir = repo.workdir()?;
+
+    for chained in chained_hooks(repo, hook) {
+        let mut command = Command::new(&chained);
+        command.args(args).current_dir(&workdir);
+        if !run_command(command, stdin)? {
+            bail!("The existing {} hook ({}) failed", hook, chained.display());
+        }
+    }
+
+    for entry in commands {
+        match entry.as_str() {
+            "gwf:lint" => lint_message_file(config, args)?,
+            "gwf:protect-branches" => {
+                let blocked = protected_pushes(&String::from_utf8_lossy(stdin), config);
+                if !blocked.is_empty() {
+                    bail!(
+                        "Pushing to protected branch {} is blocked; open a pull request instead \
+                         (or push with --no-verify)",
+                        blocked.join(", ")
+                    );
+                }
+            }
+            "gwf:template" => prefill_message_file(repo, config, args)?,
+            builtin if builtin.starts_with("gwf:") => {
+                bail!("Unknown built-in hook action '{}'", builtin)
+            }
+            shell => {
+                let mut command = Command::new("sh");
+                command
+                    .arg("-c")
+                    .arg(shell)
+                    .arg(hook)
+                    .args(args)
+                    .current_dir(&workdir);
+                if !run_command(command, stdin)? {
+                    bail!("'{}' failed in the {} hook", shell, hook);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Branches in `protect_branches` that a pre-push hook's input would update
+/// or delete. Each input line is `<local ref> <local oid> <remote ref>
+/// <remote oid>`.
+pub fn protected_pushes(input: &str, config: &Config) -> Vec<String> {
+    input
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(2))
+        .filter_map(|remote_ref| remote_ref.strip_prefix("refs/heads/"))
+        .filter(|branch| config.cleanup.protect_branches.iter().any(|p| p == branch))
+        .map(String::from)
+        .collect()
+}
+
+/// The message `gwf:template` starts a commit on `branch` with, if any.
+pub fn message_template(config: &Config, branch: &str) -> Option<String> {
+    let workflows = &config.workflows;
+    let prefixes = [
+        (&workflows.feature_branch_prefix, "feat: "),
+        (&workflows.hotfix_branch_prefix, "fix: "),
+        (&workflows.release_branch_prefix, ""),
+    ];
+    let matched = prefixes
+        .iter()
+        .find(|(prefix, _)| !prefix.is_empty() && branch.starts_with(prefix.as_str()));
+    let name = matched.map_or(branch, |(prefix, _)| &branch[prefix.len()..]);
+
+    match &config.hooks.template {
+        Some(template) => Some(template.replace("{branch}", branch).replace("{name}", name)),
+        None => matched
+            .map(|(_, start)| start.to_string())
+            .filter(|start| !start.is_empty()),
+    }
+}
+
+/// Puts `template` in front of a commit message file's contents unless the
+/// message already has text, e.g. from `-m` or a merge. Git's comment lines
+/// are kept below it.
+pub fn prefill(contents: &str, template: &str) -> Option<String> {
+    let has_message = contents
+        .lines()
+        .any(|line| !line.trim().is_empty() && !line.starts_with('#'));
+    (!has_message).then(|| format!("{}\n{}", template, contents))
+}
+
+fn lint_message_file(config: &Config, args: &[String]) -> Result<()> {
+    let Some(file) = args.first() else {
+        bail!("gwf:lint expects the commit message file as its argument");
+    };
+    let message = fs::read_to_string(file).with_context(|| format!("Failed to read {}", file))?;
+
+    let violations = lint_message(&message, config);
+    if !violations.is_empty() {
+        let details: Vec<String> = violations
+            .iter()
+            .map(|violation| format!("  {}: {}", violation.rule, violation.message))
+            .collect();
+        bail!(
+            "The commit message breaks the commit rules:\n{}",
+            details.join("\n")
+        );
+    }
+    Ok(())
+}
+
+fn prefill_message_file(repo: &GitRepo, config: &Config, args: &[String]) -> Result<()> {
+    let Some(file) = args.first() else {
+        bail!("gwf:template expects the commit message file as its argument");
+    };
+    // A source means the message came from -m, -F, a template, a merge,
+    // a squash or an existing commit.
+    if args.get(1).is_some_and(|source| !source.is_empty()) {
+        return Ok(());
+    }
+    let Ok(branch) = repo.current_branch() else {
+        return Ok(());
+    };
+    let Some(template) = message_template(config, &branch) else {
+        return Ok(());
+    };
+
+    let contents = fs::read_to_string(file).unwrap_or_default();
+    if let Some(contents) = prefill(&contents, &template) {
+        fs::write(file, contents).with_context(|| format!("Failed to write {}", file))?;
+    }
+    Ok(())
+}
+
+/// Hooks that ran before gwf took over: the renamed hook next to the shim
+/// and, when `core.hooksPath` points elsewhere, the one in `.git/hooks`
+/// that git no longer runs by itself.
+fn chained_hooks(repo: &GitRepo, hook: &str) -> Vec<PathBuf> {
+    let dir = repo.hooks_dir();
+    let mut hooks = vec![chained_path(&dir.join(hook))];
+    let default_dir = repo.default_hooks_dir();
+    if default_dir != dir {
+        hooks.push(default_dir.join(hook));
+    }
+    hooks
+        .into_iter()
+        .filter(|path| is_executable(path) && !is_shim(path))
+        .collect()
+}
+
+fn run_command(mut command: Command, stdin: &[u8]) -> Result<bool> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if let Some(mut pipe) = child.stdin.take() {
+        // Commands that do not read their input close the pipe early.
+        match pipe.write_all(stdin) {
+            Err(error) if error.kind() != ErrorKind::BrokenPipe => return Err(error.into()),
+            _ => {}
+        }
+    }
+    Ok(child.wait()?.success())
+}
+
+fn chained_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(CHAINED_SUFFIX);
+    path.with_file_name(name)
+}
+
+fn is_shim(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|contents| contents.contains(SHIM_MARKER))
+}
+
+fn write_shim(path: &Path, hook: &str) -> Result<()> {
+    let shim = format!(
+        "#!/bin/sh\n{}; `gwf hooks uninstall` restores the previous hook.\nexec gwf hooks run {} \"$@\"\n",
+        SHIM_MARKER, hook
+    );
+    fs::write(path, shim).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+fn remove_shim(path: &Path, hook: &'static str, changes: &mut HookChanges) -> Result<()> {
+    fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    changes.removed.push(hook);
+
+    let chained = chained_path(path);
+    if chained.exists() {
+        fs::rename(&chained, path)?;
+        changes.restored.push(hook);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_protected_pushes() {
+        let config = Config::default();
+        let input = "refs/heads/feature/x 1111 refs/heads/feature/x 0000\n\
+                     refs/heads/main 2222 refs/heads/main 3333\n\
+                     (delete) 0000 refs/heads/develop 4444\n\
+                     refs/tags/v1.0.0 5555 refs/tags/v1.0.0 0000\n";
+
+        assert_eq!(protected_pushes(input, &config), ["main", "develop"]);
+        assert!(protected_pushes("", &config).is_empty());
+    }
+
+    #[test]
+    fn test_message_template() {
+        let mut config = Config::default();
+        assert_eq!(
+            message_template(&config, "feature/login").as_deref(),
+            Some("feat: ")
+        );
+        assert_eq!(
+            message_template(&config, "hotfix/crash").as_deref(),
+            Some("fix: ")
+        );
+        assert_eq!(message_template(&config, "main"), None);
+
+        config.hooks.template = Some("feat({name}): \n\nBranch: {branch}".to_string());
+        assert_eq!(
+            message_template(&config, "feature/login").as_deref(),
+            Some("feat(login): \n\nBranch: feature/login")
+        );
+    }
+
+    #[test]
+    fn test_prefill_keeps_existing_messages() {
+        let comments = "\n# Please enter the commit message for your changes.\n";
+        assert_eq!(
+            prefill(comments, "feat: ").as_deref(),
+            Some("feat: \n\n# Please enter the commit message for your changes.\n")
+        );
+        assert_eq!(prefill("fix: typo\n", "feat: "), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_chains_and_uninstall_restores() {
+        let dir = TempDir::new().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        let repo = GitRepo::open(dir.path()).unwrap();
+        let hooks = repo.hooks_dir();
+        fs::create_dir_all(&hooks).unwrap();
+        let existing = "#!/bin/sh\necho existing >> \"$1.log\"\n";
+        fs::write(hooks.join("commit-msg"), existing).unwrap();
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(hooks.join("commit-msg"), fs::Permissions::from_mode(0o755))
+                .unwrap();
+        }
+
+        let mut config = Config::default();
+        config.hooks.commit_msg = vec!["gwf:lint".to_string(), "test -s \"$1\"".to_string()];
+        let installed = install_hooks(&repo, &config).unwrap();
+        assert_eq!(
+            installed.installed,
+            ["prepare-commit-msg", "commit-msg", "pre-push"]
+        );
+        assert_eq!(installed.chained, ["commit-msg"]);
+        assert!(is_shim(&hooks.join("commit-msg")));
+
+        // Installing again leaves the chained hook alone.
+        let again = install_hooks(&repo, &config).unwrap();
+        assert!(again.chained.is_empty());
+
+        let message = dir.path().join("MSG");
+        fs::write(&message, "feat: add hooks\n").unwrap();
+        let args = [message.to_string_lossy().into_owned()];
+        run_hook(&repo, &config, "commit-msg", &args, b"").unwrap();
+        assert!(dir.path().join("MSG.log").exists());
+
+        fs::write(&message, "Add hooks\n").unwrap();
+        let error = run_hook(&repo, &config, "commit-msg", &args, b"").unwrap_err();
+        assert!(error.to_string().contains("header-format"));
+
+        let removed = uninstall_hooks(&repo, &config).unwrap();
+        assert_eq!(removed.restored, ["commit-msg"]);
+        assert_eq!(
+            fs::read_to_string(hooks.join("commit-msg")).unwrap(),
+            existing
+        );
+        assert!(!hooks.join("pre-push").exists());
+    }
+}
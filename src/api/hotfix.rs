@@ -0,0 +1,29 @@
+use anyhow::Result;
+
+use super::worktree::{start_branch, Checkout, StartedBranch};
+use crate::config::Config;
+use crate::git::GitBackend;
+
+#[derive(Debug, Clone, Default)]
+pub struct HotfixOptions {
+    /// Name of the fix, without `workflows.hotfix_branch_prefix`.
+    pub name: String,
+    /// Branch to fix instead of the main branch.
+    pub target: Option<String>,
+    pub checkout: Checkout,
+}
+
+/// Creates a hotfix branch from the main branch and checks it out.
+pub fn start_hotfix(
+    repo: &mut dyn GitBackend,
+    config: &Config,
+    options: &HotfixOptions,
+) -> Result<StartedBranch> {
+    let base = options
+        .target
+        .clone()
+        .unwrap_or_else(|| config.workflows.main_branch.clone());
+    let name = format!("{}{}", config.workflows.hotfix_branch_prefix, options.name);
+
+    start_branch(repo, config, name, base, &options.checkout)
+}
@@ -0,0 +1,338 @@
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+use crate::config::{Config, SubjectCase};
+use crate::git::{GitBackend, LogOptions};
+
+static HEADER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?P<type>[A-Za-z]+)(?:\((?P<scope>[^()]*)\))?(?P<breaking>!)?: (?P<subject>\S.*)$",
+    )
+    .expect("valid regex")
+});
+/// `Token: value` or `Token #value`, where only `BREAKING CHANGE` may
+/// contain a space.
+static FOOTER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:BREAKING CHANGE|BREAKING-CHANGE|[A-Za-z][A-Za-z0-9-]*)(?:: | #)\S")
+        .expect("valid regex")
+});
+/// A line that is meant as a footer but is not one, e.g. `Reviewed by: Ann`
+/// or `Refs:#12`.
+static MALFORMED_FOOTER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:[A-Z][A-Za-z0-9-]*(?: [A-Za-z0-9-]+){1,2}(?:: | #)\S|[A-Za-z][A-Za-z0-9-]*:[^\s/])",
+    )
+    .expect("valid regex")
+});
+static BREAKING_MENTION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^breaking[ _-]?changes?\b").expect("valid regex"));
+static BREAKING_FOOTER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^BREAKING[ -]CHANGE: \S").expect("valid regex"));
+
+/// A rule a commit message breaks. Rules are named like commitlint's.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl Violation {
+    fn new(rule: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            rule,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LintedCommit {
+    pub id: String,
+    pub summary: String,
+    pub violations: Vec<Violation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LintReport {
+    /// The revision range that was checked.
+    pub range: String,
+    /// Every checked commit, newest first.
+    pub commits: Vec<LintedCommit>,
+}
+
+impl LintReport {
+    /// The commits that break at least one rule.
+    pub fn failures(&self) -> impl Iterator<Item = &LintedCommit> {
+        self.commits
+            .iter()
+            .filter(|commit| !commit.violations.is_empty())
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+/// The commit types `lint.types` allows.
+pub fn allowed_types(config: &Config) -> Vec<String> {
+    match &config.lint.types {
+        Some(types) => types.clone(),
+        None => config
+            .commits
+            .types
+            .iter()
+            .map(|kind| kind.name.clone())
+            .collect(),
+    }
+}
+
+/// The scopes `lint.scopes` allows; any scope when empty.
+pub fn allowed_scopes(config: &Config) -> &[String] {
+    config
+        .lint
+        .scopes
+        .as_deref()
+        .unwrap_or(&config.commits.scopes)
+}
+
+/// Checks the commits in `range`, by default those on the current branch
+/// that are not on the main branch. Merge commits and the messages git
+/// generates for fixups, squashes and reverts are skipped.
+pub fn lint_commits(
+    repo: &dyn GitBackend,
+    config: &Config,
+    range: Option<&str>,
+) -> Result<LintReport> {
+    let range = range
+        .map(String::from)
+        .unwrap_or_else(|| format!("{}..HEAD", config.workflows.main_branch));
+    let commits = repo.log(&LogOptions {
+        range: Some(range.clone()),
+        no_merges: true,
+        ..Default::default()
+    })?;
+
+    let commits = commits
+        .into_iter()
+        .filter(|commit| !is_generated(&commit.message))
+        .map(|commit| LintedCommit {
+            id: commit.id.to_string(),
+            violations: lint_message(&commit.message, config),
+            summary: commit.summary,
+        })
+        .collect();
+
+    Ok(LintReport { range, commits })
+}
+
+fn is_generated(message: &str) -> bool {
+    ["fixup! ", "squash! ", "amend! ", "Merge ", "Revert \""]
+        .iter()
+        .any(|prefix| message.starts_with(prefix))
+}
+
+/// Checks one commit message against the `[lint]` rules. Comment lines,
+/// as left in the file a `commit-msg` hook receives, are ignored.
+pub fn lint_message(message: &str, config: &Config) -> Vec<Violation> {
+    let rules = &config.lint;
+    let mut lines: Vec<&str> = message
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(str::trim_end)
+        .collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    let header = lines.first().copied().unwrap_or_default();
+    let mut violations = Vec::new();
+
+    let length = header.chars().count();
+    if rules.header_max_length > 0 && length > rules.header_max_length {
+        violations.push(Violation::new(
+            "header-max-length",
+            format!(
+                "the first line has {} characters, more than {}",
+                length, rules.header_max_length
+            ),
+        ));
+    }
+
+    match HEADER.captures(header) {
+        None => violations.push(Violation::new(
+            "header-format",
+            "the first line must be 'type(scope): description'",
+        )),
+        Some(captures) => {
+            let kind = &captures["type"];
+            let types = allowed_types(config);
+            if !types.iter().any(|allowed| allowed == kind) {
+                violations.push(Violation::new(
+                    "type-enum",
+                    format!("type '{}' is not one of {}", kind, types.join(", ")),
+                ));
+            }
+
+            let scopes = allowed_scopes(config);
+            if let Some(scope) = captures.name("scope") {
+                if !scopes.is_empty() {
+                    for scope in scope.as_str().split(',').map(str::trim) {
+                        if !scopes.iter().any(|allowed| allowed == scope) {
+                            violations.push(Violation::new(
+                                "scope-enum",
+                                format!("scope '{}' is not one of {}", scope, scopes.join(", ")),
+                            ));
+                        }
+                    }
+                }
+            }
+
+            let first = captures["subject"].chars().next().unwrap_or_default();
+            let expected = match rules.subject_case {
+                SubjectCase::Lower if first.is_uppercase() => Some("a lowercase"),
+                SubjectCase::Sentence if first.is_lowercase() => Some("an uppercase"),
+                _ => None,
+            };
+            if let Some(expected) = expected {
+                violations.push(Violation::new(
+                    "subject-case",
+                    format!("the description must start with {} letter", expected),
+                ));
+            }
+        }
+    }
+
+    if rules.body_leading_blank && lines.get(1).is_some_and(|line| !line.is_empty()) {
+        violations.push(Violation::new(
+            "body-leading-blank",
+            "the body must be separated from the first line by a blank line",
+        ));
+    }
+
+    // The footer is the last paragraph, if it starts with a footer token.
+    let paragraphs: Vec<&[&str]> = lines
+        .get(1..)
+        .unwrap_or_default()
+        .split(|line| line.is_empty())
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect();
+    let footer = paragraphs
+        .last()
+        .filter(|paragraph| FOOTER.is_match(paragraph[0]))
+        .copied()
+        .unwrap_or_default();
+
+    if rules.footer_format {
+        for line in footer {
+            if !FOOTER.is_match(line) && MALFORMED_FOOTER.is_match(line) {
+                violations.push(Violation::new(
+                    "footer-format",
+                    format!(
+                        "'{}' must be 'Token: value' or 'Token #value', with '-' instead of spaces in the token",
+                        line
+                    ),
+                ));
+            }
+        }
+    }
+
+    for line in lines.iter().skip(1) {
+        if !BREAKING_MENTION.is_match(line) {
+            continue;
+        }
+        if !BREAKING_FOOTER.is_match(line) {
+            violations.push(Violation::new(
+                "breaking-change",
+                format!(
+                    "'{}' must be written 'BREAKING CHANGE: <description>'",
+                    line
+                ),
+            ));
+        } else if !footer.contains(line) {
+            violations.push(Violation::new(
+                "breaking-change",
+                "BREAKING CHANGE must be a footer, after a blank line at the end of the message",
+            ));
+        }
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::MemoryRepo;
+
+    fn rules(message: &str, config: &Config) -> Vec<&'static str> {
+        lint_message(message, config)
+            .into_iter()
+            .map(|violation| violation.rule)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_messages() {
+        let config = Config::default();
+        for message in [
+            "feat: add login",
+            "fix(api)!: reject empty tokens\n\nTokens were accepted.\n",
+            "feat(auth): add SSO\n\nUses SAML.\n\nBREAKING CHANGE: sessions are reset\nRefs #12\nReviewed-by: Ann",
+            "docs: explain setup\n# Please enter the commit message\n",
+        ] {
+            assert_eq!(rules(message, &config), Vec::<&str>::new(), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_rule_violations() {
+        let mut config = Config::default();
+        config.commits.scopes = vec!["api".to_string()];
+        config.lint.header_max_length = 20;
+
+        assert_eq!(rules("Add login", &config), ["header-format"]);
+        assert_eq!(rules("feature: add", &config), ["type-enum"]);
+        assert_eq!(rules("ci(api): cache", &config), Vec::<&str>::new());
+        assert_eq!(rules("fix(web): x", &config), ["scope-enum"]);
+        assert_eq!(
+            rules("fix: Add a longer message", &config),
+            ["header-max-length", "subject-case"]
+        );
+        assert_eq!(rules("fix: a\nbody", &config), ["body-leading-blank"]);
+        assert_eq!(
+            rules("fix: a\n\nbody\n\nRefs #1\nReviewed by: Ann", &config),
+            ["footer-format"]
+        );
+        assert_eq!(
+            rules("fix: a\n\nbreaking change: removes b", &config),
+            ["breaking-change"]
+        );
+        assert_eq!(
+            rules("fix: a\n\nRemoves b.\nBREAKING CHANGE: removes b", &config),
+            ["breaking-change"]
+        );
+
+        config.lint.subject_case = SubjectCase::Sentence;
+        assert_eq!(rules("fix: a", &config), ["subject-case"]);
+    }
+
+    #[test]
+    fn test_lint_commits_on_branch() {
+        let repo = MemoryRepo::new();
+        repo.commit_on("main", "Initial commit");
+        repo.create_branch("feature/login", None).unwrap();
+        repo.checkout("feature/login").unwrap();
+        repo.commit_on("feature/login", "feat: add login");
+        repo.commit_on("feature/login", "WIP");
+        repo.commit_on("feature/login", "fixup! feat: add login");
+
+        let report = lint_commits(&repo, &Config::default(), None).unwrap();
+
+        assert_eq!(report.range, "main..HEAD");
+        assert_eq!(report.commits.len(), 2);
+        let failures: Vec<_> = report.failures().map(|commit| &commit.summary).collect();
+        assert_eq!(failures, ["WIP"]);
+        assert!(!report.passed());
+    }
+}
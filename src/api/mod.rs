@@ -0,0 +1,49 @@
+//! gwf's commands as library functions. Each operation works on a
+//! [`GitBackend`](crate::git::GitBackend) and the [`Config`](crate::config::Config)
+//! the caller loaded, and returns a typed report instead of printing; the
+//! `gwf` binary only prompts and prints.
+//!
+//! Managing git hooks writes into the repository's hooks directory, so
+//! those functions take a [`GitRepo`](crate::git::GitRepo) instead.
+//!
+//! Commands that only prompt or edit configuration (`init`, `config`,
+//! `completions`) and `bisect`, whose steps are single backend calls, have
+//! no counterpart here.
+
+pub mod ai;
+pub mod cleanup;
+pub mod commit;
+pub mod feature;
+pub mod fetch;
+pub mod hooks;
+pub mod hotfix;
+pub mod lint;
+pub mod pr;
+pub mod release;
+pub mod standup;
+pub mod stash;
+pub mod stats;
+pub mod status;
+pub mod sync;
+pub mod tag;
+pub mod undo;
+pub mod worktree;
+
+pub use ai::{GeneratedMessage, MessageProvider, Prompt};
+pub use cleanup::{cleanup, CleanupOptions, CleanupReport, MergedBranch};
+pub use commit::{commit, scope_for_paths, staged_scope, ConventionalCommit};
+pub use feature::{start_feature, FeatureOptions, FeatureReport};
+pub use fetch::{fetch, FetchReporter};
+pub use hooks::{install_hooks, run_hook, uninstall_hooks, HookChanges};
+pub use hotfix::{start_hotfix, HotfixOptions};
+pub use lint::{lint_commits, lint_message, LintReport, LintedCommit, Violation};
+pub use pr::{pull_request, pull_request_branch, PullRequest, PullRequestOptions};
+pub use release::{start_release, ReleaseOptions, ReleaseReport};
+pub use standup::{standup, StandupOptions, StandupReport};
+pub use stash::{BranchSwitch, StashRestore};
+pub use stats::{stats, RepoStats};
+pub use status::{status, Divergence, StatusReport};
+pub use sync::{sync, BaseBranchUpdate, BranchSync, SyncOptions, SyncReport};
+pub use tag::{create_tag, latest_version, Bump, TagOptions, TagReport};
+pub use undo::{undo, UndoAction};
+pub use worktree::{check_out, CheckedOut, Checkout, StartedBranch};
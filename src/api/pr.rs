@@ -0,0 +1,191 @@
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+use crate::config::Config;
+use crate::git::{GitBackend, HeadState, PushOptions};
+
+#[derive(Debug, Clone, Default)]
+pub struct PullRequestOptions {
+    pub title: String,
+    /// Branch to merge into instead of the main branch.
+    pub target: Option<String>,
+    pub draft: bool,
+}
+
+/// How the pull request was opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PullRequest {
+    /// Created with the GitHub CLI.
+    Created { url: String },
+    /// The GitHub CLI is not installed; the pull request can be created on
+    /// this compare page.
+    Compare { url: String },
+}
+
+/// The current branch, if a pull request can be opened from it.
+pub fn pull_request_branch(repo: &dyn GitBackend, config: &Config) -> Result<String> {
+    let state = repo.state()?;
+    state.ensure_idle()?;
+    let branch = match state.head {
+        HeadState::Branch(branch) => branch,
+        HeadState::Unborn(branch) => {
+            bail!(
+                "Branch '{}' has no commits yet; commit before opening a pull request",
+                branch
+            )
+        }
+        HeadState::Detached(_) => {
+            bail!("HEAD is detached; check out the branch to open a pull request from")
+        }
+    };
+
+    if branch == config.workflows.main_branch {
+        bail!("Cannot create PR from main branch");
+    }
+
+    Ok(branch)
+}
+
+/// Pushes the current branch to `remote.push` and opens a pull request
+/// from it against the upstream repository.
+pub fn pull_request(
+    repo: &dyn GitBackend,
+    config: &Config,
+    options: &PullRequestOptions,
+) -> Result<PullRequest> {
+    let current_branch = pull_request_branch(repo, config)?;
+    let target_branch = options
+        .target
+        .clone()
+        .unwrap_or_else(|| config.workflows.main_branch.clone());
+
+    let remotes = &config.remote;
+    repo.push(
+        &remotes.push,
+        &[format!("refs/heads/{}", current_branch)],
+        &PushOptions {
+            set_upstream: true,
+            ..Default::default()
+        },
+    )?;
+
+    // The pull request lives in the upstream repository; from a fork its
+    // head branch is named `owner:branch`.
+    let base_repo = repository_path(&repo.remote_url(&remotes.upstream)?);
+    let head = if remotes.is_fork() {
+        let fork = repository_path(&repo.remote_url(&remotes.push)?)
+            .with_context(|| format!("Cannot tell the owner of remote '{}'", remotes.push))?;
+        format!("{}:{}", fork.owner(), current_branch)
+    } else {
+        current_branch.clone()
+    };
+
+    // FIXME: add support for GitLab and Bitbucket
+    if which::which("gh").is_ok() {
+        let mut cmd = Command::new("gh");
+        cmd.arg("pr")
+            .arg("create")
+            .arg("--title")
+            .arg(&options.title)
+            .arg("--base")
+            .arg(&target_branch)
+            .arg("--head")
+            .arg(&head);
+        if let Some(base_repo) = &base_repo {
+            cmd.arg("--repo").arg(&base_repo.path);
+        }
+
+        if options.draft {
+            cmd.arg("--draft");
+        }
+
+        let output = cmd
+            .output()
+            .context("Failed to create PR with GitHub CLI")?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            bail!("Failed to create PR: {}", error);
+        }
+        let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return Ok(PullRequest::Created { url });
+    }
+
+    let base_repo = base_repo.with_context(|| {
+        format!(
+            "Cannot tell the repository of remote '{}'; install the GitHub CLI",
+            remotes.upstream
+        )
+    })?;
+    let url = format!(
+        "https://{}/{}/compare/{}...{}?expand=1&title={}",
+        base_repo.host,
+        base_repo.path,
+        target_branch,
+        head,
+        urlencoding::encode(&options.title)
+    );
+
+    Ok(PullRequest::Compare { url })
+}
+
+/// Where a remote's repository lives, e.g. host `github.com` and path
+/// `acme/api`.
+#[derive(Debug, PartialEq, Eq)]
+struct RepositoryPath {
+    host: String,
+    path: String,
+}
+
+impl RepositoryPath {
+    fn owner(&self) -> &str {
+        self.path.split('/').next().unwrap_or(&self.path)
+    }
+}
+
+/// Parses SSH (`git@host:owner/repo.git`, `ssh://git@host/owner/repo`) and
+/// HTTPS remote URLs.
+fn repository_path(url: &str) -> Option<RepositoryPath> {
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?,
+        None => url.split_once(':')?,
+    };
+    let host = host.rsplit('@').next()?.split(':').next()?;
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    if host.is_empty() || !path.contains('/') {
+        return None;
+    }
+
+    Some(RepositoryPath {
+        host: host.to_string(),
+        path: path.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repository_path() {
+        let expected = Some(RepositoryPath {
+            host: "github.com".to_string(),
+            path: "acme/api".to_string(),
+        });
+        assert_eq!(repository_path("git@github.com:acme/api.git"), expected);
+        assert_eq!(repository_path("https://github.com/acme/api"), expected);
+        assert_eq!(
+            repository_path("ssh://git@github.com:22/acme/api.git"),
+            expected
+        );
+        assert_eq!(
+            repository_path("https://token@github.com/acme/api.git/"),
+            expected
+        );
+        assert_eq!(repository_path("/srv/git/api.git"), None);
+        assert_eq!(
+            repository_path("git@github.com:acme/api.git").map(|path| path.owner().to_string()),
+            Some("acme".to_string())
+        );
+    }
+}
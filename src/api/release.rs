@@ -0,0 +1,120 @@
+use anyhow::Result;
+use semver::Version;
+
+use super::tag::Bump;
+use super::worktree::{start_branch, Checkout, StartedBranch};
+use crate::config::Config;
+use crate::git::{GitBackend, PushOptions};
+
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseOptions {
+    /// The version to release, or `major`, `minor` or `patch` to bump the
+    /// latest tag.
+    pub version: String,
+    /// Tag the release branch with `v<version>`.
+    pub tag: bool,
+    /// Push the branch, and the tag, to `remote.push`.
+    pub push: bool,
+    pub checkout: Checkout,
+}
+
+#[derive(Debug)]
+pub struct ReleaseReport {
+    pub branch: StartedBranch,
+    /// The released version, without a `v` prefix.
+    pub version: String,
+    /// The tag created for the release.
+    pub tag: Option<String>,
+    /// The remote the release was pushed to.
+    pub pushed_to: Option<String>,
+}
+
+/// Creates a release branch from the main branch, optionally tagging and
+/// pushing it.
+pub fn start_release(
+    repo: &mut dyn GitBackend,
+    config: &Config,
+    options: &ReleaseOptions,
+) -> Result<ReleaseReport> {
+    let version = release_version(repo, &options.version)?;
+    let name = format!(
+        "{}{}",
+        config.workflows.release_branch_prefix, options.version
+    );
+    let base = config.workflows.main_branch.clone();
+    let branch = start_branch(repo, config, name, base, &options.checkout)?;
+
+    let mut tag = None;
+    if options.tag {
+        let name = format!("v{}", version);
+        repo.create_tag(&name, &format!("Release {}", name), Some(&branch.name))?;
+        tag = Some(name);
+    }
+
+    let mut pushed_to = None;
+    if options.push {
+        let remote = &config.remote.push;
+        let mut refs = vec![format!("refs/heads/{}", branch.name)];
+        refs.extend(tag.iter().map(|tag| format!("refs/tags/{}", tag)));
+        repo.push(
+            remote,
+            &refs,
+            &PushOptions {
+                set_upstream: true,
+                ..Default::default()
+            },
+        )?;
+        pushed_to = Some(remote.clone());
+    }
+
+    Ok(ReleaseReport {
+        branch,
+        version,
+        tag,
+        pushed_to,
+    })
+}
+
+/// Resolves `major`, `minor` and `patch` against the latest tag, starting
+/// at 0.1.0 when it is not a version.
+fn release_version(repo: &dyn GitBackend, version: &str) -> Result<String> {
+    let Some(bump) = Bump::parse(version) else {
+        return Ok(version.to_string());
+    };
+
+    let last_tag = repo.latest_tag()?.unwrap_or_default();
+    Ok(match Version::parse(last_tag.trim_start_matches('v')) {
+        Ok(current) => bump.apply(&current).to_string(),
+        Err(_) => "0.1.0".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::MemoryRepo;
+
+    #[test]
+    fn test_release_bumps_and_tags() {
+        let mut repo = MemoryRepo::new();
+        repo.commit_on("main", "Initial commit");
+        repo.create_tag("v1.4.2", "Release v1.4.2", None).unwrap();
+
+        let report = start_release(
+            &mut repo,
+            &Config::default(),
+            &ReleaseOptions {
+                version: "minor".to_string(),
+                tag: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(report.branch.name, "release/minor");
+        assert_eq!(report.version, "1.5.0");
+        assert_eq!(report.tag.as_deref(), Some("v1.5.0"));
+        assert!(repo.tags().unwrap().contains(&"v1.5.0".to_string()));
+        assert!(report.pushed_to.is_none());
+    }
+}
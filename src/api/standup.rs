@@ -0,0 +1,44 @@
+use anyhow::Result;
+use chrono::{Duration, Local};
+
+use crate::git::{BranchActivity, CommitInfo, GitBackend, LogOptions, RepoState};
+
+#[derive(Debug, Clone, Copy)]
+pub struct StandupOptions {
+    /// How many days back to look for commits.
+    pub days: u32,
+    /// Include commits on remote branches.
+    pub all: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct StandupReport {
+    /// Your non-merge commits of the period, newest first.
+    pub commits: Vec<CommitInfo>,
+    pub state: RepoState,
+    pub uncommitted_changes: bool,
+    /// The five most recently committed to local branches.
+    pub recent_branches: Vec<BranchActivity>,
+}
+
+/// What you committed recently, by `user.email`, and where you are now.
+pub fn standup(repo: &dyn GitBackend, options: &StandupOptions) -> Result<StandupReport> {
+    let since_date = Local::now() - Duration::days(options.days as i64);
+
+    let commits = repo.log(&LogOptions {
+        branches: true,
+        remotes: options.all,
+        since: Some(since_date.timestamp()),
+        author_email: repo.config_string("user.email"),
+        no_merges: true,
+        limit: None,
+        ..Default::default()
+    })?;
+
+    Ok(StandupReport {
+        commits,
+        state: repo.state()?,
+        uncommitted_changes: repo.has_uncommitted_changes()?,
+        recent_branches: repo.recent_branches(5)?,
+    })
+}
@@ -0,0 +1,194 @@
+use anyhow::{Context, Result};
+use git2::Oid;
+
+use crate::git::{GitBackend, GitError, HeadState, StashEntry};
+
+/// Message of the stashes gwf creates when it switches away from a branch
+/// with uncommitted changes. Only these are restored automatically.
+pub const AUTOSTASH_MESSAGE: &str = "gwf autostash";
+
+/// Whether `entry` holds the changes gwf stashed when it left `branch`.
+pub fn is_autostash_for(entry: &StashEntry, branch: &str) -> bool {
+    entry.message == AUTOSTASH_MESSAGE && entry.branch.as_deref() == Some(branch)
+}
+
+/// What happened to uncommitted changes when gwf switched branches.
+#[derive(Debug)]
+pub struct BranchSwitch {
+    /// The branch that was checked out before, if HEAD was on one.
+    pub from: Option<String>,
+    /// The stash holding the changes left on `from`.
+    pub stashed: Option<Oid>,
+    /// The changes brought back from the last time the new branch was left.
+    pub restored: Option<StashRestore>,
+}
+
+/// Outcome of restoring the changes gwf stashed on a branch.
+#[derive(Debug)]
+pub enum StashRestore {
+    Applied(StashEntry),
+    /// The stash could not be listed or no longer applies cleanly; it is
+    /// kept in the stash list.
+    Failed(GitError),
+}
+
+/// Checks out `branch`, stashing the changes on the current branch first
+/// and restoring the changes stashed when `branch` was last left.
+pub fn switch_branch(repo: &mut dyn GitBackend, branch: &str) -> Result<BranchSwitch> {
+    let state = repo.state()?;
+    state.ensure_idle()?;
+    let from = state.branch().map(String::from);
+    if from.as_deref() == Some(branch) {
+        return Ok(BranchSwitch {
+            from,
+            stashed: None,
+            restored: None,
+        });
+    }
+
+    let stashed = match &state.head {
+        HeadState::Branch(_) => save(repo)?,
+        _ => None,
+    };
+    if let Err(error) = repo.checkout(branch) {
+        let Some(from) = from.as_deref().filter(|_| stashed.is_some()) else {
+            return Err(error.into());
+        };
+        let note = match restore(repo, from) {
+            Some(StashRestore::Applied(_)) => "your uncommitted changes were restored".to_string(),
+            _ => format!(
+                "your uncommitted changes are kept in the stash as '{}'",
+                AUTOSTASH_MESSAGE
+            ),
+        };
+        return Err(error).with_context(|| format!("Failed to switch to '{}'; {}", branch, note));
+    }
+    let restored = restore(repo, branch);
+
+    Ok(BranchSwitch {
+        from,
+        stashed,
+        restored,
+    })
+}
+
+/// Stashes the uncommitted changes on the current branch so they can be
+/// restored when gwf switches back to it.
+pub fn save(repo: &mut dyn GitBackend) -> Result<Option<Oid>> {
+    if !repo.has_uncommitted_changes()? {
+        return Ok(None);
+    }
+
+    Ok(repo.stash_save(AUTOSTASH_MESSAGE)?)
+}
+
+/// Restores the latest changes gwf stashed on `branch`, the current
+/// branch. Returns `None` if there were none.
+pub fn restore(repo: &mut dyn GitBackend, branch: &str) -> Option<StashRestore> {
+    let entry = match repo.stashes() {
+        Ok(stashes) => stashes
+            .into_iter()
+            .find(|entry| is_autostash_for(entry, branch))?,
+        Err(e) => return Some(StashRestore::Failed(e)),
+    };
+
+    Some(match repo.stash_pop(entry.oid) {
+        Ok(()) => StashRestore::Applied(entry),
+        Err(e) => StashRestore::Failed(e),
+    })
+}
+
+/// The stash list grouped by the branch each entry was made on, keeping
+/// the branches in order of their newest stash.
+pub fn stashes_by_branch(
+    repo: &mut dyn GitBackend,
+) -> Result<Vec<(Option<String>, Vec<StashEntry>)>> {
+    let mut groups: Vec<(Option<String>, Vec<StashEntry>)> = Vec::new();
+    for entry in repo.stashes()? {
+        match groups
+            .iter_mut()
+            .find(|(branch, _)| *branch == entry.branch)
+        {
+            Some((_, entries)) => entries.push(entry),
+            None => groups.push((entry.branch.clone(), vec![entry])),
+        }
+    }
+
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{MemoryRepo, StatusSummary};
+
+    #[test]
+    fn test_changes_follow_their_branch() {
+        let mut repo = MemoryRepo::new();
+        repo.commit_on("main", "Initial commit");
+        repo.create_branch("feature/login", None).unwrap();
+        let changes = StatusSummary {
+            modified: 2,
+            ..Default::default()
+        };
+        repo.set_status(changes);
+
+        let switch = switch_branch(&mut repo, "feature/login").unwrap();
+        assert_eq!(switch.from.as_deref(), Some("main"));
+        assert!(switch.stashed.is_some());
+        assert!(repo.status().unwrap().is_clean());
+        let stashes = repo.stashes().unwrap();
+        assert_eq!(stashes.len(), 1);
+        assert_eq!(stashes[0].branch.as_deref(), Some("main"));
+
+        let feature_changes = StatusSummary {
+            added: 1,
+            ..Default::default()
+        };
+        repo.set_status(feature_changes);
+        let switch = switch_branch(&mut repo, "main").unwrap();
+        assert!(matches!(switch.restored, Some(StashRestore::Applied(_))));
+        assert_eq!(repo.status().unwrap(), changes);
+
+        switch_branch(&mut repo, "feature/login").unwrap();
+        assert_eq!(repo.status().unwrap(), feature_changes);
+        assert_eq!(repo.stash_count(), 1);
+    }
+
+    #[test]
+    fn test_failed_checkout_restores_changes() {
+        let mut repo = MemoryRepo::new();
+        repo.commit_on("main", "Initial commit");
+        let changes = StatusSummary {
+            modified: 1,
+            ..Default::default()
+        };
+        repo.set_status(changes);
+
+        let error = switch_branch(&mut repo, "missing").unwrap_err();
+        assert!(error.to_string().contains("changes were restored"));
+        assert_eq!(repo.current_branch().unwrap(), "main");
+        assert_eq!(repo.status().unwrap(), changes);
+        assert_eq!(repo.stash_count(), 0);
+    }
+
+    #[test]
+    fn test_own_stashes_are_not_restored() {
+        let mut repo = MemoryRepo::new();
+        repo.commit_on("main", "Initial commit");
+        repo.create_branch("topic", None).unwrap();
+        repo.set_status(StatusSummary {
+            modified: 1,
+            ..Default::default()
+        });
+        repo.stash_save("experiment").unwrap();
+
+        switch_branch(&mut repo, "topic").unwrap();
+        let switch = switch_branch(&mut repo, "main").unwrap();
+
+        assert!(switch.restored.is_none());
+        assert!(repo.status().unwrap().is_clean());
+        assert_eq!(repo.stash_count(), 1);
+        assert_eq!(stashes_by_branch(&mut repo).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,46 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use std::collections::HashMap;
+
+use crate::git::{GitBackend, LogOptions};
+
+// TODO: add language stats
+// TODO: add contribution graph
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoStats {
+    pub total_commits: usize,
+    /// Authors by name with their number of commits, most commits first.
+    pub contributors: Vec<(String, usize)>,
+    /// Local and remote-tracking branches.
+    pub branches: usize,
+    pub tags: usize,
+    /// Commits made in the last seven days.
+    pub last_week: usize,
+}
+
+/// Counts the commits reachable from HEAD, their authors, branches and
+/// tags.
+pub fn stats(repo: &dyn GitBackend) -> Result<RepoStats> {
+    let commits = repo.log(&LogOptions::default())?;
+
+    let mut authors: HashMap<String, usize> = HashMap::new();
+    for commit in &commits {
+        *authors.entry(commit.author_name.clone()).or_insert(0) += 1;
+    }
+    let mut contributors: Vec<_> = authors.into_iter().collect();
+    contributors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let week_ago = (Utc::now() - Duration::days(7)).timestamp();
+
+    Ok(RepoStats {
+        total_commits: commits.len(),
+        contributors,
+        branches: repo.list_branches(false)?.len() + repo.list_branches(true)?.len(),
+        tags: repo.tags()?.len(),
+        last_week: commits
+            .iter()
+            .filter(|commit| commit.time > week_ago)
+            .count(),
+    })
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::git::{GitBackend, HeadState, RepoState, StatusSummary};
+
+/// How far a branch and its upstream have moved apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub upstream: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct StatusReport {
+    pub state: RepoState,
+    pub changes: StatusSummary,
+    /// `None` unless HEAD is on a branch with commits whose upstream exists.
+    pub upstream: Option<Divergence>,
+}
+
+/// Where HEAD is, what changed in the working tree and how the current
+/// branch compares with its upstream, or `<remote.push>/<branch>`.
+pub fn status(repo: &dyn GitBackend, config: &Config) -> Result<StatusReport> {
+    let state = repo.state()?;
+    let changes = repo.status()?;
+
+    // Only a branch with commits can be compared with its upstream.
+    let mut upstream = None;
+    if let HeadState::Branch(branch) = &state.head {
+        let name = repo
+            .upstream(branch)
+            .unwrap_or_else(|| format!("{}/{}", config.remote.push, branch));
+        if let Some((ahead, behind)) = repo.ahead_behind("HEAD", &name)? {
+            upstream = Some(Divergence {
+                upstream: name,
+                ahead,
+                behind,
+            });
+        }
+    }
+
+    Ok(StatusReport {
+        state,
+        changes,
+        upstream,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::MemoryRepo;
+
+    #[test]
+    fn test_status_compares_with_push_remote() {
+        let repo = MemoryRepo::new();
+        repo.add_remote("origin", "git@example.com:acme/api.git");
+        let base = repo.commit_on("main", "Initial commit");
+        repo.set_remote_branch("origin/main", base);
+        repo.commit_on("main", "Local change");
+
+        let report = status(&repo, &Config::default()).unwrap();
+
+        assert_eq!(report.state.branch(), Some("main"));
+        assert!(report.changes.is_clean());
+        assert_eq!(
+            report.upstream,
+            Some(Divergence {
+                upstream: "origin/main".to_string(),
+                ahead: 1,
+                behind: 0,
+            })
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use anyhow::{bail, Context, Result};
+use git2::Oid;
+
+use super::fetch::{fetch, FetchReporter};
+use super::stash::{self, StashRestore, AUTOSTASH_MESSAGE};
+use crate::config::{Config, SyncStrategy};
+use crate::git::{GitBackend, GitError, HeadState, MergeOutcome, PushOptions};
+
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Sync every local branch instead of the current one.
+    pub all: bool,
+    /// Sync this branch instead of the current one.
+    pub branch: Option<String>,
+}
+
+/// A base branch fast-forwarded from the upstream remote.
+#[derive(Debug)]
+pub struct BaseBranchUpdate {
+    pub branch: String,
+    pub upstream: String,
+    pub outcome: Result<MergeOutcome, GitError>,
+}
+
+/// A branch rebased onto or merged with its upstream.
+#[derive(Debug)]
+pub struct BranchSync {
+    pub branch: String,
+    pub upstream: String,
+    /// The merge commit created with the merge strategy, if any.
+    pub result: Result<Option<Oid>, GitError>,
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// The branch that was checked out, and is again afterwards.
+    pub original: String,
+    /// The remotes fetched, upstream first.
+    pub fetched: Vec<String>,
+    /// Whether the fetches pruned deleted remote branches.
+    pub pruned: bool,
+    /// The stash holding the current branch's uncommitted changes while
+    /// the branches were synced.
+    pub stashed: Option<Oid>,
+    /// The base branches the upstream remote has.
+    pub base_branches: Vec<BaseBranchUpdate>,
+    /// Result of pushing the base branches to the fork, with
+    /// `sync.update_fork`.
+    pub fork_update: Option<Result<(), GitError>>,
+    pub branches: Vec<BranchSync>,
+    pub restored: Option<StashRestore>,
+}
+
+/// Fetches, fast-forwards the base branches and rebases or merges the
+/// current branch, or the branches `options` name, onto their upstream.
+/// Failures on single branches are reported, not returned.
+pub fn sync(
+    repo: &mut dyn GitBackend,
+    config: &Config,
+    options: &SyncOptions,
+    reporter: &mut dyn FetchReporter,
+) -> Result<SyncReport> {
+    let state = repo.state()?;
+    state.ensure_idle()?;
+    let original = match state.head {
+        HeadState::Branch(branch) => branch,
+        HeadState::Unborn(branch) => {
+            bail!(
+                "Branch '{}' has no commits yet; there is nothing to sync",
+                branch
+            )
+        }
+        HeadState::Detached(_) => bail!("HEAD is detached; check out a branch to sync"),
+    };
+
+    // In a fork the push remote holds our own branches, so it is fetched
+    // too to compare them with their upstream.
+    let remotes = &config.remote;
+    let mut fetch_from = vec![remotes.upstream.as_str()];
+    if remotes.is_fork() {
+        fetch_from.push(&remotes.push);
+    }
+    fetch(repo, &fetch_from, config.sync.prune_on_fetch, reporter)?;
+
+    let mut report = SyncReport {
+        original: original.clone(),
+        fetched: fetch_from.iter().map(|remote| remote.to_string()).collect(),
+        pruned: config.sync.prune_on_fetch,
+        ..Default::default()
+    };
+
+    // Changes on the current branch are stashed once and restored after
+    // every branch is synced, so they never land on another branch.
+    if config.sync.auto_stash {
+        report.stashed = stash::save(repo)?;
+    }
+
+    let synced = sync_branches(repo, config, options, &mut report);
+
+    // Even when syncing stopped early, go back to where the user was and
+    // bring their changes back.
+    let returned = match repo.current_branch() {
+        Ok(current) if current == original => Ok(()),
+        _ => repo.checkout(&original),
+    };
+    match returned {
+        Ok(()) if report.stashed.is_some() => {
+            report.restored = stash::restore(repo, &original);
+        }
+        Ok(()) => {}
+        Err(error) if report.stashed.is_some() => {
+            return Err(error).with_context(|| {
+                format!(
+                    "Failed to check out '{}' again; your uncommitted changes are kept in \
+                     the stash as '{}'",
+                    original, AUTOSTASH_MESSAGE
+                )
+            });
+        }
+        Err(error) => return Err(error.into()),
+    }
+    synced?;
+
+    Ok(report)
+}
+
+/// Rebases or merges each branch `options` selects onto its upstream,
+/// recording the result of every branch in `report`.
+fn sync_branches(
+    repo: &mut dyn GitBackend,
+    config: &Config,
+    options: &SyncOptions,
+    report: &mut SyncReport,
+) -> Result<()> {
+    let remotes = &config.remote;
+    let base_branches = update_base_branches(repo, config, report)?;
+
+    let branches_to_sync = if options.all {
+        repo.list_branches(false)?
+    } else if let Some(branch_name) = &options.branch {
+        vec![branch_name.clone()]
+    } else {
+        vec![report.original.clone()]
+    };
+
+    for branch in branches_to_sync {
+        let upstream = if base_branches.contains(&branch) {
+            format!("{}/{}", remotes.upstream, branch)
+        } else {
+            repo.upstream(&branch)
+                .unwrap_or_else(|| format!("{}/{}", remotes.push, branch))
+        };
+        // A branch checked out in a linked worktree, or one whose checkout
+        // would overwrite files, is skipped.
+        let result = match repo.checkout(&branch) {
+            Err(error) => Err(error),
+            Ok(()) => match config.sync.strategy {
+                SyncStrategy::Rebase => repo.rebase(&upstream).map(|()| None),
+                SyncStrategy::Merge => repo.merge(&upstream).map(|outcome| match outcome {
+                    MergeOutcome::Merged(oid) => Some(oid),
+                    _ => None,
+                }),
+            },
+        };
+        report.branches.push(BranchSync {
+            branch,
+            upstream,
+            result,
+        });
+    }
+
+    Ok(())
+}
+
+/// Fast-forwards the local main and develop branches to the upstream
+/// remote and, with `sync.update_fork`, pushes them to the push remote.
+/// Returns the base branches that exist locally.
+fn update_base_branches(
+    repo: &dyn GitBackend,
+    config: &Config,
+    report: &mut SyncReport,
+) -> Result<Vec<String>> {
+    let remotes = &config.remote;
+    let local = repo.list_branches(false)?;
+    let base_branches: Vec<String> = std::iter::once(&config.workflows.main_branch)
+        .chain(config.workflows.develop_branch.as_ref())
+        .filter(|branch| local.contains(branch))
+        .cloned()
+        .collect();
+
+    let mut to_push = Vec::new();
+    for branch in &base_branches {
+        let upstream = format!("{}/{}", remotes.upstream, branch);
+        let outcome = match repo.fast_forward(branch, &upstream) {
+            // The upstream remote does not have this branch.
+            Err(GitError::RevisionNotFound(_)) => continue,
+            outcome => outcome,
+        };
+        if outcome.is_ok() {
+            to_push.push(format!("refs/heads/{}", branch));
+        }
+        report.base_branches.push(BaseBranchUpdate {
+            branch: branch.clone(),
+            upstream,
+            outcome,
+        });
+    }
+
+    if config.sync.update_fork && remotes.is_fork() && !to_push.is_empty() {
+        report.fork_update = Some(repo.push(&remotes.push, &to_push, &PushOptions::default()));
+    }
+
+    Ok(base_branches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SyncStrategy;
+    use crate::git::{MemoryRepo, StatusSummary};
+    use std::path::Path;
+
+    fn run(
+        repo: &mut MemoryRepo,
+        config: &Config,
+        all: bool,
+        branch: Option<String>,
+    ) -> Result<SyncReport> {
+        sync(repo, config, &SyncOptions { all, branch }, &mut ())
+    }
+
+    fn diverged_repo() -> MemoryRepo {
+        let repo = MemoryRepo::new();
+        repo.add_remote("origin", "git@example.com:acme/api.git");
+        repo.commit_on("main", "Initial commit");
+        repo.create_branch("upstream", None).unwrap();
+        let upstream = repo.commit_on("upstream", "Upstream change");
+        repo.set_remote_branch("origin/main", upstream);
+        repo.commit_on("main", "Local change");
+        repo
+    }
+
+    #[test]
+    fn test_rebase_keeps_uncommitted_changes() {
+        let mut repo = diverged_repo();
+        let changes = StatusSummary {
+            modified: 1,
+            ..Default::default()
+        };
+        repo.set_status(changes);
+
+        let report = run(&mut repo, &Config::default(), false, None).unwrap();
+
+        assert!(report.stashed.is_some());
+        assert!(matches!(report.restored, Some(StashRestore::Applied(_))));
+        assert_eq!(report.branches.len(), 1);
+        assert!(report.branches[0].result.is_ok());
+        let tip = repo.branch_tip("main").unwrap();
+        let upstream = repo.branch_tip("upstream").unwrap();
+        assert_eq!(repo.parents(tip), vec![upstream]);
+        assert_eq!(repo.status().unwrap(), changes);
+        assert_eq!(repo.stash_count(), 0);
+    }
+
+    #[test]
+    fn test_sync_all_keeps_changes_on_their_branch() {
+        let mut repo = diverged_repo();
+        repo.set_remote_branch("origin/upstream", repo.branch_tip("upstream").unwrap());
+        let changes = StatusSummary {
+            added: 1,
+            ..Default::default()
+        };
+        repo.set_status(changes);
+
+        run(&mut repo, &Config::default(), true, None).unwrap();
+
+        assert_eq!(repo.current_branch().unwrap(), "main");
+        assert_eq!(repo.status().unwrap(), changes);
+        assert_eq!(repo.stash_count(), 0);
+    }
+
+    #[test]
+    fn test_failed_checkout_is_reported_and_changes_restored() {
+        let mut repo = diverged_repo();
+        repo.set_remote_branch("origin/upstream", repo.branch_tip("upstream").unwrap());
+        repo.add_worktree("upstream", Path::new("/work/api-upstream"))
+            .unwrap();
+        let changes = StatusSummary {
+            modified: 1,
+            ..Default::default()
+        };
+        repo.set_status(changes);
+
+        let report = run(&mut repo, &Config::default(), true, None).unwrap();
+
+        let failed: Vec<_> = report
+            .branches
+            .iter()
+            .filter(|sync| sync.result.is_err())
+            .map(|sync| sync.branch.as_str())
+            .collect();
+        assert_eq!(failed, ["upstream"]);
+        assert!(matches!(report.restored, Some(StashRestore::Applied(_))));
+        assert_eq!(repo.current_branch().unwrap(), "main");
+        assert_eq!(repo.status().unwrap(), changes);
+        assert_eq!(repo.stash_count(), 0);
+    }
+
+    #[test]
+    fn test_fork_fast_forwards_base_branches_from_upstream() {
+        let mut repo = MemoryRepo::new();
+        repo.add_remote("origin", "git@github.com:me/api.git");
+        repo.add_remote("upstream", "git@github.com:acme/api.git");
+        let base = repo.commit_on("main", "Initial commit");
+        repo.set_remote_branch("origin/main", base);
+        repo.create_branch("develop", None).unwrap();
+        repo.create_branch("feature/login", None).unwrap();
+        repo.create_branch("upstream-main", None).unwrap();
+        repo.checkout("feature/login").unwrap();
+        let ahead = repo.commit_on("upstream-main", "Upstream change");
+        repo.set_remote_branch("upstream/main", ahead);
+
+        let mut config = Config::default();
+        config.remote.upstream = "upstream".to_string();
+        config.sync.update_fork = true;
+        let report = run(&mut repo, &config, false, None).unwrap();
+
+        assert_eq!(report.fetched, ["upstream", "origin"]);
+        assert_eq!(report.base_branches.len(), 1);
+        assert!(matches!(report.fork_update, Some(Ok(()))));
+        assert_eq!(repo.branch_tip("main"), Some(ahead));
+        assert_eq!(repo.branch_tip("develop"), Some(base));
+        assert_eq!(repo.current_branch().unwrap(), "feature/login");
+        assert_eq!(repo.pushed(), vec!["refs/heads/main".to_string()]);
+        assert_eq!(
+            repo.ahead_behind("main", "origin/main").unwrap(),
+            Some((0, 0))
+        );
+    }
+
+    #[test]
+    fn test_merge_strategy_creates_merge_commit() {
+        let mut repo = diverged_repo();
+        let mut config = Config::default();
+        config.sync.strategy = SyncStrategy::Merge;
+
+        let report = run(&mut repo, &config, false, Some("main".to_string())).unwrap();
+
+        let tip = repo.branch_tip("main").unwrap();
+        assert!(matches!(report.branches[0].result, Ok(Some(oid)) if oid == tip));
+        assert_eq!(repo.parents(tip).len(), 2);
+        assert_eq!(
+            repo.ahead_behind("main", "origin/main").unwrap(),
+            Some((2, 0))
+        );
+    }
+
+    #[test]
+    fn test_refuses_during_bisect() {
+        let mut repo = diverged_repo();
+        repo.bisect_start("main", &["upstream".to_string()])
+            .unwrap();
+
+        let error = run(&mut repo, &Config::default(), false, None).unwrap_err();
+        assert!(error.to_string().contains("bisect is in progress"));
+
+        repo.bisect_reset().unwrap();
+        assert!(run(&mut repo, &Config::default(), false, None).is_ok());
+    }
+
+    #[test]
+    fn test_missing_remote_fails() {
+        let mut repo = MemoryRepo::new();
+        repo.commit_on("main", "Initial commit");
+
+        let error = run(&mut repo, &Config::default(), false, None).unwrap_err();
+        assert!(error.to_string().contains("origin"));
+    }
+}
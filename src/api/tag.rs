@@ -0,0 +1,108 @@
+use anyhow::Result;
+use semver::Version;
+
+use crate::config::Config;
+use crate::git::{GitBackend, PushOptions};
+
+/// Which part of a semantic version a release bumps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bump {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl Bump {
+    /// Parses `major`, `minor` or `patch`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "major" => Some(Bump::Major),
+            "minor" => Some(Bump::Minor),
+            "patch" => Some(Bump::Patch),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, version: &Version) -> Version {
+        match self {
+            Bump::Major => Version::new(version.major + 1, 0, 0),
+            Bump::Minor => Version::new(version.major, version.minor + 1, 0),
+            Bump::Patch => Version::new(version.major, version.minor, version.patch + 1),
+        }
+    }
+}
+
+/// The highest semantic version among the tags, with or without a `v`
+/// prefix. `None` if no tag is a version.
+pub fn latest_version(repo: &dyn GitBackend) -> Result<Option<Version>> {
+    Ok(repo
+        .tags()?
+        .iter()
+        .filter_map(|tag| Version::parse(tag.trim_start_matches('v')).ok())
+        .max())
+}
+
+#[derive(Debug, Clone)]
+pub struct TagOptions {
+    pub version: Version,
+    pub message: String,
+    /// Push the tag to `remote.push`.
+    pub push: bool,
+}
+
+#[derive(Debug)]
+pub struct TagReport {
+    /// The tag's name, the version with a `v` prefix.
+    pub name: String,
+    /// The remote the tag was pushed to.
+    pub pushed_to: Option<String>,
+}
+
+/// Creates an annotated `v<version>` tag on HEAD.
+pub fn create_tag(
+    repo: &dyn GitBackend,
+    config: &Config,
+    options: &TagOptions,
+) -> Result<TagReport> {
+    // TODO: add GPG signing support
+    let name = format!("v{}", options.version);
+    repo.create_tag(&name, &options.message, None)?;
+
+    let mut pushed_to = None;
+    if options.push {
+        let remote = &config.remote.push;
+        repo.push(
+            remote,
+            &[format!("refs/tags/{}", name)],
+            &PushOptions::default(),
+        )?;
+        pushed_to = Some(remote.clone());
+    }
+
+    Ok(TagReport { name, pushed_to })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::MemoryRepo;
+
+    #[test]
+    fn test_bump_latest_version() {
+        let repo = MemoryRepo::new();
+        repo.commit_on("main", "Initial commit");
+        assert_eq!(latest_version(&repo).unwrap(), None);
+
+        for tag in ["v1.2.3", "v1.10.0", "nightly", "0.9.0"] {
+            repo.create_tag(tag, tag, None).unwrap();
+        }
+        let latest = latest_version(&repo).unwrap().unwrap();
+        assert_eq!(latest, Version::new(1, 10, 0));
+
+        assert_eq!(Bump::Major.apply(&latest), Version::new(2, 0, 0));
+        assert_eq!(Bump::Minor.apply(&latest), Version::new(1, 11, 0));
+        assert_eq!(Bump::Patch.apply(&latest), Version::new(1, 10, 1));
+        assert_eq!(Bump::parse("minor"), Some(Bump::Minor));
+        assert_eq!(Bump::parse("1.0.0"), None);
+    }
+}
@@ -0,0 +1,28 @@
+use anyhow::Result;
+
+use crate::git::{GitBackend, ResetMode};
+
+/// What `gwf undo` takes back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UndoAction {
+    /// Remove the last commit, keeping its changes staged.
+    LastCommit,
+    /// Remove the last commit and discard its changes.
+    DiscardLastCommit,
+    /// Return to where the branch was before the last merge.
+    LastMerge,
+    /// Abort a merge that stopped on conflicts.
+    AbortMerge,
+}
+
+pub fn undo(repo: &dyn GitBackend, action: UndoAction) -> Result<()> {
+    // TODO: add reflog support for more undo options
+    match action {
+        UndoAction::LastCommit => repo.reset("HEAD~1", ResetMode::Soft)?,
+        UndoAction::DiscardLastCommit => repo.reset("HEAD~1", ResetMode::Hard)?,
+        UndoAction::LastMerge => repo.reset("ORIG_HEAD", ResetMode::Hard)?,
+        UndoAction::AbortMerge => repo.abort_merge()?,
+    }
+
+    Ok(())
+}
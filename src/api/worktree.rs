@@ -0,0 +1,85 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use super::stash::{self, BranchSwitch};
+use crate::config::Config;
+use crate::git::worktree::{normalize_path, worktree_name};
+use crate::git::GitBackend;
+
+/// Where a newly created branch is checked out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Checkout {
+    /// Switch the current worktree to it, stashing uncommitted changes.
+    #[default]
+    Switch,
+    /// Check it out in a linked worktree at the given path, relative to the
+    /// current directory, or at `workflows.worktree_path`.
+    Worktree(Option<PathBuf>),
+}
+
+/// How a branch ended up checked out.
+#[derive(Debug)]
+pub enum CheckedOut {
+    Switched(BranchSwitch),
+    /// The path of the linked worktree it was checked out in.
+    Worktree(PathBuf),
+}
+
+/// A branch created by `gwf feature`, `gwf hotfix` or `gwf release`.
+#[derive(Debug)]
+pub struct StartedBranch {
+    pub name: String,
+    /// The branch it was created from.
+    pub base: String,
+    pub checkout: CheckedOut,
+}
+
+/// Checks out the newly created `branch` as `checkout` says.
+pub fn check_out(
+    repo: &mut dyn GitBackend,
+    config: &Config,
+    branch: &str,
+    checkout: &Checkout,
+) -> Result<CheckedOut> {
+    let path = match checkout {
+        Checkout::Switch => {
+            return Ok(CheckedOut::Switched(stash::switch_branch(repo, branch)?));
+        }
+        Checkout::Worktree(Some(path)) => std::env::current_dir()?.join(path),
+        Checkout::Worktree(None) => {
+            let root = repo.workdir()?;
+            let repo_name = root
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            root.join(
+                config
+                    .workflows
+                    .worktree_path
+                    .replace("{repo}", &repo_name)
+                    .replace("{branch}", &worktree_name(branch)),
+            )
+        }
+    };
+
+    let worktree = repo.add_worktree(branch, &normalize_path(&path))?;
+    Ok(CheckedOut::Worktree(worktree.path))
+}
+
+/// Creates `branch` from `base` and checks it out.
+pub(super) fn start_branch(
+    repo: &mut dyn GitBackend,
+    config: &Config,
+    branch: String,
+    base: String,
+    checkout: &Checkout,
+) -> Result<StartedBranch> {
+    repo.create_branch(&branch, Some(&base))?;
+    let checkout = check_out(repo, config, &branch, checkout)?;
+
+    Ok(StartedBranch {
+        name: branch,
+        base,
+        checkout,
+    })
+}
@@ -1,9 +1,11 @@
 use anyhow::Result;
 use colored::Colorize;
 use dialoguer::{Select, Input, Confirm};
-use std::process::{Command, Stdio};
+use std::path::Path;
 
-pub async fn run() -> Result<()> {
+use crate::git::{BisectMark, BisectStep, GitBackend};
+
+pub async fn run(repo: &dyn GitBackend) -> Result<()> {
     let choices = vec![
         "Start bisect",
         "Mark as good",
@@ -20,80 +22,56 @@ pub async fn run() -> Result<()> {
         .interact()?;
     
     match selection {
-        0 => start_bisect()?,
-        1 => mark_good()?,
-        2 => mark_bad()?,
-        3 => skip_current()?,
-        4 => reset_bisect()?,
-        5 => automated_bisect()?,
+        0 => start_bisect(repo)?,
+        1 => mark(repo, BisectMark::Good)?,
+        2 => mark(repo, BisectMark::Bad)?,
+        3 => mark(repo, BisectMark::Skip)?,
+        4 => reset_bisect(repo)?,
+        5 => automated_bisect(repo)?,
         _ => {}
     }
     
     Ok(())
 }
 
-fn start_bisect() -> Result<()> {
-    Command::new("git")
-        .args(&["bisect", "start"])
-        .status()?;
-    
+fn prompt_range() -> Result<(String, String)> {
     let bad_commit: String = Input::new()
         .with_prompt("Bad commit (or press enter for HEAD)")
         .default("HEAD".to_string())
         .interact_text()?;
     
-    Command::new("git")
-        .args(&["bisect", "bad", &bad_commit])
-        .status()?;
-    
     let good_commit: String = Input::new()
         .with_prompt("Good commit")
         .interact_text()?;
     
-    Command::new("git")
-        .args(&["bisect", "good", &good_commit])
-        .status()?;
+    Ok((bad_commit, good_commit))
+}
+
+fn start_bisect(repo: &dyn GitBackend) -> Result<()> {
+    let (bad_commit, good_commit) = prompt_range()?;
+    
+    let step = repo.bisect_start(&bad_commit, &[good_commit])?;
     
     println!("{}", "Bisect started. Test and mark commits as good/bad.".green());
-    show_status()?;
+    show_step(&step);
     
     Ok(())
 }
 
-fn mark_good() -> Result<()> {
-    Command::new("git")
-        .args(&["bisect", "good"])
-        .status()?;
-    show_status()?;
-    Ok(())
-}
-
-fn mark_bad() -> Result<()> {
-    Command::new("git")
-        .args(&["bisect", "bad"])
-        .status()?;
-    show_status()?;
-    Ok(())
-}
-
-fn skip_current() -> Result<()> {
-    Command::new("git")
-        .args(&["bisect", "skip"])
-        .status()?;
-    show_status()?;
+fn mark(repo: &dyn GitBackend, mark: BisectMark) -> Result<()> {
+    let step = repo.bisect_mark(mark, None)?;
+    show_step(&step);
     Ok(())
 }
 
-fn reset_bisect() -> Result<()> {
-    Command::new("git")
-        .args(&["bisect", "reset"])
-        .status()?;
+fn reset_bisect(repo: &dyn GitBackend) -> Result<()> {
+    repo.bisect_reset()?;
     println!("{}", "Bisect reset".green());
     Ok(())
 }
 
-fn automated_bisect() -> Result<()> {
-    // HACK: this assumes the test script returns proper exit codes
+fn automated_bisect(repo: &dyn GitBackend) -> Result<()> {
+    // The script decides each step: exit 0 for good, 125 to skip, 1-127 for bad.
     let script_path: String = Input::new()
         .with_prompt("Test script path")
         .interact_text()?;
@@ -107,76 +85,36 @@ fn automated_bisect() -> Result<()> {
         return Ok(());
     }
     
-    println!("{}", "Starting automated bisect...".cyan());
-    
-    Command::new("git")
-        .args(&["bisect", "start"])
-        .status()?;
+    let (bad_commit, good_commit) = prompt_range()?;
     
-    let bad_commit: String = Input::new()
-        .with_prompt("Bad commit")
-        .default("HEAD".to_string())
-        .interact_text()?;
-    
-    Command::new("git")
-        .args(&["bisect", "bad", &bad_commit])
-        .status()?;
-    
-    let good_commit: String = Input::new()
-        .with_prompt("Good commit")
-        .interact_text()?;
+    println!("{}", "Starting automated bisect...".cyan());
     
-    Command::new("git")
-        .args(&["bisect", "good", &good_commit])
-        .status()?;
+    repo.bisect_start(&bad_commit, &[good_commit])?;
+    let script = Path::new(&script_path).canonicalize()?;
+    let commit = repo.bisect_run(&script)?;
     
-    let output = Command::new("git")
-        .args(&["bisect", "run", &script_path])
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .output()?;
-    
-    if output.status.success() {
-        println!("{}", "Bisect completed!".green().bold());
-        
-        let output = Command::new("git")
-            .args(&["bisect", "view"])
-            .output()?;
-        
-        if let Ok(commit) = String::from_utf8(output.stdout) {
-            println!("{} {}", "First bad commit:".yellow(), commit.trim());
-        }
-    }
+    println!("{}", "Bisect completed!".green().bold());
+    println!("{} {} {}", "First bad commit:".yellow(), commit.short_id, commit.summary);
     
     Ok(())
 }
 
-fn show_status() -> Result<()> {
-    let output = Command::new("git")
-        .args(&["bisect", "log"])
-        .output()?;
-    
-    if output.status.success() {
-        let log = String::from_utf8_lossy(&output.stdout);
-        let lines: Vec<&str> = log.lines().collect();
-        
-        if let Some(last_line) = lines.last() {
-            if last_line.contains("bisecting") {
-                let output = Command::new("git")
-                    .args(&["rev-list", "--count", "--bisect-all"])
-                    .output()?;
-                
-                if let Ok(count_str) = String::from_utf8(output.stdout) {
-                    if let Some(count) = count_str.trim().split_whitespace().next() {
-                        println!("{} {} steps remaining", 
-                            "Bisecting:".cyan(),
-                            count.yellow()
-                        );
-                    }
-                }
-            }
+fn show_step(step: &BisectStep) {
+    match step {
+        BisectStep::Testing { commit, remaining } => {
+            let steps = usize::BITS - remaining.leading_zeros();
+            println!("{} {} revisions left to test (roughly {} steps)", 
+                "Bisecting:".cyan(),
+                remaining.to_string().yellow(),
+                steps
+            );
+            println!("  [{}] {}", commit.short_id.yellow(), commit.summary);
+        }
+        BisectStep::Found(commit) => {
+            println!("{} {} {}", "First bad commit:".yellow(), commit.short_id, commit.summary);
+        }
+        BisectStep::NeedsMarks => {
+            println!("{}", "Mark a good and a bad commit to continue".yellow());
         }
     }
-    
-    Ok(())
 }
\ No newline at end of file
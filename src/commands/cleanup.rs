@@ -2,39 +2,33 @@ use anyhow::Result;
 use colored::*;
 use dialoguer::Confirm;
 
+use crate::api::cleanup::merged_branches;
+use crate::api::{self, CleanupOptions};
 use crate::config::Config;
-use crate::git::GitRepo;
-use crate::utils::{print_info, print_success, print_warning};
+use crate::git::GitBackend;
+use crate::utils::progress::FetchBars;
+use crate::utils::{format_error, print_info, print_success, print_warning};
 
-pub async fn execute(yes: bool, dry_run: bool, remote: bool) -> Result<()> {
+pub async fn execute(repo: &dyn GitBackend, yes: bool, dry_run: bool, remote: bool) -> Result<()> {
     let config = Config::load()?;
-    let repo = GitRepo::open_current()?;
 
     print_info("Scanning for branches to clean up...");
-
-    let current_branch = repo.current_branch()?;
-    let branches = repo.list_branches(false)?;
-
-    let mut branches_to_delete = Vec::new();
-
-    for branch in &branches {
-        if config.cleanup.protect_branches.contains(branch) || branch == &current_branch {
-            continue;
-        }
-
-        if repo.is_branch_merged(branch, &config.workflows.main_branch)? {
-            branches_to_delete.push(branch.clone());
-        }
-    }
-
-    if branches_to_delete.is_empty() {
+    let merged = merged_branches(repo, &config)?;
+    if merged.is_empty() {
         print_success("No branches to clean up!");
         return Ok(());
     }
 
     println!("\n{}", "Branches to delete:".bright_white().underline());
-    for branch in &branches_to_delete {
-        println!("  - {}", branch.bright_red());
+    for branch in &merged {
+        match &branch.worktree {
+            Some(worktree) => println!(
+                "  - {} {}",
+                branch.name.bright_red(),
+                format!("(and its worktree {})", worktree.path.display()).dimmed()
+            ),
+            None => println!("  - {}", branch.name.bright_red()),
+        }
     }
 
     if dry_run {
@@ -59,16 +53,33 @@ pub async fn execute(yes: bool, dry_run: bool, remote: bool) -> Result<()> {
         return Ok(());
     }
 
-    for branch in branches_to_delete {
-        match repo.delete_branch(&branch) {
-            Ok(_) => print_success(&format!("Deleted branch '{}'", branch)),
-            Err(e) => print_warning(&format!("Failed to delete '{}': {}", branch, e)),
-        }
+    if remote {
+        print_info("Deleting branches and pruning remote branches...");
     }
+    let report = api::cleanup(
+        repo,
+        &config,
+        &CleanupOptions {
+            dry_run: false,
+            prune_remote: remote,
+        },
+        &mut FetchBars::new(),
+    )?;
 
-    if remote {
-        print_info("Pruning remote branches...");
-        repo.fetch("origin")?;
+    for path in &report.removed_worktrees {
+        print_success(&format!("Removed worktree '{}'", path.display()));
+    }
+    for branch in &report.deleted {
+        print_success(&format!("Deleted branch '{}'", branch));
+    }
+    for (branch, e) in &report.failed {
+        print_warning(&format!(
+            "Failed to delete '{}': {}",
+            branch,
+            format_error(e)
+        ));
+    }
+    if report.pruned {
         print_success("Remote branches pruned");
     }
 
@@ -1,89 +1,76 @@
-use anyhow::{bail, Context, Result};
-use dialoguer::{Input, Select};
-use std::process::Command;
-
-use crate::config::Config;
-use crate::git::GitRepo;
-use crate::utils::{print_info, print_success};
-
-const COMMIT_TYPES: &[(&str, &str)] = &[
-    ("feat", "A new feature"),
-    ("fix", "A bug fix"),
-    ("docs", "Documentation only changes"),
-    (
-        "style",
-        "Changes that do not affect the meaning of the code",
-    ),
-    (
-        "refactor",
-        "A code change that neither fixes a bug nor adds a feature",
-    ),
-    ("perf", "A code change that improves performance"),
-    ("test", "Adding missing tests or correcting existing tests"),
-    ("chore", "Changes to the build process or auxiliary tools"),
-];
-
-pub async fn execute(message: Option<String>, ai: bool, amend: bool) -> Result<()> {
+use anyhow::{bail, Result};
+use colored::*;
+use dialoguer::{Editor, Input, Select};
+
+use crate::api::{self, ai, ConventionalCommit, GeneratedMessage};
+use crate::config::{AiConfig, Config};
+use crate::git::GitBackend;
+use crate::utils::progress::spinner;
+use crate::utils::{print_info, print_success, print_warning};
+
+pub async fn execute(
+    repo: &dyn GitBackend,
+    message: Option<String>,
+    ai: bool,
+    amend: bool,
+) -> Result<()> {
     let config = Config::load()?;
-    let _repo = GitRepo::open_current()?;
 
-    if ai {
-        print_info("AI-powered commit messages are not yet implemented");
-        return Ok(());
-    }
+    let ai_config = config.ai.as_ref();
+    let use_ai = ai || ai_config.is_some_and(|ai| ai.enabled);
 
     let final_message = if let Some(msg) = message {
         msg
+    } else if use_ai {
+        let default = AiConfig::default();
+        match generate_message(repo, &config, ai_config.unwrap_or(&default), amend)? {
+            Some(message) => message,
+            None => {
+                print_info("Commit cancelled");
+                return Ok(());
+            }
+        }
     } else if config.commits.conventional {
         if !atty::is(atty::Stream::Stdin) {
             bail!("Interactive mode requires a terminal. Please provide a message with -m");
         }
 
+        let types = &config.commits.types;
+        if types.is_empty() {
+            bail!("No commit types are configured; add some to commits.types");
+        }
         let type_index = Select::new()
             .with_prompt("Select commit type")
             .items(
-                &COMMIT_TYPES
+                &types
                     .iter()
-                    .map(|(t, d)| format!("{}: {}", t, d))
+                    .map(|t| match &t.emoji {
+                        Some(emoji) => format!("{} {}: {}", emoji, t.name, t.description),
+                        None => format!("{}: {}", t.name, t.description),
+                    })
                     .collect::<Vec<_>>(),
             )
             .default(0)
             .interact()?;
+        let kind = &types[type_index];
 
-        let commit_type = COMMIT_TYPES[type_index].0;
-
-        let scope: String = Input::new()
-            .with_prompt("Scope (optional)")
-            .allow_empty(true)
-            .interact_text()?;
-
+        let scope = select_scope(repo, &config)?;
         let description: String = Input::new().with_prompt("Description").interact_text()?;
-
-        let body: String = Input::new()
-            .with_prompt("Body (optional)")
-            .allow_empty(true)
-            .interact_text()?;
-
-        let breaking: String = Input::new()
-            .with_prompt("Breaking change (optional)")
-            .allow_empty(true)
-            .interact_text()?;
-
-        let mut commit_msg = if scope.is_empty() {
-            format!("{}: {}", commit_type, description)
-        } else {
-            format!("{}({}): {}", commit_type, scope, description)
+        let description = match &kind.emoji {
+            Some(emoji) if config.commits.emoji => format!("{} {}", emoji, description),
+            _ => description,
         };
-
-        if !body.is_empty() {
-            commit_msg.push_str(&format!("\n\n{}", body));
-        }
-
-        if !breaking.is_empty() {
-            commit_msg.push_str(&format!("\n\nBREAKING CHANGE: {}", breaking));
+        let body = optional_input("Body (optional)")?;
+        let breaking = optional_input("Breaking change (optional)")?;
+
+        ConventionalCommit {
+            kind: kind.name.clone(),
+            scope,
+            description,
+            body,
+            breaking,
         }
-
-        commit_msg
+        .message()
     } else {
         if !atty::is(atty::Stream::Stdin) {
             bail!("Interactive mode requires a terminal. Please provide a message with -m");
@@ -92,35 +79,127 @@ pub async fn execute(message: Option<String>, ai: bool, amend: bool) -> Result<(
         Input::new().with_prompt("Commit message").interact_text()?
     };
 
-    let mut cmd = Command::new("git");
-    cmd.arg("commit");
+    print_info("Creating commit...");
 
-    if amend {
-        cmd.arg("--amend");
-    }
+    api::commit(repo, &config, &final_message, amend)?;
 
-    if config.commits.sign_commits {
-        cmd.arg("-S");
-        if let Some(key) = &config.commits.gpg_key {
-            cmd.arg("--gpg-sign").arg(key);
+    print_success(&format!(
+        "Commit created: {}",
+        final_message.lines().next().unwrap_or("")
+    ));
+
+    Ok(())
+}
+
+/// Generates a message from the staged diff and lets the user accept, edit
+/// or regenerate it. Returns `None` when the user cancels.
+fn generate_message(
+    repo: &dyn GitBackend,
+    config: &Config,
+    ai_config: &AiConfig,
+    amend: bool,
+) -> Result<Option<String>> {
+    let provider = ai::provider(ai_config)?;
+    let prompt = ai::prompt(repo, config, ai_config, amend)?;
+    if prompt.truncated {
+        print_info(&format!(
+            "The staged diff is over {} characters; sending a summary instead",
+            ai_config.max_diff_chars
+        ));
+    }
+    let interactive = atty::is(atty::Stream::Stdin);
+
+    loop {
+        let progress = spinner("Generating commit message...");
+        let generated = ai::generate(provider.as_ref(), &prompt, config);
+        progress.finish_and_clear();
+        let mut generated = generated?;
+
+        loop {
+            show_generated(&generated);
+            if !interactive {
+                if !generated.violations.is_empty() {
+                    bail!("The generated message does not follow the commit rules");
+                }
+                return Ok(Some(generated.message));
+            }
+
+            let choice = Select::new()
+                .with_prompt("Use this message?")
+                .items(&["Accept", "Edit", "Regenerate", "Cancel"])
+                .default(if generated.violations.is_empty() {
+                    0
+                } else {
+                    1
+                })
+                .interact()?;
+            match choice {
+                0 => return Ok(Some(generated.message)),
+                1 => {
+                    let Some(edited) = Editor::new().edit(&generated.message)? else {
+                        continue;
+                    };
+                    let edited = ai::clean_message(&edited);
+                    if edited.is_empty() {
+                        print_warning("The message is empty");
+                        continue;
+                    }
+                    generated = GeneratedMessage::new(edited, config);
+                }
+                2 => break,
+                _ => return Ok(None),
+            }
         }
     }
+}
 
-    cmd.arg("-m").arg(&final_message);
-
-    print_info("Creating commit...");
+fn show_generated(generated: &GeneratedMessage) {
+    println!();
+    for line in generated.message.lines() {
+        println!("  {}", line.bold());
+    }
+    println!();
+    for violation in &generated.violations {
+        print_warning(&format!(
+            "{} ({})",
+            violation.message,
+            violation.rule.dimmed()
+        ));
+    }
+}
 
-    let output = cmd.output().context("Failed to execute git commit")?;
+/// Asks for the scope, offering `commits.scopes` when set and starting from
+/// the scope the staged files map to.
+fn select_scope(repo: &dyn GitBackend, config: &Config) -> Result<Option<String>> {
+    let detected = api::staged_scope(repo, config)?;
+    let scopes = &config.commits.scopes;
 
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        bail!("Git commit failed: {}", error);
+    if scopes.is_empty() {
+        let value: String = Input::new()
+            .with_prompt("Scope (optional)")
+            .with_initial_text(detected.unwrap_or_default())
+            .allow_empty(true)
+            .interact_text()?;
+        return Ok((!value.is_empty()).then_some(value));
     }
 
-    print_success(&format!(
-        "Commit created: {}",
-        final_message.lines().next().unwrap_or("")
-    ));
+    let mut items = vec!["(none)".to_string()];
+    items.extend(scopes.iter().cloned());
+    let default = detected
+        .and_then(|detected| scopes.iter().position(|scope| *scope == detected))
+        .map_or(0, |index| index + 1);
+    let index = Select::new()
+        .with_prompt("Scope")
+        .items(&items)
+        .default(default)
+        .interact()?;
+    Ok((index > 0).then(|| scopes[index - 1].clone()))
+}
 
-    Ok(())
+fn optional_input(prompt: &str) -> Result<Option<String>> {
+    let value: String = Input::new()
+        .with_prompt(prompt)
+        .allow_empty(true)
+        .interact_text()?;
+    Ok((!value.is_empty()).then_some(value))
 }
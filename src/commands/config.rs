@@ -1,15 +1,18 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use colored::*;
+use similar::{ChangeTag, TextDiff};
 use std::fs;
-use std::path::Path;
+use std::path::PathBuf;
 use std::process::Command;
 
-use crate::config::Config;
-use crate::utils::{print_info, print_success};
+use crate::config::migrate::CURRENT_VERSION;
+use crate::config::schema::config_schema;
+use crate::config::validate::{self, Diagnostic};
+use crate::config::{Config, ConfigDocument, ConfigLayer, ConfigPaths, LayeredConfig};
+use crate::git::GitBackend;
+use crate::utils::{print_error, print_info, print_success, print_warning};
 
 pub async fn execute(_show: bool, edit: bool, reset: bool) -> Result<()> {
-    let config_path = Path::new(".gwf.toml");
-
     if reset {
         print_info("Resetting configuration to defaults...");
         let default_config = Config::default();
@@ -23,7 +26,7 @@ pub async fn execute(_show: bool, edit: bool, reset: bool) -> Result<()> {
         print_info(&format!("Opening configuration in {}...", editor));
 
         Command::new(editor)
-            .arg(".gwf.toml")
+            .arg(ConfigPaths::discover().repository)
             .status()
             .context("Failed to open editor")?;
 
@@ -31,15 +34,245 @@ pub async fn execute(_show: bool, edit: bool, reset: bool) -> Result<()> {
         return Ok(());
     }
 
-    if !config_path.exists() {
-        print_info("No configuration file found. Run 'gwf init' to create one.");
-        return Ok(());
+    let layered = LayeredConfig::load()?;
+
+    println!("{}", "Effective configuration:".bright_white().underline());
+    for (key, value, layer) in layered.entries() {
+        println!(
+            "  {} = {} {}",
+            key.bright_cyan(),
+            value,
+            format!("({})", layer).bright_black()
+        );
+    }
+
+    println!("\n{}", "Configuration files:".bright_white().underline());
+    for (layer, path) in layered.paths.files() {
+        let state = if path.exists() {
+            "found".green()
+        } else {
+            "not found".bright_black()
+        };
+        println!("  {:<10} {} ({})", layer.to_string(), path.display(), state);
+    }
+    let profile = match &layered.profile {
+        Some(profile) => match &profile.matched_remote {
+            Some(url) => format!("{} (matched {})", profile.name, url),
+            None => format!("{} (selected by name)", profile.name),
+        },
+        None => "none".bright_black().to_string(),
+    };
+    println!("  {:<10} {}", ConfigLayer::Profile.to_string(), profile);
+    println!(
+        "  {:<10} GWF_<SECTION>_<KEY> variables",
+        ConfigLayer::Environment.to_string()
+    );
+
+    Ok(())
+}
+
+/// Reports every problem in the configuration and fails if any is an error,
+/// so it can gate commits or CI.
+pub async fn validate(repo: Option<&dyn GitBackend>) -> Result<()> {
+    let paths = ConfigPaths::discover();
+    let mut diagnostics = Vec::new();
+
+    for (_, path) in paths.files() {
+        if !path.exists() {
+            continue;
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        diagnostics.extend(validate::check_file(path, &content));
+    }
+
+    // Semantic checks need a configuration that loads.
+    if !diagnostics.iter().any(Diagnostic::is_error) {
+        let layered = LayeredConfig::load_paths(paths)?;
+        diagnostics.extend(validate::check_semantics(&layered, repo));
+    }
+
+    for diagnostic in &diagnostics {
+        if diagnostic.is_error() {
+            print_error(&diagnostic.to_string());
+        } else {
+            print_warning(&diagnostic.to_string());
+        }
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    if errors > 0 {
+        bail!(
+            "Configuration has {} error(s) and {} warning(s)",
+            errors,
+            diagnostics.len() - errors
+        );
     }
 
-    let content = fs::read_to_string(config_path).context("Failed to read configuration file")?;
+    print_success(&format!(
+        "Configuration is valid ({} warning(s))",
+        diagnostics.len()
+    ));
+
+    Ok(())
+}
+
+/// Upgrades every configuration file to the current schema version, or
+/// shows what would change.
+pub async fn migrate(dry_run: bool) -> Result<()> {
+    let paths = ConfigPaths::discover();
+    let mut pending = 0;
+
+    for (_, path) in paths.files() {
+        if !path.exists() {
+            continue;
+        }
+
+        let mut document = ConfigDocument::open(path)?;
+        let before = document.to_string();
+        let report = document.migrate()?;
+
+        if report.is_noop() {
+            print_info(&format!("{} is up to date", path.display()));
+            continue;
+        }
 
-    println!("{}", "Current configuration:".bright_white().underline());
-    println!("{}", content);
+        pending += 1;
+        println!(
+            "\n{} {} (version {} -> {})",
+            "Migrating".bright_white().bold(),
+            path.display(),
+            report.from,
+            CURRENT_VERSION
+        );
+        for description in &report.applied {
+            println!("  - {}", description);
+        }
+
+        if dry_run {
+            print_diff(&before, &document.to_string());
+        } else {
+            document.save()?;
+            print_success(&format!("Updated {}", path.display()));
+        }
+    }
+
+    if dry_run && pending > 0 {
+        print_info("Dry run - run 'gwf config migrate' to apply these changes");
+    } else if pending == 0 {
+        print_success("All configuration files use the current schema");
+    }
 
     Ok(())
 }
+
+fn print_diff(before: &str, after: &str) {
+    let diff = TextDiff::from_lines(before, after);
+    for change in diff.iter_all_changes() {
+        let line = change.to_string_lossy();
+        let line = line.trim_end_matches('\n');
+        match change.tag() {
+            ChangeTag::Delete => println!("{}", format!("-{}", line).red()),
+            ChangeTag::Insert => println!("{}", format!("+{}", line).green()),
+            ChangeTag::Equal => println!(" {}", line),
+        }
+    }
+}
+
+/// Prints the schema as JSON so it can be redirected into a file.
+pub fn schema() -> Result<()> {
+    let schema = serde_json::to_string_pretty(&config_schema())
+        .context("Failed to serialize configuration schema")?;
+    println!("{}", schema);
+    Ok(())
+}
+
+/// Prints the effective value of a key, or every key below a section.
+pub async fn get(key: String) -> Result<()> {
+    let layered = LayeredConfig::load()?;
+    let section = format!("{}.", key);
+
+    let mut found = false;
+    for (entry_key, value, layer) in layered.entries() {
+        if entry_key == key {
+            println!("{}", display_value(value));
+            return Ok(());
+        }
+        if entry_key.starts_with(&section) {
+            found = true;
+            println!(
+                "{} = {} {}",
+                entry_key,
+                value,
+                format!("({})", layer).bright_black()
+            );
+        }
+    }
+
+    if !found {
+        bail!("Configuration key '{}' is not set", key);
+    }
+
+    Ok(())
+}
+
+pub async fn set(key: String, value: String, global: bool, local: bool) -> Result<()> {
+    let path = target_file(global, local)?;
+    let mut document = ConfigDocument::open(&path)?;
+
+    document.set(&key, &value)?;
+    document.save()?;
+
+    print_success(&format!(
+        "Set {} = {} in {}",
+        key.bright_cyan(),
+        value,
+        path.display()
+    ));
+
+    Ok(())
+}
+
+pub async fn unset(key: String, global: bool, local: bool) -> Result<()> {
+    let path = target_file(global, local)?;
+    if !path.exists() {
+        print_info(&format!("{} does not exist", path.display()));
+        return Ok(());
+    }
+
+    let mut document = ConfigDocument::open(&path)?;
+    if document.unset(&key)? {
+        document.save()?;
+        print_success(&format!(
+            "Removed {} from {}",
+            key.bright_cyan(),
+            path.display()
+        ));
+    } else {
+        print_info(&format!("{} is not set in {}", key, path.display()));
+    }
+
+    Ok(())
+}
+
+fn target_file(global: bool, local: bool) -> Result<PathBuf> {
+    let paths = ConfigPaths::discover();
+
+    if global {
+        paths
+            .global
+            .context("Could not determine the global configuration directory")
+    } else if local {
+        Ok(paths.local)
+    } else {
+        Ok(paths.repository)
+    }
+}
+
+/// Strings are printed bare so `gwf config get` composes with shell scripts.
+fn display_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
@@ -1,16 +1,25 @@
 use anyhow::{bail, Result};
 use colored::*;
 use dialoguer::Confirm;
+use std::path::PathBuf;
 
+use super::worktree;
+use crate::api::{self, FeatureOptions};
 use crate::config::Config;
-use crate::git::GitRepo;
+use crate::git::GitBackend;
 use crate::utils::{print_info, print_success, print_warning};
 
-pub async fn execute(name: String, from: Option<String>, push: bool) -> Result<()> {
+pub async fn execute(
+    repo: &mut dyn GitBackend,
+    name: String,
+    from: Option<String>,
+    push: bool,
+    worktree: Option<Option<PathBuf>>,
+) -> Result<()> {
     let config = Config::load()?;
-    let repo = GitRepo::open_current()?;
 
-    if repo.has_uncommitted_changes()? {
+    // A new worktree starts clean, so local changes can stay where they are.
+    if worktree.is_none() && repo.has_uncommitted_changes()? {
         print_warning("You have uncommitted changes.");
 
         if atty::is(atty::Stream::Stdin) {
@@ -21,38 +30,38 @@ pub async fn execute(name: String, from: Option<String>, push: bool) -> Result<(
             if !proceed {
                 bail!("Operation cancelled");
             }
-
-            print_info("Stashing changes...");
-        } else {
-            print_info("Proceeding with uncommitted changes (non-interactive mode)");
         }
     }
 
-    let base_branch = from.unwrap_or_else(|| {
-        config
-            .workflows
-            .develop_branch
-            .unwrap_or(config.workflows.main_branch)
-    });
+    let report = api::start_feature(
+        repo,
+        &config,
+        &FeatureOptions {
+            name,
+            from,
+            push,
+            checkout: worktree::checkout(worktree),
+        },
+    )?;
+    let branch = &report.branch;
 
     print_info(&format!(
-        "Creating feature branch from '{}'",
-        base_branch.bright_cyan()
-    ));
-
-    let branch_name = format!("{}{}", config.workflows.feature_branch_prefix, name);
-
-    repo.create_branch(&branch_name, Some(&base_branch))?;
-    repo.checkout(&branch_name)?;
-
-    print_success(&format!(
-        "Created and switched to branch '{}'",
-        branch_name.bright_green()
+        "Created feature branch from '{}'",
+        branch.base.bright_cyan()
     ));
+    worktree::print_started(
+        branch,
+        &format!(
+            "Created and switched to branch '{}'",
+            branch.name.bright_green()
+        ),
+    );
 
-    if push {
-        print_info("Pushing branch to remote...");
-        print_success("Branch pushed to remote");
+    if let Some(remote) = &report.pushed_to {
+        print_success(&format!(
+            "Branch pushed and tracking '{}/{}'",
+            remote, branch.name
+        ));
     }
 
     println!("\n{}", "Next steps:".bright_white().underline());
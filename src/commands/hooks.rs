@@ -0,0 +1,73 @@
+use anyhow::Result;
+use colored::*;
+use std::io::Read;
+
+use crate::api::{self, hooks::STDIN_HOOKS, HookChanges};
+use crate::config::Config;
+use crate::git::GitRepo;
+use crate::utils::{print_info, print_success};
+
+pub async fn install(repo: &GitRepo) -> Result<()> {
+    let config = Config::load()?;
+    let changes = api::install_hooks(repo, &config)?;
+
+    if let Some(path) = &changes.hooks_path {
+        print_info(&format!("Set core.hooksPath to {}", path.bright_cyan()));
+    }
+    for hook in &changes.chained {
+        print_info(&format!(
+            "Kept the existing {} hook; it runs before gwf's",
+            hook.bright_cyan()
+        ));
+    }
+    print_removed(&changes);
+    if changes.installed.is_empty() {
+        print_info("No hooks have commands in [hooks]");
+    } else {
+        print_success(&format!(
+            "Installed {} in {}",
+            changes.installed.join(", ").bright_green(),
+            changes.dir.display()
+        ));
+    }
+    Ok(())
+}
+
+pub async fn uninstall(repo: &GitRepo) -> Result<()> {
+    let config = Config::load()?;
+    let changes = api::uninstall_hooks(repo, &config)?;
+
+    if changes.removed.is_empty() {
+        print_info(&format!("No gwf hooks in {}", changes.dir.display()));
+    } else {
+        print_removed(&changes);
+    }
+    if let Some(path) = &changes.hooks_path {
+        print_info(&format!("Unset core.hooksPath ({})", path));
+    }
+    Ok(())
+}
+
+fn print_removed(changes: &HookChanges) {
+    for hook in &changes.removed {
+        if changes.restored.contains(hook) {
+            print_success(&format!(
+                "Restored the previous {} hook",
+                hook.bright_cyan()
+            ));
+        } else {
+            print_success(&format!("Removed the {} hook", hook.bright_cyan()));
+        }
+    }
+}
+
+/// Entry point of the hook shims; git passes the hook's arguments and, for
+/// some hooks, data on stdin.
+pub async fn run(repo: &GitRepo, hook: String, args: Vec<String>) -> Result<()> {
+    let config = Config::load()?;
+    let mut stdin = Vec::new();
+    if STDIN_HOOKS.contains(&hook.as_str()) {
+        std::io::stdin().read_to_end(&mut stdin)?;
+    }
+    api::run_hook(repo, &config, &hook, &args, &stdin)
+}
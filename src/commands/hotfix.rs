@@ -1,30 +1,42 @@
 use anyhow::Result;
 use colored::*;
+use std::path::PathBuf;
 
+use super::worktree;
+use crate::api::{self, HotfixOptions};
 use crate::config::Config;
-use crate::git::GitRepo;
-use crate::utils::{print_info, print_success};
-
-pub async fn execute(name: String, target: Option<String>) -> Result<()> {
+use crate::git::GitBackend;
+use crate::utils::print_info;
+
+pub async fn execute(
+    repo: &mut dyn GitBackend,
+    name: String,
+    target: Option<String>,
+    worktree: Option<Option<PathBuf>>,
+) -> Result<()> {
     let config = Config::load()?;
-    let repo = GitRepo::open_current()?;
 
-    let target_branch = target.unwrap_or_else(|| config.workflows.main_branch.clone());
+    let branch = api::start_hotfix(
+        repo,
+        &config,
+        &HotfixOptions {
+            name,
+            target,
+            checkout: worktree::checkout(worktree),
+        },
+    )?;
 
     print_info(&format!(
-        "Creating hotfix from '{}'",
-        target_branch.bright_cyan()
-    ));
-
-    let branch_name = format!("{}{}", config.workflows.hotfix_branch_prefix, name);
-
-    repo.create_branch(&branch_name, Some(&target_branch))?;
-    repo.checkout(&branch_name)?;
-
-    print_success(&format!(
-        "Created and switched to hotfix branch '{}'",
-        branch_name.bright_green()
+        "Created hotfix from '{}'",
+        branch.base.bright_cyan()
     ));
+    worktree::print_started(
+        &branch,
+        &format!(
+            "Created and switched to hotfix branch '{}'",
+            branch.name.bright_green()
+        ),
+    );
 
     println!("\n{}", "Hotfix workflow:".bright_white().underline());
     println!("  1. Make your emergency fixes");
@@ -32,7 +44,7 @@ pub async fn execute(name: String, target: Option<String>) -> Result<()> {
     println!("  3. Run 'gwf commit' to commit changes");
     println!(
         "  4. Run 'gwf pr --target {}' to create a pull request",
-        target_branch
+        branch.base
     );
     println!("  5. After merge, run 'gwf release patch' to tag the release");
 
@@ -1,46 +1,92 @@
 use anyhow::{bail, Context, Result};
 use colored::*;
+use dialoguer::{Confirm, Input};
 use std::fs;
 use std::path::Path;
 
-use crate::config::Config;
-use crate::git::GitRepo;
+use crate::config::detect::{Conventions, Detected, RepoFacts};
+use crate::config::layers::CONFIG_FILE;
+use crate::config::{presets, validate, Config, ConfigDocument};
+use crate::git::GitBackend;
+use crate::utils::{print_info, print_warning};
 
-pub async fn execute(force: bool) -> Result<()> {
+pub async fn execute(
+    repo: &dyn GitBackend,
+    force: bool,
+    yes: bool,
+    template: Option<String>,
+) -> Result<()> {
     println!(
         "{}",
         "Initializing GWF in current repository...".bright_blue()
     );
 
-    let repo = GitRepo::open_current().context("Not in a git repository. Run 'git init' first.")?;
-
-    let config_path = Path::new(".gwf.toml");
+    let root = repo.workdir()?;
+    let config_path = root.join(CONFIG_FILE);
     if config_path.exists() && !force {
         bail!("GWF is already initialized. Use --force to reinitialize.");
     }
 
-    let config = Config::default();
-    let config_content = toml::to_string_pretty(&config)?;
+    if let Some(template) = template {
+        write_template(&template, &config_path)?;
+    } else {
+        let mut conventions = Conventions::detect(&RepoFacts::gather(repo)?);
+        print_conventions(&conventions);
+
+        if !yes {
+            if !atty::is(atty::Stream::Stdin) {
+                bail!(
+                    "Interactive mode requires a terminal. Use --yes to accept the detected settings"
+                );
+            }
+            prompt_conventions(&mut conventions)?;
+        }
 
-    fs::write(config_path, config_content).context("Failed to write configuration file")?;
+        let mut config = Config::default();
+        conventions.apply(&mut config);
+        config.save_to(&config_path)?;
+    }
 
-    let gitignore_path = Path::new(".gitignore");
+    // .gwf.toml is shared with the team; only the personal overrides are ignored.
+    let gitignore_path = root.join(".gitignore");
     if gitignore_path.exists() {
-        let gitignore = fs::read_to_string(gitignore_path)?;
-        if !gitignore.contains(".gwf.toml") {
+        let gitignore = fs::read_to_string(&gitignore_path)?;
+        if gitignore.lines().any(|line| line.trim() == ".gwf.toml") {
+            let updated: Vec<&str> = gitignore
+                .lines()
+                .map(|line| {
+                    if line.trim() == ".gwf.toml" {
+                        ".gwf.local.toml"
+                    } else {
+                        line
+                    }
+                })
+                .collect();
+            fs::write(&gitignore_path, format!("{}\n", updated.join("\n")))?;
+            println!(
+                "  {} Replaced .gwf.toml with .gwf.local.toml in .gitignore",
+                "✓".green()
+            );
+        } else if !gitignore.contains(".gwf.local.toml") {
             fs::write(
                 gitignore_path,
-                format!("{}\n# GWF configuration\n.gwf.toml\n", gitignore),
+                format!(
+                    "{}\n# GWF personal configuration\n.gwf.local.toml\n",
+                    gitignore
+                ),
             )?;
-            println!("  {} Added .gwf.toml to .gitignore", "✓".green());
+            println!("  {} Added .gwf.local.toml to .gitignore", "✓".green());
         }
     }
 
-    let current_branch = repo.current_branch()?;
+    let state = repo.state()?;
     let remotes = repo.list_remotes()?;
 
     println!("\n{}", "Repository Information:".bright_white().underline());
-    println!("  Current branch: {}", current_branch.bright_yellow());
+    println!(
+        "  Current branch: {}",
+        state.head.to_string().bright_yellow()
+    );
     println!(
         "  Remotes: {}",
         if remotes.is_empty() {
@@ -52,9 +98,122 @@ pub async fn execute(force: bool) -> Result<()> {
 
     println!("\n{} GWF initialized successfully!", "✓".green().bold());
     println!("\n{}", "Next steps:".bright_white());
-    println!("  1. Review and customize .gwf.toml");
+    println!("  1. Review and commit .gwf.toml (personal overrides go in .gwf.local.toml)");
     println!("  2. Run 'gwf feature <name>' to start a new feature");
     println!("  3. Run 'gwf --help' to see all available commands");
 
     Ok(())
 }
+
+/// Writes a built-in preset, or copies a configuration file such as a
+/// company-wide standard, comments included.
+fn write_template(template: &str, config_path: &Path) -> Result<()> {
+    if let Some(preset) = presets::find(template) {
+        print_info(&format!(
+            "Using the {} preset: {}",
+            preset.name, preset.description
+        ));
+        return preset.config().save_to(config_path);
+    }
+
+    let path = Path::new(template);
+    if !path.is_file() {
+        let names: Vec<&str> = presets::PRESETS.iter().map(|p| p.name).collect();
+        bail!(
+            "Unknown template '{}'. Use one of {} or the path to a configuration file",
+            template,
+            names.join(", ")
+        );
+    }
+
+    let mut document = ConfigDocument::open(path)?;
+    document.migrate()?;
+    let content = document.to_string();
+    for diagnostic in validate::check_file(path, &content) {
+        if diagnostic.is_error() {
+            bail!("Invalid template: {}", diagnostic);
+        }
+        print_warning(&diagnostic.to_string());
+    }
+
+    print_info(&format!("Using template {}", path.display()));
+    fs::write(config_path, content)
+        .with_context(|| format!("Failed to write {}", config_path.display()))
+}
+
+fn print_conventions(conventions: &Conventions) {
+    fn row<T: std::fmt::Display>(label: &str, detected: &Detected<T>) {
+        println!(
+            "  {:<16} {} {}",
+            label,
+            detected.value.to_string().bright_yellow(),
+            format!("({})", detected.reason).bright_black()
+        );
+    }
+
+    let develop = Detected {
+        value: conventions
+            .develop_branch
+            .value
+            .clone()
+            .unwrap_or_else(|| "none".to_string()),
+        reason: conventions.develop_branch.reason.clone(),
+    };
+
+    println!("\n{}", "Detected settings:".bright_white().underline());
+    row("Main branch", &conventions.main_branch);
+    row("Develop branch", &develop);
+    row("Feature prefix", &conventions.feature_branch_prefix);
+    row("Hotfix prefix", &conventions.hotfix_branch_prefix);
+    row("Release prefix", &conventions.release_branch_prefix);
+    row("Conventional", &conventions.conventional);
+    row("Sign commits", &conventions.sign_commits);
+    println!();
+}
+
+/// Lets the user confirm or change each detected value.
+fn prompt_conventions(conventions: &mut Conventions) -> Result<()> {
+    conventions.main_branch.value = Input::new()
+        .with_prompt("Main branch")
+        .default(conventions.main_branch.value.clone())
+        .interact_text()?;
+
+    let develop: String = Input::new()
+        .with_prompt("Develop branch (empty for none)")
+        .default(conventions.develop_branch.value.clone().unwrap_or_default())
+        .allow_empty(true)
+        .interact_text()?;
+    conventions.develop_branch.value = Some(develop.trim().to_string()).filter(|b| !b.is_empty());
+
+    for (prompt, prefix) in [
+        (
+            "Feature branch prefix",
+            &mut conventions.feature_branch_prefix,
+        ),
+        (
+            "Hotfix branch prefix",
+            &mut conventions.hotfix_branch_prefix,
+        ),
+        (
+            "Release branch prefix",
+            &mut conventions.release_branch_prefix,
+        ),
+    ] {
+        prefix.value = Input::new()
+            .with_prompt(prompt)
+            .default(prefix.value.clone())
+            .interact_text()?;
+    }
+
+    conventions.conventional.value = Confirm::new()
+        .with_prompt("Use Conventional Commits?")
+        .default(conventions.conventional.value)
+        .interact()?;
+
+    conventions.sign_commits.value = Confirm::new()
+        .with_prompt("Sign commits?")
+        .default(conventions.sign_commits.value)
+        .interact()?;
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use anyhow::{bail, Result};
+use colored::*;
+
+use crate::api;
+use crate::config::Config;
+use crate::git::GitBackend;
+use crate::utils::print_success;
+
+pub async fn execute(repo: &dyn GitBackend, range: Option<String>, json: bool) -> Result<()> {
+    let config = Config::load()?;
+    let report = api::lint_commits(repo, &config, range.as_deref())?;
+    let failures = report.failures().count();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if report.commits.is_empty() {
+        println!("No commits to check in {}", report.range.bright_cyan());
+    } else {
+        for commit in report.failures() {
+            println!(
+                "{} {}",
+                commit.id[..7].yellow(),
+                commit.summary.bright_white()
+            );
+            for violation in &commit.violations {
+                println!(
+                    "  {} {}: {}",
+                    "✗".red(),
+                    violation.rule.bright_red(),
+                    violation.message
+                );
+            }
+        }
+        if failures == 0 {
+            print_success(&format!(
+                "All {} commit(s) in {} follow the rules",
+                report.commits.len(),
+                report.range
+            ));
+        }
+    }
+
+    if failures > 0 {
+        bail!(
+            "{} of {} commit(s) in {} break the commit rules",
+            failures,
+            report.commits.len(),
+            report.range
+        );
+    }
+    Ok(())
+}
@@ -4,13 +4,17 @@ pub mod commit;
 pub mod completions;
 pub mod config;
 pub mod feature;
+pub mod hooks;
 pub mod hotfix;
 pub mod init;
+pub mod lint;
 pub mod pr;
 pub mod release;
 pub mod standup;
+pub mod stash;
 pub mod stats;
 pub mod status;
 pub mod sync;
 pub mod tag;
 pub mod undo;
+pub mod worktree;
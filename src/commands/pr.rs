@@ -1,22 +1,19 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
 use dialoguer::Input;
-use std::process::Command;
 
+use crate::api::{self, PullRequest, PullRequestOptions};
 use crate::config::Config;
-use crate::git::GitRepo;
+use crate::git::GitBackend;
 use crate::utils::{print_info, print_success};
 
-pub async fn execute(title: Option<String>, target: Option<String>, draft: bool) -> Result<()> {
+pub async fn execute(
+    repo: &dyn GitBackend,
+    title: Option<String>,
+    target: Option<String>,
+    draft: bool,
+) -> Result<()> {
     let config = Config::load()?;
-    let repo = GitRepo::open_current()?;
-
-    let current_branch = repo.current_branch()?;
-
-    if current_branch == config.workflows.main_branch {
-        bail!("Cannot create PR from main branch");
-    }
-
-    let target_branch = target.unwrap_or_else(|| config.workflows.main_branch.clone());
+    let current_branch = api::pull_request_branch(repo, &config)?;
 
     let pr_title = if let Some(t) = title {
         t
@@ -29,48 +26,24 @@ pub async fn execute(title: Option<String>, target: Option<String>, draft: bool)
         current_branch.clone()
     };
 
-    print_info("Creating pull request...");
-
-    // FIXME: add support for GitLab and Bitbucket
-    let gh_available = which::which("gh").is_ok();
-
-    if gh_available {
-        let mut cmd = Command::new("gh");
-        cmd.arg("pr")
-            .arg("create")
-            .arg("--title")
-            .arg(&pr_title)
-            .arg("--base")
-            .arg(&target_branch);
-
-        if draft {
-            cmd.arg("--draft");
+    print_info(&format!(
+        "Pushing '{}' to '{}' and creating pull request...",
+        current_branch, config.remote.push
+    ));
+    let options = PullRequestOptions {
+        title: pr_title,
+        target,
+        draft,
+    };
+    match api::pull_request(repo, &config, &options)? {
+        PullRequest::Created { url } => {
+            print_success(&format!("Pull request created: {}", url));
         }
-
-        let output = cmd
-            .output()
-            .context("Failed to create PR with GitHub CLI")?;
-
-        if output.status.success() {
-            let url = String::from_utf8_lossy(&output.stdout);
-            print_success(&format!("Pull request created: {}", url.trim()));
-        } else {
-            let error = String::from_utf8_lossy(&output.stderr);
-            bail!("Failed to create PR: {}", error);
+        PullRequest::Compare { url } => {
+            print_info("GitHub CLI not found. Opening browser...");
+            webbrowser::open(&url).context("Failed to open browser")?;
+            print_success("Browser opened with PR creation page");
         }
-    } else {
-        print_info("GitHub CLI not found. Opening browser...");
-
-        let url = format!(
-            "https://github.com/user/repo/compare/{}...{}?title={}",
-            target_branch,
-            current_branch,
-            urlencoding::encode(&pr_title)
-        );
-
-        webbrowser::open(&url).context("Failed to open browser")?;
-
-        print_success("Browser opened with PR creation page");
     }
 
     Ok(())
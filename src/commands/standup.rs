@@ -1,13 +1,13 @@
-use anyhow::{Context, Result};
-use chrono::{Duration, Local};
+use anyhow::Result;
+use chrono::Local;
 use colored::*;
-use std::process::Command;
 
-use crate::git::GitRepo;
-use crate::utils::print_info;
+use crate::api::{self, StandupOptions};
+use crate::git::GitBackend;
+use crate::utils::{format_relative_time, print_info};
 
-pub async fn execute(days: u32, all: bool) -> Result<()> {
-    let repo = GitRepo::open_current()?;
+pub async fn execute(repo: &dyn GitBackend, days: u32, all: bool) -> Result<()> {
+    let report = api::standup(repo, &StandupOptions { days, all })?;
 
     println!(
         "{}",
@@ -22,84 +22,47 @@ pub async fn execute(days: u32, all: bool) -> Result<()> {
     );
     println!();
 
-    let since_date = Local::now() - Duration::days(days as i64);
-    let since_str = since_date.format("%Y-%m-%d").to_string();
-
-    let mut cmd = Command::new("git");
-    cmd.arg("log")
-        .arg("--since")
-        .arg(&since_str)
-        .arg("--author")
-        .arg(get_git_user()?)
-        .arg("--pretty=format:%h|%s|%cr|%b")
-        .arg("--no-merges");
-
-    if !all {
-        cmd.arg("--branches");
-    }
-
-    let output = cmd.output().context("Failed to get git log")?;
-
-    let log_output = String::from_utf8_lossy(&output.stdout);
-
-    if log_output.is_empty() {
+    if report.commits.is_empty() {
         print_info(&format!("No commits in the last {} day(s)", days));
     } else {
         println!("{}", "Recent commits:".bright_white().underline());
-        for line in log_output.lines() {
-            let parts: Vec<&str> = line.split('|').collect();
-            if parts.len() >= 3 {
-                let hash = parts[0];
-                let message = parts[1];
-                let time = parts[2];
-
-                println!(
-                    "  {} {} {}",
-                    hash.bright_yellow(),
-                    message.bright_white(),
-                    format!("({})", time).bright_black()
-                );
-            }
+        for commit in &report.commits {
+            println!(
+                "  {} {} {}",
+                commit.short_id.bright_yellow(),
+                commit.summary.bright_white(),
+                format!("({})", format_relative_time(commit.time)).bright_black()
+            );
         }
     }
 
     println!("\n{}", "Current status:".bright_white().underline());
-    let current_branch = repo.current_branch()?;
-    println!("  Branch: {}", current_branch.bright_cyan());
+    let state = &report.state;
+    println!("  Branch: {}", state.head.to_string().bright_cyan());
+    if let Some(operation) = &state.operation {
+        println!("  {} {} in progress", "⚠".yellow(), operation);
+    }
 
-    if repo.has_uncommitted_changes()? {
+    if report.uncommitted_changes {
         println!("  {} Uncommitted changes", "⚠".yellow());
     } else {
         println!("  {} Working directory clean", "✓".green());
     }
 
-    let mut cmd = Command::new("git");
-    cmd.arg("for-each-ref")
-        .arg("--sort=-committerdate")
-        .arg("--format=%(refname:short)|%(committerdate:relative)")
-        .arg("refs/heads/")
-        .arg("--count=5");
-
-    let output = cmd.output().context("Failed to get branch list")?;
-
-    let branches = String::from_utf8_lossy(&output.stdout);
-
-    if !branches.is_empty() {
+    if !report.recent_branches.is_empty() {
         println!("\n{}", "Recent branches:".bright_white().underline());
-        for line in branches.lines() {
-            let parts: Vec<&str> = line.split('|').collect();
-            if parts.len() >= 2 {
-                let branch = parts[0];
-                let last_commit = parts[1];
-
-                let marker = if branch == current_branch { "*" } else { " " };
-                println!(
-                    "  {} {} {}",
-                    marker.bright_green(),
-                    branch.bright_cyan(),
-                    format!("({})", last_commit).bright_black()
-                );
-            }
+        for branch in &report.recent_branches {
+            let marker = if state.branch() == Some(branch.name.as_str()) {
+                "*"
+            } else {
+                " "
+            };
+            println!(
+                "  {} {} {}",
+                marker.bright_green(),
+                branch.name.bright_cyan(),
+                format!("({})", format_relative_time(branch.time)).bright_black()
+            );
         }
     }
 
@@ -110,12 +73,3 @@ pub async fn execute(days: u32, all: bool) -> Result<()> {
 
     Ok(())
 }
-
-fn get_git_user() -> Result<String> {
-    let output = Command::new("git")
-        .args(["config", "user.email"])
-        .output()
-        .context("Failed to get git user email")?;
-
-    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-}
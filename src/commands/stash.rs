@@ -0,0 +1,69 @@
+use anyhow::Result;
+use colored::*;
+
+use crate::api::stash::{is_autostash_for, stashes_by_branch};
+use crate::api::{BranchSwitch, StashRestore};
+use crate::git::GitBackend;
+use crate::utils::{format_error, print_info, print_warning};
+
+/// Reports the changes gwf stashed and restored when it switched to
+/// `branch`.
+pub fn print_switch(switch: &BranchSwitch, branch: &str) {
+    if let (Some(from), Some(_)) = (&switch.from, switch.stashed) {
+        print_stashed(from);
+    }
+    if let Some(restored) = &switch.restored {
+        print_restored(restored, branch);
+    }
+}
+
+pub fn print_stashed(branch: &str) {
+    print_info(&format!(
+        "Stashed uncommitted changes on '{}'",
+        branch.bright_cyan()
+    ));
+}
+
+/// A stash that no longer applies cleanly is kept and reported.
+pub fn print_restored(restored: &StashRestore, branch: &str) {
+    match restored {
+        StashRestore::Applied(_) => print_info(&format!(
+            "Restored the changes stashed on '{}'",
+            branch.bright_cyan()
+        )),
+        StashRestore::Failed(e) => print_warning(&format_error(e)),
+    }
+}
+
+pub async fn list(repo: &mut dyn GitBackend) -> Result<()> {
+    let groups = stashes_by_branch(repo)?;
+    if groups.is_empty() {
+        print_info("No stashed changes");
+        return Ok(());
+    }
+
+    println!("{}", "Stashes:".bright_white().underline());
+    for (branch, entries) in groups {
+        match &branch {
+            Some(branch) => println!("\n  {}", branch.bright_cyan()),
+            None => println!("\n  {}", "(detached HEAD)".dimmed()),
+        }
+        for entry in entries {
+            let automatic = branch
+                .as_deref()
+                .is_some_and(|branch| is_autostash_for(&entry, branch));
+            let message = if automatic {
+                "restored when gwf switches back".dimmed()
+            } else {
+                entry.message.normal()
+            };
+            println!(
+                "    {} {}",
+                format!("stash@{{{}}}", entry.index).yellow(),
+                message
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -2,33 +2,19 @@ use anyhow::Result;
 use colored::Colorize;
 use dialoguer::{Input, Select, Confirm};
 use semver::Version;
-use std::process::Command;
-use git2::Repository;
 
-pub async fn run() -> Result<()> {
-    let repo = Repository::open(".")?;
-    
-    let mut latest_version = Version::new(0, 1, 0);
-    let mut tags = Vec::new();
-    
-    repo.tag_foreach(|_oid, name| {
-        if let Ok(name_str) = std::str::from_utf8(name) {
-            let tag_name = name_str.trim_start_matches("refs/tags/");
-            tags.push(tag_name.to_string());
-            
-            let version_str = tag_name.trim_start_matches('v');
-            if let Ok(v) = Version::parse(version_str) {
-                if v > latest_version {
-                    latest_version = v;
-                }
-            }
-        }
-        true
-    })?;
+use crate::api::{self, Bump, TagOptions};
+use crate::config::Config;
+use crate::git::GitBackend;
+
+pub async fn run(repo: &dyn GitBackend) -> Result<()> {
+    let config = Config::load()?;
+    let latest = api::latest_version(repo)?;
     
-    if !tags.is_empty() {
-        println!("{} v{}", "Latest version:".cyan(), latest_version.to_string().yellow());
+    if let Some(version) = &latest {
+        println!("{} v{}", "Latest version:".cyan(), version.to_string().yellow());
     }
+    let latest_version = latest.unwrap_or_else(|| Version::new(0, 1, 0));
     
     let choices = vec![
         "Major version (breaking changes)",
@@ -46,24 +32,9 @@ pub async fn run() -> Result<()> {
         .interact()?;
     
     let new_version = match selection {
-        0 => {
-            let mut v = latest_version.clone();
-            v.major += 1;
-            v.minor = 0;
-            v.patch = 0;
-            v
-        },
-        1 => {
-            let mut v = latest_version.clone();
-            v.minor += 1;
-            v.patch = 0;
-            v
-        },
-        2 => {
-            let mut v = latest_version.clone();
-            v.patch += 1;
-            v
-        },
+        0 => Bump::Major.apply(&latest_version),
+        1 => Bump::Minor.apply(&latest_version),
+        2 => Bump::Patch.apply(&latest_version),
         3 => {
             let input: String = Input::new()
                 .with_prompt("Enter version (without v prefix)")
@@ -71,6 +42,7 @@ pub async fn run() -> Result<()> {
             Version::parse(&input)?
         },
         4 => {
+            let tags = repo.tags()?;
             if tags.is_empty() {
                 println!("{}", "No tags found".yellow());
             } else {
@@ -84,8 +56,7 @@ pub async fn run() -> Result<()> {
         _ => return Ok(())
     };
     
-    let tag_name = format!("v{}", new_version);
-    println!("\n{} {}", "Creating tag:".cyan(), tag_name.yellow());
+    println!("\n{} v{}", "Creating tag:".cyan(), new_version.to_string().yellow());
     
     let message: String = Input::new()
         .with_prompt("Tag message")
@@ -97,18 +68,16 @@ pub async fn run() -> Result<()> {
         .default(true)
         .interact()?;
     
-    // TODO: add GPG signing support
-    Command::new("git")
-        .args(&["tag", "-a", &tag_name, "-m", &message])
-        .status()?;
+    let report = api::create_tag(repo, &config, &TagOptions {
+        version: new_version,
+        message,
+        push,
+    })?;
     
-    println!("{} {}", "Created tag:".green(), tag_name);
+    println!("{} {}", "Created tag:".green(), report.name);
     
-    if push {
-        Command::new("git")
-            .args(&["push", "origin", &tag_name])
-            .status()?;
-        println!("{}", "Tag pushed to remote".green());
+    if let Some(remote) = &report.pushed_to {
+        println!("{} {}", "Tag pushed to".green(), remote);
     }
     
     Ok(())
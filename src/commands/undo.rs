@@ -1,10 +1,11 @@
 use anyhow::Result;
 use colored::Colorize;
 use dialoguer::{Select, Confirm};
-use std::process::Command;
 
-pub async fn run() -> Result<()> {
-    // TODO: add reflog support for more undo options
+use crate::api::{self, UndoAction};
+use crate::git::GitBackend;
+
+pub async fn run(repo: &dyn GitBackend) -> Result<()> {
     let choices = vec![
         "Undo last commit (keep changes)",
         "Undo last commit (discard changes)",
@@ -21,9 +22,7 @@ pub async fn run() -> Result<()> {
     
     match selection {
         0 => {
-            Command::new("git")
-                .args(&["reset", "--soft", "HEAD~1"])
-                .status()?;
+            api::undo(repo, UndoAction::LastCommit)?;
             println!("{}", "Last commit undone, changes kept".green());
         },
         1 => {
@@ -33,22 +32,16 @@ pub async fn run() -> Result<()> {
                 .interact()?;
             
             if confirm {
-                Command::new("git")
-                    .args(&["reset", "--hard", "HEAD~1"])
-                    .status()?;
+                api::undo(repo, UndoAction::DiscardLastCommit)?;
                 println!("{}", "Last commit undone, changes discarded".yellow());
             }
         },
         2 => {
-            Command::new("git")
-                .args(&["reset", "--hard", "ORIG_HEAD"])
-                .status()?;
+            api::undo(repo, UndoAction::LastMerge)?;
             println!("{}", "Last merge undone".green());
         },
         3 => {
-            Command::new("git")
-                .args(&["merge", "--abort"])
-                .status()?;
+            api::undo(repo, UndoAction::AbortMerge)?;
             println!("{}", "Merge aborted".green());
         },
         _ => {
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use colored::*;
+use std::path::{Path, PathBuf};
+
+use super::stash;
+use crate::api::{CheckedOut, Checkout, StartedBranch};
+use crate::git::GitBackend;
+use crate::utils::{print_info, print_success};
+
+/// The checkout requested with `--worktree [path]`.
+pub fn checkout(worktree: Option<Option<PathBuf>>) -> Checkout {
+    match worktree {
+        Some(path) => Checkout::Worktree(path),
+        None => Checkout::Switch,
+    }
+}
+
+/// Prints how `branch` was checked out, or `switched` after switching to
+/// it.
+pub fn print_started(branch: &StartedBranch, switched: &str) {
+    match &branch.checkout {
+        CheckedOut::Worktree(path) => print_created(&branch.name, path),
+        CheckedOut::Switched(switch) => {
+            stash::print_switch(switch, &branch.name);
+            print_success(switched);
+        }
+    }
+}
+
+/// Prints where the worktree for `branch` was created and how to get there.
+fn print_created(branch: &str, path: &Path) {
+    print_success(&format!(
+        "Created branch '{}' in worktree '{}'",
+        branch.bright_green(),
+        path.display()
+    ));
+    print_info(&format!(
+        "Run 'cd {}' to start working on it",
+        path.display()
+    ));
+}
+
+pub async fn list(repo: &dyn GitBackend) -> Result<()> {
+    let worktrees = repo.worktrees()?;
+    if worktrees.is_empty() {
+        print_info("No linked worktrees");
+        return Ok(());
+    }
+
+    println!("{}", "Worktrees:".bright_white().underline());
+    for worktree in worktrees {
+        let branch = match &worktree.branch {
+            Some(branch) => branch.bright_cyan(),
+            None => "(detached)".dimmed(),
+        };
+        let mut notes = Vec::new();
+        if worktree.locked {
+            notes.push("locked");
+        }
+        if worktree.prunable {
+            notes.push("missing, run 'gwf worktree prune'");
+        }
+
+        print!("  {} {}", worktree.path.display(), branch);
+        if !notes.is_empty() {
+            print!(" {}", format!("[{}]", notes.join(", ")).yellow());
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+pub async fn remove(repo: &dyn GitBackend, target: String, force: bool) -> Result<()> {
+    let worktree = repo.remove_worktree(&target, force)?;
+    print_success(&format!("Removed worktree '{}'", worktree.path.display()));
+    Ok(())
+}
+
+pub async fn prune(repo: &dyn GitBackend) -> Result<()> {
+    let pruned = repo.prune_worktrees()?;
+    if pruned.is_empty() {
+        print_info("No stale worktrees to prune");
+    }
+    for worktree in pruned {
+        print_success(&format!("Pruned worktree '{}'", worktree.name));
+    }
+    Ok(())
+}
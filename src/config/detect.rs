@@ -0,0 +1,321 @@
+use anyhow::Result;
+use regex::Regex;
+
+use super::Config;
+use crate::git::{GitBackend, LogOptions};
+
+const MAIN_CANDIDATES: &[&str] = &["main", "master", "trunk"];
+const DEVELOP_CANDIDATES: &[&str] = &["develop", "development", "dev"];
+const FEATURE_PREFIXES: &[&str] = &["feature/", "feat/", "features/", "feature-", "feat-"];
+const HOTFIX_PREFIXES: &[&str] = &["hotfix/", "fix/", "bugfix/", "hotfix-"];
+const RELEASE_PREFIXES: &[&str] = &["release/", "releases/", "rel/", "release-"];
+
+/// How many recent commits are checked for Conventional Commits, and how many
+/// are needed before the history is trusted over the default.
+const COMMIT_SAMPLE: usize = 100;
+const MIN_COMMITS: usize = 5;
+
+/// What `gwf init` reads from the repository before suggesting settings.
+#[derive(Debug, Default)]
+pub struct RepoFacts {
+    /// The branch `origin/HEAD` points to.
+    pub remote_default_branch: Option<String>,
+    pub current_branch: Option<String>,
+    /// Local branches and remote branches without their remote prefix.
+    pub branches: Vec<String>,
+    pub commit_summaries: Vec<String>,
+    pub gpg_sign: bool,
+    pub signing_key: Option<String>,
+}
+
+impl RepoFacts {
+    pub fn gather(repo: &dyn GitBackend) -> Result<Self> {
+        let mut branches = repo.list_branches(false)?;
+        for remote_branch in repo.list_branches(true)? {
+            let Some((_, name)) = remote_branch.split_once('/') else {
+                continue;
+            };
+            if name != "HEAD" && !branches.iter().any(|branch| branch == name) {
+                branches.push(name.to_string());
+            }
+        }
+
+        Ok(Self {
+            remote_default_branch: repo.remote_default_branch("origin"),
+            current_branch: repo.current_branch().ok(),
+            branches,
+            commit_summaries: repo
+                .log(&LogOptions {
+                    no_merges: true,
+                    limit: Some(COMMIT_SAMPLE),
+                    ..Default::default()
+                })?
+                .into_iter()
+                .map(|commit| commit.summary)
+                .collect(),
+            gpg_sign: repo.config_bool("commit.gpgsign").unwrap_or(false),
+            signing_key: repo.config_string("user.signingkey"),
+        })
+    }
+}
+
+/// A suggested value together with what it was inferred from.
+#[derive(Debug, Clone)]
+pub struct Detected<T> {
+    pub value: T,
+    pub reason: String,
+}
+
+impl<T> Detected<T> {
+    fn new(value: T, reason: impl Into<String>) -> Self {
+        Self {
+            value,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Settings that match how the repository is already used.
+#[derive(Debug, Clone)]
+pub struct Conventions {
+    pub main_branch: Detected<String>,
+    pub develop_branch: Detected<Option<String>>,
+    pub feature_branch_prefix: Detected<String>,
+    pub hotfix_branch_prefix: Detected<String>,
+    pub release_branch_prefix: Detected<String>,
+    pub conventional: Detected<bool>,
+    pub sign_commits: Detected<bool>,
+    pub gpg_key: Option<String>,
+}
+
+impl Conventions {
+    pub fn detect(facts: &RepoFacts) -> Self {
+        let defaults = Config::default();
+        let workflows = &defaults.workflows;
+
+        Self {
+            main_branch: detect_main_branch(facts),
+            develop_branch: detect_develop_branch(facts),
+            feature_branch_prefix: detect_prefix(
+                &facts.branches,
+                FEATURE_PREFIXES,
+                &workflows.feature_branch_prefix,
+            ),
+            hotfix_branch_prefix: detect_prefix(
+                &facts.branches,
+                HOTFIX_PREFIXES,
+                &workflows.hotfix_branch_prefix,
+            ),
+            release_branch_prefix: detect_prefix(
+                &facts.branches,
+                RELEASE_PREFIXES,
+                &workflows.release_branch_prefix,
+            ),
+            conventional: detect_conventional(
+                &facts.commit_summaries,
+                defaults.commits.conventional,
+            ),
+            sign_commits: if facts.gpg_sign {
+                Detected::new(true, "commit.gpgsign is set")
+            } else {
+                Detected::new(false, "commit.gpgsign is not set")
+            },
+            gpg_key: facts.signing_key.clone(),
+        }
+    }
+
+    /// Writes the conventions into `config`, protecting the long-lived
+    /// branches so `gwf cleanup` never deletes them.
+    pub fn apply(&self, config: &mut Config) {
+        let workflows = &mut config.workflows;
+        workflows.main_branch = self.main_branch.value.clone();
+        workflows.develop_branch = self.develop_branch.value.clone();
+        workflows.feature_branch_prefix = self.feature_branch_prefix.value.clone();
+        workflows.hotfix_branch_prefix = self.hotfix_branch_prefix.value.clone();
+        workflows.release_branch_prefix = self.release_branch_prefix.value.clone();
+
+        config.commits.conventional = self.conventional.value;
+        config.commits.sign_commits = self.sign_commits.value;
+        if self.sign_commits.value {
+            config.commits.gpg_key = self.gpg_key.clone();
+        }
+
+        let protected = &mut config.cleanup.protect_branches;
+        for branch in std::iter::once(&self.main_branch.value).chain(&self.develop_branch.value) {
+            if !protected.contains(branch) {
+                protected.push(branch.clone());
+            }
+        }
+    }
+}
+
+fn detect_main_branch(facts: &RepoFacts) -> Detected<String> {
+    if let Some(branch) = &facts.remote_default_branch {
+        return Detected::new(branch.clone(), "origin/HEAD points to it");
+    }
+    if let Some(branch) = MAIN_CANDIDATES
+        .iter()
+        .find(|candidate| facts.branches.iter().any(|branch| branch == *candidate))
+    {
+        return Detected::new(branch.to_string(), "branch exists");
+    }
+    if let Some(branch) = &facts.current_branch {
+        return Detected::new(branch.clone(), "current branch");
+    }
+
+    Detected::new(Config::default().workflows.main_branch, "default")
+}
+
+fn detect_develop_branch(facts: &RepoFacts) -> Detected<Option<String>> {
+    match DEVELOP_CANDIDATES
+        .iter()
+        .find(|candidate| facts.branches.iter().any(|branch| branch == *candidate))
+    {
+        Some(branch) => Detected::new(Some(branch.to_string()), "branch exists"),
+        None => Detected::new(None, "no develop branch found"),
+    }
+}
+
+/// Picks the most used prefix; ties go to the earlier candidate.
+fn detect_prefix(branches: &[String], candidates: &[&str], default: &str) -> Detected<String> {
+    let mut best: Option<(&str, Vec<&String>)> = None;
+    for candidate in candidates {
+        let matching: Vec<&String> = branches
+            .iter()
+            .filter(|branch| branch.len() > candidate.len() && branch.starts_with(candidate))
+            .collect();
+        if matching.len() > best.as_ref().map_or(0, |(_, m)| m.len()) {
+            best = Some((candidate, matching));
+        }
+    }
+
+    match best {
+        Some((prefix, matching)) => Detected::new(
+            prefix.to_string(),
+            format!("{} branch(es) such as '{}'", matching.len(), matching[0]),
+        ),
+        None => Detected::new(default.to_string(), "default"),
+    }
+}
+
+fn detect_conventional(summaries: &[String], default: bool) -> Detected<bool> {
+    if summaries.len() < MIN_COMMITS {
+        return Detected::new(default, "too few commits to tell");
+    }
+
+    let pattern = Regex::new(
+        r"^(feat|fix|docs|style|refactor|perf|test|build|ci|chore|revert)(\([^)]*\))?!?: \S",
+    )
+    .expect("valid regex");
+    let matching = summaries
+        .iter()
+        .filter(|summary| pattern.is_match(summary))
+        .count();
+
+    Detected::new(
+        matching * 2 >= summaries.len(),
+        format!(
+            "{} of the last {} commits follow Conventional Commits",
+            matching,
+            summaries.len()
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::backend::MockGitBackend;
+    use crate::git::GitError;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn test_detects_branch_conventions() {
+        let facts = RepoFacts {
+            current_branch: Some("feat/login".to_string()),
+            branches: strings(&[
+                "master",
+                "feat/login",
+                "feat/signup",
+                "feature/old",
+                "fix/typo",
+            ]),
+            ..Default::default()
+        };
+
+        let conventions = Conventions::detect(&facts);
+
+        assert_eq!(conventions.main_branch.value, "master");
+        assert_eq!(conventions.develop_branch.value, None);
+        assert_eq!(conventions.feature_branch_prefix.value, "feat/");
+        assert_eq!(conventions.hotfix_branch_prefix.value, "fix/");
+        assert_eq!(conventions.release_branch_prefix.value, "release/");
+
+        let mut config = Config::default();
+        conventions.apply(&mut config);
+        assert_eq!(config.workflows.main_branch, "master");
+        assert!(config
+            .cleanup
+            .protect_branches
+            .contains(&"master".to_string()));
+    }
+
+    #[test]
+    fn test_remote_head_and_commit_history() {
+        let facts = RepoFacts {
+            remote_default_branch: Some("trunk".to_string()),
+            branches: strings(&["main", "trunk", "develop"]),
+            commit_summaries: strings(&[
+                "Update readme",
+                "fix typo",
+                "Merge things",
+                "WIP",
+                "feat: add login",
+                "tidy up",
+            ]),
+            gpg_sign: true,
+            signing_key: Some("ABC123".to_string()),
+            ..Default::default()
+        };
+
+        let conventions = Conventions::detect(&facts);
+
+        assert_eq!(conventions.main_branch.value, "trunk");
+        assert_eq!(conventions.develop_branch.value.as_deref(), Some("develop"));
+        assert!(!conventions.conventional.value);
+        assert!(conventions.sign_commits.value);
+
+        let mut config = Config::default();
+        conventions.apply(&mut config);
+        assert_eq!(config.commits.gpg_key.as_deref(), Some("ABC123"));
+    }
+
+    #[test]
+    fn test_gather_merges_remote_branches() {
+        let mut repo = MockGitBackend::new();
+        repo.expect_list_branches().returning(|remote| {
+            Ok(if remote {
+                strings(&["origin/HEAD", "origin/main", "origin/develop"])
+            } else {
+                strings(&["main", "feat/login"])
+            })
+        });
+        repo.expect_remote_default_branch()
+            .returning(|_| Some("main".to_string()));
+        repo.expect_current_branch()
+            .returning(|| Err(GitError::DetachedHead));
+        repo.expect_log().returning(|_| Ok(Vec::new()));
+        repo.expect_config_bool().returning(|_| None);
+        repo.expect_config_string().returning(|_| None);
+
+        let facts = RepoFacts::gather(&repo).unwrap();
+
+        assert_eq!(facts.branches, strings(&["main", "feat/login", "develop"]));
+        assert_eq!(facts.remote_default_branch.as_deref(), Some("main"));
+        assert_eq!(facts.current_branch, None);
+        assert!(!facts.gpg_sign);
+    }
+}
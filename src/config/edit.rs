@@ -0,0 +1,399 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, TableLike, Value};
+
+use super::migrate::{self, MigrationReport};
+use super::validate;
+
+/// A configuration file opened for edits that keep its comments, key order
+/// and formatting intact.
+pub struct ConfigDocument {
+    path: PathBuf,
+    doc: DocumentMut,
+}
+
+impl ConfigDocument {
+    /// Opens `path`, starting from an empty document if it does not exist yet.
+    pub fn open(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self {
+                path: path.to_path_buf(),
+                doc: DocumentMut::new(),
+            });
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(path, &content)
+    }
+
+    pub fn parse(path: &Path, content: &str) -> Result<Self> {
+        let doc = content
+            .parse::<DocumentMut>()
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            doc,
+        })
+    }
+
+    /// Upgrades the document to the current schema version.
+    pub fn migrate(&mut self) -> Result<MigrationReport> {
+        migrate::migrate(&mut self.doc)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+            }
+        }
+
+        fs::write(&self.path, self.doc.to_string()).context("Failed to write configuration file")
+    }
+
+    /// Sets a dotted key from its command-line form. The value is read as a
+    /// TOML literal (`true`, `30`, `["main", "qa"]`) and falls back to a plain
+    /// string; whichever form the `Config` schema accepts is kept.
+    ///
+    /// A document without a `version` is migrated first, so a new file is
+    /// stamped with the current version and is never mistaken for a legacy
+    /// one later.
+    pub fn set(&mut self, key: &str, raw: &str) -> Result<()> {
+        self.migrate()?;
+
+        let mut candidates = Vec::new();
+        if let Ok(literal) = raw.parse::<Value>() {
+            candidates.push(literal);
+        }
+        candidates.push(Value::from(raw));
+
+        let mut last_error = None;
+        for candidate in candidates {
+            let mut doc = self.doc.clone();
+            set_value(&mut doc, key, candidate)?;
+
+            match check_key(&self.path, &doc, key) {
+                Ok(()) => {
+                    self.doc = doc;
+                    return Ok(());
+                }
+                Err(error) => last_error = Some(error),
+            }
+        }
+
+        Err(last_error.expect("at least one candidate is always tried"))
+    }
+
+    /// Removes a dotted key, returning whether it was present. Tables left
+    /// empty by the removal are dropped as well. Like [`set`](Self::set), a
+    /// document without a `version` is migrated first.
+    pub fn unset(&mut self, key: &str) -> Result<bool> {
+        let segments = split_key(key)?;
+        self.migrate()?;
+        Ok(remove_value(self.doc.as_table_mut(), &segments))
+    }
+
+    /// Rewrites the document so it holds exactly the values in `table`, while
+    /// leaving untouched every entry whose value did not change.
+    pub fn replace_with(&mut self, table: &toml::Table) -> Result<()> {
+        sync_table(self.doc.as_table_mut(), table)
+    }
+}
+
+impl std::fmt::Display for ConfigDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.doc)
+    }
+}
+
+fn split_key(key: &str) -> Result<Vec<&str>> {
+    let segments: Vec<&str> = key.split('.').collect();
+    if segments.iter().any(|segment| segment.is_empty()) {
+        bail!(
+            "Invalid key '{}': expected a dotted path like sync.strategy",
+            key
+        );
+    }
+    Ok(segments)
+}
+
+/// Rejects an edit that leaves `key` unknown to the schema or of the wrong type.
+fn check_key(path: &Path, doc: &DocumentMut, key: &str) -> Result<()> {
+    let content = doc.to_string();
+    for diagnostic in validate::check_file(path, &content) {
+        if diagnostic.is_error() {
+            bail!("Invalid value for '{}': {}", key, diagnostic.message);
+        }
+        if diagnostic.key.as_deref() == Some(key) {
+            bail!("Unknown configuration key '{}'", key);
+        }
+    }
+    Ok(())
+}
+
+fn set_value(doc: &mut DocumentMut, key: &str, value: Value) -> Result<()> {
+    let segments = split_key(key)?;
+    let (last, parents) = segments
+        .split_last()
+        .expect("split_key never returns empty");
+
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+    for segment in parents {
+        let item = table.entry(segment).or_insert_with(|| {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        });
+        table = item
+            .as_table_like_mut()
+            .with_context(|| format!("'{}' is not a table", segment))?;
+    }
+
+    replace_value(table, last, value);
+
+    Ok(())
+}
+
+/// Inserts or overwrites a value. An overwritten value keeps its key and the
+/// comments around it.
+fn replace_value(table: &mut dyn TableLike, key: &str, mut value: Value) {
+    if let Some(existing) = table.get_mut(key).and_then(Item::as_value_mut) {
+        *value.decor_mut() = existing.decor().clone();
+        *existing = value;
+    } else {
+        table.insert(key, Item::Value(value));
+    }
+}
+
+fn remove_value(table: &mut dyn TableLike, segments: &[&str]) -> bool {
+    match segments {
+        [] => false,
+        [last] => table.remove(last).is_some(),
+        [first, rest @ ..] => {
+            let Some(inner) = table.get_mut(first).and_then(Item::as_table_like_mut) else {
+                return false;
+            };
+            let removed = remove_value(inner, rest);
+            if removed && inner.is_empty() {
+                table.remove(first);
+            }
+            removed
+        }
+    }
+}
+
+fn sync_table(target: &mut dyn TableLike, source: &toml::Table) -> Result<()> {
+    let stale: Vec<String> = target
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !source.contains_key(key))
+        .collect();
+    for key in stale {
+        target.remove(&key);
+    }
+
+    for (key, value) in source {
+        if let toml::Value::Table(inner) = value {
+            let item = target
+                .entry(key)
+                .or_insert_with(|| Item::Table(Table::new()));
+            let inner_target = item
+                .as_table_like_mut()
+                .with_context(|| format!("'{}' is not a table", key))?;
+            sync_table(inner_target, inner)?;
+            continue;
+        }
+
+        // Lists of tables, like `commits.types`, read better as `[[...]]`
+        // blocks than as one long inline array, unless the file already
+        // writes them inline.
+        if let Some(tables) = tables_in(value) {
+            if !target.get(key).is_some_and(Item::is_value) {
+                sync_array_of_tables(target, key, &tables)?;
+                continue;
+            }
+        }
+
+        let new_value: Value = value
+            .to_string()
+            .parse()
+            .with_context(|| format!("Failed to convert '{}'", key))?;
+
+        if let Some(existing) = target.get(key).and_then(Item::as_value) {
+            if existing.to_string().trim() == new_value.to_string().trim() {
+                continue;
+            }
+        }
+        replace_value(target, key, new_value);
+    }
+
+    Ok(())
+}
+
+fn tables_in(value: &toml::Value) -> Option<Vec<&toml::Table>> {
+    let toml::Value::Array(items) = value else {
+        return None;
+    };
+    if items.is_empty() {
+        return None;
+    }
+    items.iter().map(toml::Value::as_table).collect()
+}
+
+/// Updates the `[[key]]` blocks table by table, keeping their comments, or
+/// writes them anew when the number of entries changed.
+fn sync_array_of_tables(
+    target: &mut dyn TableLike,
+    key: &str,
+    tables: &[&toml::Table],
+) -> Result<()> {
+    if let Some(existing) = target
+        .get_mut(key)
+        .and_then(Item::as_array_of_tables_mut)
+        .filter(|existing| existing.len() == tables.len())
+    {
+        for (table, source) in existing.iter_mut().zip(tables) {
+            sync_table(table, source)?;
+        }
+        return Ok(());
+    }
+
+    let mut array = ArrayOfTables::new();
+    for source in tables {
+        let mut table = Table::new();
+        sync_table(&mut table, source)?;
+        array.push(table);
+    }
+    target.insert(key, Item::ArrayOfTables(array));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigPaths, LayeredConfig};
+
+    fn document(content: &str) -> ConfigDocument {
+        ConfigDocument {
+            path: PathBuf::from(".gwf.toml"),
+            doc: content.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_set_keeps_comments_and_order() {
+        let mut doc = document(
+            "version = 1\n# Team settings\n[workflows]\nmain_branch = \"main\" # trunk\n\n[sync]\n# how to sync\nstrategy = \"rebase\"\n",
+        );
+
+        doc.set("sync.strategy", "merge").unwrap();
+        doc.set("workflows.main_branch", "master").unwrap();
+        doc.set("cleanup.days_until_stale", "14").unwrap();
+
+        assert_eq!(
+            doc.to_string(),
+            "version = 1\n# Team settings\n[workflows]\nmain_branch = \"master\" # trunk\n\n[sync]\n# how to sync\nstrategy = \"merge\"\n\n[cleanup]\ndays_until_stale = 14\n"
+        );
+    }
+
+    #[test]
+    fn test_set_checks_schema() {
+        let mut doc = document("");
+
+        assert!(doc.set("sync.strategy", "squash").is_err());
+        assert!(doc.set("cleanup.days_until_stale", "soon").is_err());
+        assert!(doc.set("sync.unknown", "true").is_err());
+
+        // A literal that does not fit the schema is retried as a string, and
+        // the new file is stamped with the current version.
+        doc.set("workflows.main_branch", "true").unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "version = 1\n\n[workflows]\nmain_branch = \"true\"\n"
+        );
+    }
+
+    #[test]
+    fn test_unset_drops_empty_tables() {
+        let mut doc = document("[sync]\nstrategy = \"merge\"\n\n[cleanup]\ndays_until_stale = 7\n");
+
+        assert!(doc.unset("sync.strategy").unwrap());
+        assert!(!doc.unset("sync.strategy").unwrap());
+        assert_eq!(
+            doc.to_string(),
+            "version = 1\n\n[cleanup]\ndays_until_stale = 7\n"
+        );
+    }
+
+    #[test]
+    fn test_file_created_by_set_keeps_the_develop_branch() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".gwf.toml");
+        let mut doc = ConfigDocument::open(&path).unwrap();
+        for key in [
+            "feature_branch_prefix",
+            "hotfix_branch_prefix",
+            "release_branch_prefix",
+        ] {
+            doc.set(&format!("workflows.{}", key), "x/").unwrap();
+        }
+        doc.set("workflows.main_branch", "main").unwrap();
+        doc.save().unwrap();
+
+        let mut paths = ConfigPaths::for_directory(dir.path(), dir.path());
+        paths.global = None;
+        let layered = LayeredConfig::load_from(paths, Vec::new(), &[]).unwrap();
+
+        assert_eq!(
+            layered.config.workflows.develop_branch.as_deref(),
+            Some("develop")
+        );
+        assert!(layered.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_legacy_file_is_migrated_before_an_edit() {
+        let mut doc = document(
+            "[workflows]\nfeature_branch_prefix = \"feature/\"\nhotfix_branch_prefix = \"hotfix/\"\n\
+             release_branch_prefix = \"release/\"\nmain_branch = \"main\"\n",
+        );
+
+        doc.set("sync.strategy", "merge").unwrap();
+
+        let config: crate::config::Config = toml::from_str(&doc.to_string()).unwrap();
+        assert_eq!(config.workflows.develop_branch, None);
+        assert!(doc.to_string().starts_with("version = 1\n"));
+    }
+
+    #[test]
+    fn test_lists_of_tables_are_written_as_blocks() {
+        let mut doc = document(
+            "[commits]
+# Our types
+[[commits.types]]
+name = \"feat\" # new things
+",
+        );
+        let source: toml::Table =
+            toml::from_str("[commits]\ntypes = [{ name = \"feature\" }]\n").unwrap();
+
+        doc.replace_with(&source).unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "[commits]\n# Our types\n[[commits.types]]\nname = \"feature\" # new things\n"
+        );
+
+        let source: toml::Table =
+            toml::from_str("[commits]\ntypes = [{ name = \"feat\" }, { name = \"fix\" }]\n")
+                .unwrap();
+        doc.replace_with(&source).unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "[commits]\n\n[[commits.types]]\nname = \"feat\"\n\n[[commits.types]]\nname = \"fix\"\n"
+        );
+    }
+}
@@ -0,0 +1,638 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use toml::{Table, Value};
+
+use super::validate::{self, Diagnostic, Severity};
+use super::{Config, ConfigDocument};
+use crate::git::GitRepo;
+
+pub const CONFIG_FILE: &str = ".gwf.toml";
+pub const LOCAL_CONFIG_FILE: &str = ".gwf.local.toml";
+
+/// Sections that may be overridden through `GWF_<SECTION>_<KEY>` variables.
+const ENV_SECTIONS: &[&str] = &[
+    "workflows",
+    "commits",
+    "sync",
+    "cleanup",
+    "remote",
+    "lint",
+    "hooks",
+    "ai",
+];
+
+/// Names the profile to use, or with an empty value turns off automatic
+/// selection by remote URL.
+const PROFILE_ENV: &str = "GWF_PROFILE";
+
+static PROFILE_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// Selects a profile for every configuration load in this process, taking
+/// precedence over `GWF_PROFILE`. Used for the global `--profile` flag.
+pub fn select_profile(name: String) {
+    let _ = PROFILE_OVERRIDE.set(name);
+}
+
+/// A configuration source, ordered from lowest to highest precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigLayer {
+    Default,
+    Global,
+    Repository,
+    Directory,
+    Local,
+    Profile,
+    Environment,
+}
+
+impl fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConfigLayer::Default => "default",
+            ConfigLayer::Global => "global",
+            ConfigLayer::Repository => "repository",
+            ConfigLayer::Directory => "directory",
+            ConfigLayer::Local => "local",
+            ConfigLayer::Profile => "profile",
+            ConfigLayer::Environment => "env",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Locations of the file-backed configuration layers.
+#[derive(Debug, Clone)]
+pub struct ConfigPaths {
+    pub global: Option<PathBuf>,
+    /// `.gwf.toml` at the root of the working tree.
+    pub repository: PathBuf,
+    /// `.gwf.toml` files in subdirectories between the root and the current
+    /// directory, outermost first.
+    pub directories: Vec<PathBuf>,
+    pub local: PathBuf,
+}
+
+impl ConfigPaths {
+    /// Resolves the layers from the root of the current repository, falling
+    /// back to the current directory outside of a repository.
+    pub fn discover() -> Self {
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let root = GitRepo::open_current()
+            .and_then(|repo| repo.workdir())
+            .unwrap_or_else(|_| cwd.clone());
+
+        Self::for_directory(&root, &cwd)
+    }
+
+    pub fn for_directory(root: &Path, cwd: &Path) -> Self {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let cwd = cwd.canonicalize().unwrap_or_else(|_| cwd.to_path_buf());
+
+        let mut directories: Vec<PathBuf> = cwd
+            .ancestors()
+            .take_while(|dir| *dir != root && dir.starts_with(&root))
+            .map(|dir| dir.join(CONFIG_FILE))
+            .filter(|path| path.exists())
+            .collect();
+        directories.reverse();
+
+        Self {
+            global: global_config_path(),
+            repository: root.join(CONFIG_FILE),
+            directories,
+            local: root.join(LOCAL_CONFIG_FILE),
+        }
+    }
+
+    /// File layers in the order they are applied.
+    pub fn files(&self) -> Vec<(ConfigLayer, &Path)> {
+        let mut files = Vec::new();
+        if let Some(global) = &self.global {
+            files.push((ConfigLayer::Global, global.as_path()));
+        }
+        files.push((ConfigLayer::Repository, self.repository.as_path()));
+        for directory in &self.directories {
+            files.push((ConfigLayer::Directory, directory.as_path()));
+        }
+        files.push((ConfigLayer::Local, self.local.as_path()));
+        files
+    }
+}
+
+/// The user-wide configuration file, `~/.config/gwf/config.toml` unless
+/// `XDG_CONFIG_HOME` points elsewhere.
+pub fn global_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+
+    Some(base.join("gwf").join("config.toml"))
+}
+
+/// The profile applied on top of the configuration files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveProfile {
+    pub name: String,
+    /// The remote URL that selected the profile, or `None` when it was
+    /// requested by name.
+    pub matched_remote: Option<String>,
+}
+
+/// The effective configuration together with the layer each key came from.
+#[derive(Debug)]
+pub struct LayeredConfig {
+    pub config: Config,
+    pub paths: ConfigPaths,
+    pub profile: Option<ActiveProfile>,
+    /// Non-fatal problems found while reading the files, such as unknown keys.
+    pub diagnostics: Vec<Diagnostic>,
+    values: Table,
+    sources: BTreeMap<String, ConfigLayer>,
+}
+
+impl LayeredConfig {
+    pub fn load() -> Result<Self> {
+        Self::load_paths(ConfigPaths::discover())
+    }
+
+    /// Loads `paths` with the process environment, the `--profile` flag and
+    /// the remotes of the current repository.
+    pub fn load_paths(paths: ConfigPaths) -> Result<Self> {
+        let mut env: Vec<(String, String)> = std::env::vars().collect();
+        if let Some(name) = PROFILE_OVERRIDE.get() {
+            env.push((PROFILE_ENV.to_string(), name.clone()));
+        }
+        let remotes = GitRepo::open_current()
+            .and_then(|repo| repo.remote_urls())
+            .unwrap_or_default();
+
+        Self::load_from(paths, env, &remotes)
+    }
+
+    pub fn load_from<I>(paths: ConfigPaths, env: I, remotes: &[String]) -> Result<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let env: Vec<(String, String)> = env.into_iter().collect();
+        let mut values = Table::try_from(Config::default())
+            .context("Failed to serialize default configuration")?;
+        let mut sources = BTreeMap::new();
+        let mut diagnostics = Vec::new();
+        record_sources(&values, "", ConfigLayer::Default, &mut sources);
+
+        for (layer, path) in paths.files() {
+            if !path.exists() {
+                continue;
+            }
+
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+
+            // Positions refer to the file on disk, before migrations add keys.
+            for diagnostic in validate::check_file(path, &content) {
+                if diagnostic.is_error() {
+                    bail!("Invalid configuration: {}", diagnostic);
+                }
+                diagnostics.push(diagnostic);
+            }
+
+            let mut document = ConfigDocument::parse(path, &content)?;
+            let report = document
+                .migrate()
+                .with_context(|| format!("Failed to load {}", path.display()))?;
+            if report.changed {
+                diagnostics.push(Diagnostic::warning(
+                    path,
+                    format!(
+                        "uses schema version {}; run 'gwf config migrate' to upgrade it",
+                        report.from
+                    ),
+                ));
+            }
+            let content = document.to_string();
+
+            let table: Table = toml::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+            merge_table(&mut values, table, "", layer, &mut sources);
+        }
+
+        let requested = env
+            .iter()
+            .rev()
+            .find(|(name, _)| name == PROFILE_ENV)
+            .map(|(_, value)| value.as_str());
+        let profile = choose_profile(&values, requested, remotes, &mut diagnostics)?;
+        if let Some(profile) = &profile {
+            apply_profile(&mut values, &profile.name, &mut sources);
+        }
+
+        apply_env(&mut values, env, &mut sources);
+
+        let config = Value::Table(values.clone())
+            .try_into()
+            .context("Failed to parse configuration file")?;
+
+        Ok(Self {
+            config,
+            paths,
+            profile,
+            diagnostics,
+            values,
+            sources,
+        })
+    }
+
+    /// Every leaf key as `(dotted.key, value, layer)`, sorted by key.
+    pub fn entries(&self) -> Vec<(String, &Value, ConfigLayer)> {
+        let mut entries = Vec::new();
+        collect_entries(&self.values, "", &mut entries);
+
+        entries
+            .into_iter()
+            .map(|(key, value)| {
+                let layer = self
+                    .sources
+                    .get(&key)
+                    .copied()
+                    .unwrap_or(ConfigLayer::Default);
+                (key, value, layer)
+            })
+            .collect()
+    }
+}
+
+/// Picks the profile named by `requested`, or else the first profile, by
+/// name, with a pattern matching one of `remotes`.
+fn choose_profile(
+    values: &Table,
+    requested: Option<&str>,
+    remotes: &[String],
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Option<ActiveProfile>> {
+    let empty = Table::new();
+    let profiles = match values.get("profiles") {
+        Some(Value::Table(profiles)) => profiles,
+        _ => &empty,
+    };
+
+    match requested {
+        Some("") => return Ok(None),
+        Some(name) => {
+            if !profiles.contains_key(name) {
+                let known: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                if known.is_empty() {
+                    bail!("Unknown profile '{}': no profiles are configured", name);
+                }
+                bail!(
+                    "Unknown profile '{}'; configured profiles: {}",
+                    name,
+                    known.join(", ")
+                );
+            }
+            return Ok(Some(ActiveProfile {
+                name: name.to_string(),
+                matched_remote: None,
+            }));
+        }
+        None => {}
+    }
+
+    let mut matches = Vec::new();
+    for (name, profile) in profiles {
+        let patterns = profile
+            .get("remotes")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let matched = remotes.iter().find(|url| {
+            patterns
+                .iter()
+                .filter_map(Value::as_str)
+                .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+                .any(|pattern| pattern.matches(url))
+        });
+        if let Some(url) = matched {
+            matches.push(ActiveProfile {
+                name: name.clone(),
+                matched_remote: Some(url.clone()),
+            });
+        }
+    }
+
+    if matches.len() > 1 {
+        let names: Vec<&str> = matches.iter().map(|m| m.name.as_str()).collect();
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            Some("profiles".to_string()),
+            format!(
+                "profiles {} all match this repository's remotes; using '{}'",
+                names.join(", "),
+                names[0]
+            ),
+        ));
+    }
+
+    Ok(matches.into_iter().next())
+}
+
+/// Merges the overrides of profile `name` into `values`.
+fn apply_profile(values: &mut Table, name: &str, sources: &mut BTreeMap<String, ConfigLayer>) {
+    let Some(Value::Table(mut overlay)) = values
+        .get("profiles")
+        .and_then(|profiles| profiles.get(name))
+        .cloned()
+    else {
+        return;
+    };
+    overlay.remove("remotes");
+
+    merge_table(values, overlay, "", ConfigLayer::Profile, sources);
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn record_sources(
+    table: &Table,
+    prefix: &str,
+    layer: ConfigLayer,
+    sources: &mut BTreeMap<String, ConfigLayer>,
+) {
+    for (key, value) in table {
+        let key = join_key(prefix, key);
+        match value {
+            Value::Table(inner) => record_sources(inner, &key, layer, sources),
+            _ => {
+                sources.insert(key, layer);
+            }
+        }
+    }
+}
+
+/// Deep-merges `overlay` into `base`. Tables merge key by key; every other
+/// value, arrays included, replaces what was there.
+fn merge_table(
+    base: &mut Table,
+    overlay: Table,
+    prefix: &str,
+    layer: ConfigLayer,
+    sources: &mut BTreeMap<String, ConfigLayer>,
+) {
+    for (key, value) in overlay {
+        let full_key = join_key(prefix, &key);
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(inner)) => {
+                merge_table(existing, inner, &full_key, layer, sources);
+            }
+            (_, value) => {
+                let nested = format!("{}.", full_key);
+                sources.retain(|k, _| k != &full_key && !k.starts_with(&nested));
+                match &value {
+                    Value::Table(inner) => record_sources(inner, &full_key, layer, sources),
+                    _ => {
+                        sources.insert(full_key, layer);
+                    }
+                }
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Applies `GWF_<SECTION>_<KEY>` overrides, e.g. `GWF_SYNC_STRATEGY=merge`.
+fn apply_env<I>(values: &mut Table, env: I, sources: &mut BTreeMap<String, ConfigLayer>)
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    for (name, raw) in env {
+        let Some(rest) = name.strip_prefix("GWF_") else {
+            continue;
+        };
+        let rest = rest.to_lowercase();
+        let Some((section, key)) = rest.split_once('_') else {
+            continue;
+        };
+        if !ENV_SECTIONS.contains(&section) || key.is_empty() {
+            continue;
+        }
+
+        let section_table = values
+            .entry(section.to_string())
+            .or_insert_with(|| Value::Table(Table::new()));
+        let Value::Table(section_table) = section_table else {
+            continue;
+        };
+
+        let value = parse_env_value(&raw, section_table.get(key));
+        section_table.insert(key.to_string(), value);
+        sources.insert(format!("{}.{}", section, key), ConfigLayer::Environment);
+    }
+}
+
+/// Interprets an environment value using the type of the value it replaces.
+fn parse_env_value(raw: &str, current: Option<&Value>) -> Value {
+    match current {
+        Some(Value::Array(_)) => Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        ),
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        _ => {
+            if let Ok(flag) = raw.parse::<bool>() {
+                Value::Boolean(flag)
+            } else if let Ok(number) = raw.parse::<i64>() {
+                Value::Integer(number)
+            } else {
+                Value::String(raw.to_string())
+            }
+        }
+    }
+}
+
+fn collect_entries<'a>(table: &'a Table, prefix: &str, out: &mut Vec<(String, &'a Value)>) {
+    for (key, value) in table {
+        let key = join_key(prefix, key);
+        match value {
+            Value::Table(inner) => collect_entries(inner, &key, out),
+            _ => out.push((key, value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SyncStrategy;
+    use tempfile::TempDir;
+
+    fn paths_in(dir: &TempDir) -> ConfigPaths {
+        ConfigPaths {
+            global: Some(dir.path().join("global.toml")),
+            repository: dir.path().join(".gwf.toml"),
+            directories: Vec::new(),
+            local: dir.path().join(".gwf.local.toml"),
+        }
+    }
+
+    fn source_of(layered: &LayeredConfig, key: &str) -> ConfigLayer {
+        layered
+            .entries()
+            .into_iter()
+            .find(|(k, _, _)| k == key)
+            .map(|(_, _, layer)| layer)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_layers_apply_in_order() {
+        let dir = TempDir::new().unwrap();
+        let paths = paths_in(&dir);
+        fs::write(
+            paths.global.as_ref().unwrap(),
+            "[workflows]\nmain_branch = \"trunk\"\n",
+        )
+        .unwrap();
+        fs::write(&paths.repository, "[workflows]\nmain_branch = \"master\"\n").unwrap();
+        fs::write(&paths.local, "[sync]\nstrategy = \"merge\"\n").unwrap();
+
+        let layered = LayeredConfig::load_from(paths, Vec::new(), &[]).unwrap();
+
+        assert_eq!(layered.config.workflows.main_branch, "master");
+        assert!(matches!(layered.config.sync.strategy, SyncStrategy::Merge));
+        assert_eq!(
+            source_of(&layered, "workflows.main_branch"),
+            ConfigLayer::Repository
+        );
+        assert_eq!(source_of(&layered, "sync.strategy"), ConfigLayer::Local);
+        assert_eq!(source_of(&layered, "sync.auto_stash"), ConfigLayer::Default);
+    }
+
+    #[test]
+    fn test_unversioned_file_warnings_point_at_the_file_on_disk() {
+        let dir = TempDir::new().unwrap();
+        let paths = paths_in(&dir);
+        fs::write(&paths.repository, "[sync]\nauto_stsh = false\n").unwrap();
+
+        let layered = LayeredConfig::load_from(paths, Vec::new(), &[]).unwrap();
+
+        let unknown = layered
+            .diagnostics
+            .iter()
+            .find(|d| d.key.as_deref() == Some("sync.auto_stsh"))
+            .unwrap();
+        assert_eq!(unknown.position, Some((2, 1)));
+    }
+
+    #[test]
+    fn test_env_overrides_files() {
+        let dir = TempDir::new().unwrap();
+        let env = vec![
+            ("GWF_SYNC_AUTO_STASH".to_string(), "false".to_string()),
+            (
+                "GWF_CLEANUP_PROTECT_BRANCHES".to_string(),
+                "main, qa".to_string(),
+            ),
+            ("GWF_UNRELATED".to_string(), "ignored".to_string()),
+        ];
+
+        let layered = LayeredConfig::load_from(paths_in(&dir), env, &[]).unwrap();
+
+        assert!(!layered.config.sync.auto_stash);
+        assert_eq!(layered.config.cleanup.protect_branches, vec!["main", "qa"]);
+        assert_eq!(
+            source_of(&layered, "cleanup.protect_branches"),
+            ConfigLayer::Environment
+        );
+    }
+
+    #[test]
+    fn test_nested_directory_overrides_root() {
+        let dir = TempDir::new().unwrap();
+        let package = dir.path().join("crates").join("api");
+        fs::create_dir_all(package.join("src")).unwrap();
+        fs::write(
+            dir.path().join(CONFIG_FILE),
+            "[workflows]\nmain_branch = \"master\"\nfeature_branch_prefix = \"feat/\"\n",
+        )
+        .unwrap();
+        fs::write(
+            package.join(CONFIG_FILE),
+            "[workflows]\nfeature_branch_prefix = \"api/\"\n",
+        )
+        .unwrap();
+
+        let mut paths = ConfigPaths::for_directory(dir.path(), &package.join("src"));
+        paths.global = None;
+        assert_eq!(paths.directories.len(), 1);
+
+        let layered = LayeredConfig::load_from(paths, Vec::new(), &[]).unwrap();
+        assert_eq!(layered.config.workflows.main_branch, "master");
+        assert_eq!(layered.config.workflows.feature_branch_prefix, "api/");
+        assert_eq!(
+            source_of(&layered, "workflows.feature_branch_prefix"),
+            ConfigLayer::Directory
+        );
+    }
+
+    const PROFILES: &str = "[commits]\nsign_commits = false\n\n\
+        [profiles.work]\nremotes = [\"*github.com:acme/*\"]\n\
+        [profiles.work.commits]\nsign_commits = true\n\
+        [profiles.work.workflows]\nmain_branch = \"develop\"\n\n\
+        [profiles.oss.workflows]\nmain_branch = \"main\"\n";
+
+    #[test]
+    fn test_profile_selected_by_remote() {
+        let dir = TempDir::new().unwrap();
+        let paths = paths_in(&dir);
+        fs::write(paths.global.as_ref().unwrap(), PROFILES).unwrap();
+        fs::write(&paths.repository, "[workflows]\nmain_branch = \"master\"\n").unwrap();
+        let remotes = vec!["git@github.com:acme/api.git".to_string()];
+
+        let layered = LayeredConfig::load_from(paths, Vec::new(), &remotes).unwrap();
+
+        let profile = layered.profile.as_ref().unwrap();
+        assert_eq!(profile.name, "work");
+        assert_eq!(profile.matched_remote.as_deref(), Some(remotes[0].as_str()));
+        assert!(layered.config.commits.sign_commits);
+        assert_eq!(layered.config.workflows.main_branch, "develop");
+        assert_eq!(
+            source_of(&layered, "workflows.main_branch"),
+            ConfigLayer::Profile
+        );
+        assert_eq!(source_of(&layered, "sync.strategy"), ConfigLayer::Default);
+    }
+
+    #[test]
+    fn test_profile_selected_by_name() {
+        let dir = TempDir::new().unwrap();
+        let paths = paths_in(&dir);
+        fs::write(paths.global.as_ref().unwrap(), PROFILES).unwrap();
+        let remotes = vec!["git@github.com:acme/api.git".to_string()];
+        let env = |value: &str| vec![(PROFILE_ENV.to_string(), value.to_string())];
+
+        let layered = LayeredConfig::load_from(paths.clone(), env("oss"), &remotes).unwrap();
+        assert_eq!(layered.profile.unwrap().matched_remote, None);
+        assert!(!layered.config.commits.sign_commits);
+        assert_eq!(layered.config.workflows.main_branch, "main");
+
+        let layered = LayeredConfig::load_from(paths.clone(), env(""), &remotes).unwrap();
+        assert!(layered.profile.is_none());
+
+        let error = LayeredConfig::load_from(paths, env("home"), &remotes).unwrap_err();
+        assert!(error.to_string().contains("oss, work"));
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::{bail, Result};
+use toml_edit::{value, DocumentMut, Item};
+
+/// Schema version written by this build of gwf. Files without a `version`
+/// key predate versioning and are treated as version 0.
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Upgrades a configuration document from `from` to `from + 1`.
+pub struct Migration {
+    pub from: u32,
+    pub description: &'static str,
+    /// Returns whether the document needed any change beyond the version bump.
+    pub apply: fn(&mut DocumentMut) -> bool,
+}
+
+/// Every migration in order; `MIGRATIONS[n].from == n`.
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "an omitted workflows.develop_branch now means \"develop\"; \
+                  spell out \"no develop branch\" for configs written before that",
+    apply: explicit_no_develop_branch,
+}];
+
+/// The outcome of migrating one document.
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub from: u32,
+    pub applied: Vec<&'static str>,
+    /// Whether any migration changed the meaning of the file, as opposed to
+    /// only stamping the new version.
+    pub changed: bool,
+}
+
+impl MigrationReport {
+    pub fn is_noop(&self) -> bool {
+        self.applied.is_empty()
+    }
+}
+
+pub fn document_version(doc: &DocumentMut) -> Result<u32> {
+    match doc.get("version") {
+        None => Ok(0),
+        Some(item) => match item.as_integer() {
+            Some(version) if version >= 0 => Ok(version as u32),
+            _ => bail!("`version` must be a non-negative integer"),
+        },
+    }
+}
+
+/// Brings `doc` up to `CURRENT_VERSION`, stamping the version as it goes.
+pub fn migrate(doc: &mut DocumentMut) -> Result<MigrationReport> {
+    let from = document_version(doc)?;
+    if from > CURRENT_VERSION {
+        bail!(
+            "Configuration uses schema version {}, but this gwf only understands up to {}. \
+             Please upgrade gwf.",
+            from,
+            CURRENT_VERSION
+        );
+    }
+
+    let mut report = MigrationReport {
+        from,
+        ..Default::default()
+    };
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from) {
+        report.changed |= (migration.apply)(doc);
+        report.applied.push(migration.description);
+        doc.insert("version", value(i64::from(migration.from + 1)));
+    }
+
+    Ok(report)
+}
+
+/// Before versioning, every `[workflows]` key was required and leaving out
+/// `develop_branch` meant the workflow had none.
+fn explicit_no_develop_branch(doc: &mut DocumentMut) -> bool {
+    const REQUIRED: &[&str] = &[
+        "feature_branch_prefix",
+        "hotfix_branch_prefix",
+        "release_branch_prefix",
+        "main_branch",
+    ];
+
+    let Some(workflows) = doc.get_mut("workflows").and_then(Item::as_table_like_mut) else {
+        return false;
+    };
+    let legacy = REQUIRED.iter().all(|key| workflows.contains_key(key));
+    if !legacy || workflows.contains_key("develop_branch") {
+        return false;
+    }
+
+    workflows.insert("develop_branch", value(""));
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_contiguous() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.from as usize, index);
+        }
+        assert_eq!(MIGRATIONS.len() as u32, CURRENT_VERSION);
+    }
+
+    #[test]
+    fn test_legacy_file_keeps_its_meaning() {
+        let mut doc: DocumentMut = "[workflows]\nfeature_branch_prefix = \"feature/\"\n\
+             hotfix_branch_prefix = \"hotfix/\"\nrelease_branch_prefix = \"release/\"\n\
+             main_branch = \"main\"\n"
+            .parse()
+            .unwrap();
+
+        let report = migrate(&mut doc).unwrap();
+
+        assert_eq!(report.from, 0);
+        assert!(report.changed);
+        assert_eq!(doc["version"].as_integer(), Some(1));
+        assert_eq!(doc["workflows"]["develop_branch"].as_str(), Some(""));
+    }
+
+    #[test]
+    fn test_partial_file_is_only_stamped() {
+        let mut doc: DocumentMut = "[sync]\nstrategy = \"merge\"\n".parse().unwrap();
+
+        let report = migrate(&mut doc).unwrap();
+
+        assert!(!report.changed);
+        assert_eq!(
+            doc.to_string(),
+            "version = 1\n[sync]\nstrategy = \"merge\"\n"
+        );
+
+        let again = migrate(&mut doc).unwrap();
+        assert!(again.is_noop());
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let mut doc: DocumentMut = "version = 99\n".parse().unwrap();
+        assert!(migrate(&mut doc).is_err());
+    }
+}
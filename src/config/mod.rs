@@ -1,121 +1,556 @@
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::collections::BTreeMap;
 use std::path::Path;
 
-#[derive(Debug, Serialize, Deserialize)]
+pub mod detect;
+pub mod edit;
+pub mod layers;
+pub mod migrate;
+pub mod presets;
+pub mod schema;
+pub mod validate;
+
+pub use edit::ConfigDocument;
+pub use layers::{select_profile, ConfigLayer, ConfigPaths, LayeredConfig};
+
+use crate::utils::print_warning;
+
+/// Configuration for gwf. Every section and key is optional; anything left
+/// out falls back to its default.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
+#[schemars(title = "gwf configuration")]
 pub struct Config {
+    /// Schema version of this file. gwf migrates older versions automatically.
+    pub version: u32,
+    /// Branch naming and the long-lived branches of the workflow.
     pub workflows: WorkflowConfig,
+    /// How `gwf commit` builds and signs commits.
     pub commits: CommitConfig,
+    /// How `gwf sync` brings branches up to date.
     pub sync: SyncConfig,
+    /// Which branches `gwf cleanup` may delete.
     pub cleanup: CleanupConfig,
+    /// Which remotes gwf fetches from and pushes to.
+    pub remote: RemoteConfig,
+    /// Rules `gwf lint-commits` checks commit messages against.
+    pub lint: LintConfig,
+    /// Commands the git hooks installed by `gwf hooks install` run.
+    pub hooks: HooksConfig,
+    /// AI-generated commit messages.
+    pub ai: Option<AiConfig>,
+    /// Named sets of overrides, selected with `--profile`, `GWF_PROFILE` or
+    /// by matching the repository's remote URLs.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A named overlay on top of the configuration files. Only the keys it sets
+/// take effect; everything else keeps its value from the other layers.
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
+pub struct Profile {
+    /// Remote URL patterns that select this profile automatically, e.g.
+    /// `*github.com:acme/*`. `*` matches any run of characters, `/` included.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remotes: Vec<String>,
+    /// Overrides for `[workflows]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workflows: Option<WorkflowConfig>,
+    /// Overrides for `[commits]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commits: Option<CommitConfig>,
+    /// Overrides for `[sync]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync: Option<SyncConfig>,
+    /// Overrides for `[cleanup]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cleanup: Option<CleanupConfig>,
+    /// Overrides for `[remote]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote: Option<RemoteConfig>,
+    /// Overrides for `[lint]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lint: Option<LintConfig>,
+    /// Overrides for `[hooks]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<HooksConfig>,
+    /// Overrides for `[ai]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ai: Option<AiConfig>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct WorkflowConfig {
+    /// Prefix for branches created by `gwf feature`.
     pub feature_branch_prefix: String,
+    /// Prefix for branches created by `gwf hotfix`.
     pub hotfix_branch_prefix: String,
+    /// Prefix for branches created by `gwf release`.
     pub release_branch_prefix: String,
+    /// The branch releases are cut from and pull requests target by default.
     pub main_branch: String,
+    /// Integration branch that features start from. Set to an empty string
+    /// when the workflow has no develop branch.
+    #[serde(with = "optional_branch")]
+    #[schemars(with = "String")]
     pub develop_branch: Option<String>,
+    /// Where `--worktree` creates linked worktrees, relative to the
+    /// repository root. `{repo}` is replaced by the repository's directory
+    /// name and `{branch}` by the branch name with `/` replaced by `-`.
+    pub worktree_path: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct CommitConfig {
+    /// Prompt for Conventional Commits messages (`type(scope): description`).
     pub conventional: bool,
+    /// GPG-sign every commit.
     pub sign_commits: bool,
+    /// Key used for signing; git's default key when unset.
     pub gpg_key: Option<String>,
+    /// Types `gwf commit` offers, in this order, and `gwf lint-commits`
+    /// accepts.
+    pub types: Vec<CommitType>,
+    /// Scopes `gwf commit` offers and `gwf lint-commits` accepts; any scope
+    /// is accepted when empty.
+    pub scopes: Vec<String>,
+    /// Scopes for paths, e.g. `"crates/api/**" = "api"`. `gwf commit`
+    /// pre-selects the scope most staged files map to; a file matched by
+    /// several globs takes the scope of the longest one.
+    pub scope_paths: BTreeMap<String, String>,
+    /// Start commit descriptions with the type's emoji.
+    pub emoji: bool,
+}
+
+/// A Conventional Commits type, such as `feat`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct CommitType {
+    /// The type as written in messages.
+    pub name: String,
+    /// What the type is for, shown when choosing it.
+    #[serde(default)]
+    pub description: String,
+    /// Emoji shown when choosing the type and, with `commits.emoji`, put in
+    /// front of the description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+}
+
+impl CommitType {
+    fn new(name: &str, description: &str, emoji: &str) -> Self {
+        CommitType {
+            name: name.to_string(),
+            description: description.to_string(),
+            emoji: Some(emoji.to_string()),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct SyncConfig {
+    /// How local commits are combined with upstream changes.
     pub strategy: SyncStrategy,
+    /// Stash uncommitted changes before syncing and restore them afterwards.
     pub auto_stash: bool,
+    /// Remove remote-tracking branches that no longer exist on the remote.
     pub prune_on_fetch: bool,
+    /// Push the base branches to the push remote after fast-forwarding them
+    /// from the upstream remote, keeping a fork's branches current.
+    pub update_fork: bool,
+}
+
+/// The roles of the repository's remotes. Both are `origin` unless you work
+/// on a fork, where branches are pushed to the fork and the base branches
+/// come from the original repository.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
+pub struct RemoteConfig {
+    /// Remote that branches and tags are pushed to and pull requests are
+    /// opened from; your fork in a fork-based workflow.
+    pub push: String,
+    /// Remote the base branches are synced from and pull requests target.
+    pub upstream: String,
+}
+
+impl RemoteConfig {
+    /// Whether branches are pushed somewhere other than where they are
+    /// merged, i.e. the repository is a fork.
+    pub fn is_fork(&self) -> bool {
+        self.push != self.upstream
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Conventional Commits rules for `gwf lint-commits`, modeled on
+/// commitlint's conventional configuration.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
+pub struct LintConfig {
+    /// Allowed commit types; the names in `commits.types` when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub types: Option<Vec<String>>,
+    /// Allowed scopes; `commits.scopes` when unset. Any scope is accepted
+    /// when the list is empty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
+    /// Longest allowed first line, in characters; 0 turns the check off.
+    pub header_max_length: usize,
+    /// How the description after `type(scope): ` must start.
+    pub subject_case: SubjectCase,
+    /// Require a blank line between the first line and the body.
+    pub body_leading_blank: bool,
+    /// Require footers to be `Token: value` or `Token #value`, with `-`
+    /// instead of spaces in tokens other than `BREAKING CHANGE`.
+    pub footer_format: bool,
+}
+
+/// How the description of a commit message must start.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SubjectCase {
+    /// With a lowercase letter, e.g. `add login page`.
+    #[default]
+    Lower,
+    /// With an uppercase letter, e.g. `Add login page`.
+    Sentence,
+    /// Either way.
+    Any,
+}
+
+/// Commands each git hook runs, in order. Entries starting with `gwf:` are
+/// built-in actions (`gwf:lint`, `gwf:protect-branches`, `gwf:template`);
+/// anything else runs in `sh`, with the hook's arguments as `$1`, `$2`, ...
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Run before a commit is created; failing aborts the commit.
+    pub pre_commit: Vec<String>,
+    /// Run before the commit message editor opens, with the message file.
+    pub prepare_commit_msg: Vec<String>,
+    /// Run with the commit message file; failing aborts the commit.
+    pub commit_msg: Vec<String>,
+    /// Run after a commit is created.
+    pub post_commit: Vec<String>,
+    /// Run before pushing, with the refs to push on stdin; failing aborts
+    /// the push.
+    pub pre_push: Vec<String>,
+    /// Run after a checkout or switch.
+    pub post_checkout: Vec<String>,
+    /// Run after a merge, including the one `git pull` makes.
+    pub post_merge: Vec<String>,
+    /// Message `gwf:template` starts new commits with. `{branch}` is replaced
+    /// with the branch name and `{name}` with it without its prefix. When
+    /// unset, feature branches start with `feat: ` and hotfixes with `fix: `.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Install the hooks into this directory and point `core.hooksPath` at
+    /// it, instead of using `.git/hooks`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl HooksConfig {
+    /// The hooks gwf can install, by their git names.
+    pub const NAMES: &'static [&'static str] = &[
+        "pre-commit",
+        "prepare-commit-msg",
+        "commit-msg",
+        "post-commit",
+        "pre-push",
+        "post-checkout",
+        "post-merge",
+    ];
+
+    /// Built-in actions and the hook each one works in.
+    pub const BUILTINS: &'static [(&'static str, &'static str)] = &[
+        ("gwf:lint", "commit-msg"),
+        ("gwf:protect-branches", "pre-push"),
+        ("gwf:template", "prepare-commit-msg"),
+    ];
+
+    /// The commands configured for the hook git calls `hook`, or `None` if
+    /// gwf does not manage that hook.
+    pub fn commands(&self, hook: &str) -> Option<&[String]> {
+        let commands = match hook {
+            "pre-commit" => &self.pre_commit,
+            "prepare-commit-msg" => &self.prepare_commit_msg,
+            "commit-msg" => &self.commit_msg,
+            "post-commit" => &self.post_commit,
+            "pre-push" => &self.pre_push,
+            "post-checkout" => &self.post_checkout,
+            "post-merge" => &self.post_merge,
+            _ => return None,
+        };
+        Some(commands)
+    }
+}
+
+/// How local commits are combined with upstream changes.
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum SyncStrategy {
+    /// Replay local commits on top of the upstream branch.
+    #[default]
     Rebase,
+    /// Merge the upstream branch into the local branch.
     Merge,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct CleanupConfig {
+    /// Delete local branches that are fully merged into the main branch.
     pub delete_merged: bool,
+    /// Days without commits after which a branch counts as stale.
     pub days_until_stale: u32,
+    /// Branches that are never deleted.
     pub protect_branches: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(deny_unknown_fields)]
 pub struct AiConfig {
+    /// Let `gwf commit` generate the message without `--ai`.
     pub enabled: bool,
+    /// `openai` for any OpenAI-compatible API, including local servers such
+    /// as Ollama or llama.cpp, or `command` to pipe the diff to `ai.command`.
     pub provider: String,
+    /// Model requested from the provider.
     pub model: String,
+    /// Base URL of the OpenAI-compatible API, e.g.
+    /// `http://localhost:11434/v1` for Ollama.
+    pub endpoint: String,
+    /// API key for the provider; `OPENAI_API_KEY` when unset. Prefer an
+    /// environment variable (`GWF_AI_API_KEY`) over committing the key.
     pub api_key: Option<String>,
+    /// Shell command for the `command` provider. It gets the staged diff on
+    /// stdin and the instructions in `GWF_AI_PROMPT`, and prints the message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Longest diff sent to the provider, in characters; larger diffs are
+    /// cut down file by file, after a summary of every changed file.
+    pub max_diff_chars: usize,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
-            workflows: WorkflowConfig {
-                feature_branch_prefix: "feature/".to_string(),
-                hotfix_branch_prefix: "hotfix/".to_string(),
-                release_branch_prefix: "release/".to_string(),
-                main_branch: "main".to_string(),
-                develop_branch: Some("develop".to_string()),
-            },
-            commits: CommitConfig {
-                conventional: true,
-                sign_commits: false,
-                gpg_key: None,
-            },
-            sync: SyncConfig {
-                strategy: SyncStrategy::Rebase,
-                auto_stash: true,
-                prune_on_fetch: true,
-            },
-            cleanup: CleanupConfig {
-                delete_merged: true,
-                days_until_stale: 30,
-                protect_branches: vec![
-                    "main".to_string(),
-                    "master".to_string(),
-                    "develop".to_string(),
-                    "production".to_string(),
-                ],
-            },
+            version: migrate::CURRENT_VERSION,
+            workflows: WorkflowConfig::default(),
+            commits: CommitConfig::default(),
+            sync: SyncConfig::default(),
+            cleanup: CleanupConfig::default(),
+            remote: RemoteConfig::default(),
+            lint: LintConfig::default(),
+            hooks: HooksConfig::default(),
             ai: None,
+            profiles: BTreeMap::new(),
         }
     }
 }
 
-impl Config {
-    pub fn load() -> Result<Self> {
-        let config_path = Path::new(".gwf.toml");
+impl Default for WorkflowConfig {
+    fn default() -> Self {
+        WorkflowConfig {
+            feature_branch_prefix: "feature/".to_string(),
+            hotfix_branch_prefix: "hotfix/".to_string(),
+            release_branch_prefix: "release/".to_string(),
+            main_branch: "main".to_string(),
+            develop_branch: Some("develop".to_string()),
+            worktree_path: "../{repo}-{branch}".to_string(),
+        }
+    }
+}
+
+impl Default for CommitConfig {
+    fn default() -> Self {
+        CommitConfig {
+            conventional: true,
+            sign_commits: false,
+            gpg_key: None,
+            types: vec![
+                CommitType::new("feat", "A new feature", "✨"),
+                CommitType::new("fix", "A bug fix", "🐛"),
+                CommitType::new("docs", "Documentation only changes", "📝"),
+                CommitType::new(
+                    "style",
+                    "Changes that do not affect the meaning of the code",
+                    "💄",
+                ),
+                CommitType::new(
+                    "refactor",
+                    "A code change that neither fixes a bug nor adds a feature",
+                    "♻️",
+                ),
+                CommitType::new("perf", "A code change that improves performance", "⚡️"),
+                CommitType::new(
+                    "test",
+                    "Adding missing tests or correcting existing tests",
+                    "✅",
+                ),
+                CommitType::new(
+                    "build",
+                    "Changes to the build system or external dependencies",
+                    "📦️",
+                ),
+                CommitType::new("ci", "Changes to CI configuration and scripts", "👷"),
+                CommitType::new(
+                    "chore",
+                    "Other changes that don't modify src or test files",
+                    "🔧",
+                ),
+                CommitType::new("revert", "Reverts a previous commit", "⏪️"),
+            ],
+            scopes: Vec::new(),
+            scope_paths: BTreeMap::new(),
+            emoji: false,
+        }
+    }
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        SyncConfig {
+            strategy: SyncStrategy::Rebase,
+            auto_stash: true,
+            prune_on_fetch: true,
+            update_fork: false,
+        }
+    }
+}
 
-        if !config_path.exists() {
-            return Ok(Config::default());
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        CleanupConfig {
+            delete_merged: true,
+            days_until_stale: 30,
+            protect_branches: vec![
+                "main".to_string(),
+                "master".to_string(),
+                "develop".to_string(),
+                "production".to_string(),
+            ],
         }
+    }
+}
 
-        let content =
-            fs::read_to_string(config_path).context("Failed to read configuration file")?;
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        RemoteConfig {
+            push: "origin".to_string(),
+            upstream: "origin".to_string(),
+        }
+    }
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            types: None,
+            scopes: None,
+            header_max_length: 100,
+            subject_case: SubjectCase::Lower,
+            body_leading_blank: true,
+            footer_format: true,
+        }
+    }
+}
 
-        let config: Config =
-            toml::from_str(&content).context("Failed to parse configuration file")?;
+impl Default for HooksConfig {
+    fn default() -> Self {
+        HooksConfig {
+            pre_commit: Vec::new(),
+            prepare_commit_msg: vec!["gwf:template".to_string()],
+            commit_msg: vec!["gwf:lint".to_string()],
+            post_commit: Vec::new(),
+            pre_push: vec!["gwf:protect-branches".to_string()],
+            post_checkout: Vec::new(),
+            post_merge: Vec::new(),
+            template: None,
+            path: None,
+        }
+    }
+}
 
-        Ok(config)
+impl Default for AiConfig {
+    fn default() -> Self {
+        AiConfig {
+            enabled: false,
+            provider: "openai".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            endpoint: "https://api.openai.com/v1".to_string(),
+            api_key: None,
+            command: None,
+            max_diff_chars: 16_000,
+        }
     }
+}
+
+mod optional_branch {
+    use serde::{Deserialize, Deserializer, Serializer};
 
+    pub fn serialize<S: Serializer>(
+        value: &Option<String>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(value.as_deref().unwrap_or(""))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<String>, D::Error> {
+        let value = Option::<String>::deserialize(deserializer)?;
+        Ok(value.filter(|branch| !branch.is_empty()))
+    }
+}
+
+impl Config {
+    /// Loads the effective configuration: defaults, then the global, repository
+    /// and local files, then the active profile, then `GWF_*` environment
+    /// variables.
+    ///
+    /// Problems that do not prevent loading, like unknown keys or an unprotected
+    /// main branch, are printed as warnings.
+    pub fn load() -> Result<Self> {
+        let layered = LayeredConfig::load()?;
+
+        let semantic = validate::check_semantics(&layered, None);
+        for diagnostic in layered.diagnostics.iter().chain(&semantic) {
+            print_warning(&format!("Config: {}", diagnostic));
+        }
+
+        Ok(layered.config)
+    }
+
+    /// Writes the configuration to `.gwf.toml` at the repository root.
     pub fn save(&self) -> Result<()> {
-        let config_path = Path::new(".gwf.toml");
-        let content = toml::to_string_pretty(self).context("Failed to serialize configuration")?;
+        self.save_to(&ConfigPaths::discover().repository)
+    }
 
-        fs::write(config_path, content).context("Failed to write configuration file")?;
+    /// Writes the configuration to `path`. An existing file is updated in
+    /// place, so comments and unchanged entries survive.
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let table = toml::Table::try_from(self).context("Failed to serialize configuration")?;
 
-        Ok(())
+        let mut document = ConfigDocument::open(path)?;
+        document.replace_with(&table)?;
+        document.save()
     }
 }
 
@@ -130,9 +565,9 @@ mod tests {
         assert_eq!(config.workflows.hotfix_branch_prefix, "hotfix/");
         assert_eq!(config.workflows.release_branch_prefix, "release/");
         assert_eq!(config.workflows.main_branch, "main");
-        assert_eq!(config.commits.conventional, true);
-        assert_eq!(config.sync.auto_stash, true);
-        assert_eq!(config.cleanup.delete_merged, true);
+        assert!(config.commits.conventional);
+        assert!(config.sync.auto_stash);
+        assert!(config.cleanup.delete_merged);
     }
 
     #[test]
@@ -140,4 +575,23 @@ mod tests {
         let config = Config::default();
         matches!(config.sync.strategy, SyncStrategy::Rebase);
     }
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let config: Config = toml::from_str("[sync]\nstrategy = \"merge\"\n").unwrap();
+        assert!(matches!(config.sync.strategy, SyncStrategy::Merge));
+        assert!(config.sync.auto_stash);
+        assert_eq!(config.workflows.main_branch, "main");
+        assert_eq!(config.cleanup.days_until_stale, 30);
+    }
+
+    #[test]
+    fn test_empty_develop_branch_disables_it() {
+        let config: Config = toml::from_str("[workflows]\ndevelop_branch = \"\"\n").unwrap();
+        assert_eq!(config.workflows.develop_branch, None);
+
+        let saved = toml::to_string(&config).unwrap();
+        let reloaded: Config = toml::from_str(&saved).unwrap();
+        assert_eq!(reloaded.workflows.develop_branch, None);
+    }
 }
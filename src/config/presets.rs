@@ -0,0 +1,112 @@
+use super::{Config, SyncStrategy};
+
+/// A built-in starting point for `gwf init --template`.
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    apply: fn(&mut Config),
+}
+
+impl Preset {
+    pub fn config(&self) -> Config {
+        let mut config = Config::default();
+        (self.apply)(&mut config);
+        config
+    }
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "git-flow",
+        description: "develop branch, release and hotfix branches, rebase sync",
+        apply: git_flow,
+    },
+    Preset {
+        name: "github-flow",
+        description: "short-lived branches off main, no develop branch",
+        apply: github_flow,
+    },
+    Preset {
+        name: "gitlab-flow",
+        description: "main plus pre-production and production environment branches",
+        apply: gitlab_flow,
+    },
+    Preset {
+        name: "trunk-based",
+        description: "everyone integrates into main daily, merge sync, quick cleanup",
+        apply: trunk_based,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+}
+
+fn protect(config: &mut Config, branches: &[&str]) {
+    config.cleanup.protect_branches = branches.iter().map(|b| b.to_string()).collect();
+}
+
+fn git_flow(config: &mut Config) {
+    config.workflows.main_branch = "main".to_string();
+    config.workflows.develop_branch = Some("develop".to_string());
+    config.sync.strategy = SyncStrategy::Rebase;
+    config.cleanup.days_until_stale = 30;
+    protect(config, &["main", "develop"]);
+}
+
+fn github_flow(config: &mut Config) {
+    config.workflows.main_branch = "main".to_string();
+    config.workflows.develop_branch = None;
+    config.sync.strategy = SyncStrategy::Rebase;
+    config.cleanup.days_until_stale = 14;
+    protect(config, &["main"]);
+}
+
+fn gitlab_flow(config: &mut Config) {
+    config.workflows.main_branch = "main".to_string();
+    config.workflows.develop_branch = None;
+    config.sync.strategy = SyncStrategy::Merge;
+    config.cleanup.days_until_stale = 30;
+    protect(config, &["main", "pre-production", "production"]);
+}
+
+fn trunk_based(config: &mut Config) {
+    config.workflows.main_branch = "main".to_string();
+    config.workflows.develop_branch = None;
+    config.sync.strategy = SyncStrategy::Merge;
+    config.cleanup.days_until_stale = 7;
+    protect(config, &["main"]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_protect_their_long_lived_branches() {
+        for preset in PRESETS {
+            let config = preset.config();
+            let workflows = &config.workflows;
+            let protected = &config.cleanup.protect_branches;
+
+            assert!(
+                protected.contains(&workflows.main_branch),
+                "{}",
+                preset.name
+            );
+            if let Some(develop) = &workflows.develop_branch {
+                assert!(protected.contains(develop), "{}", preset.name);
+            }
+            assert!(find(preset.name).is_some());
+        }
+    }
+
+    #[test]
+    fn test_trunk_based_has_no_develop_branch() {
+        let config = find("trunk-based").unwrap().config();
+
+        assert_eq!(config.workflows.develop_branch, None);
+        assert!(matches!(config.sync.strategy, SyncStrategy::Merge));
+        assert!(config.cleanup.days_until_stale < 30);
+    }
+}
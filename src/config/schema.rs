@@ -0,0 +1,67 @@
+use schemars::{generate::SchemaSettings, Schema};
+
+use super::Config;
+
+/// JSON Schema for `.gwf.toml`, derived from the `Config` types so it never
+/// drifts from what gwf actually accepts.
+pub fn config_schema() -> Schema {
+    SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<Config>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn assert_described(value: &Value, path: &str) {
+        if let Some(properties) = value.get("properties").and_then(Value::as_object) {
+            for (name, property) in properties {
+                let described = property.get("description").is_some()
+                    || property
+                        .get("$ref")
+                        .or_else(|| property.pointer("/allOf/0/$ref"))
+                        .is_some();
+                assert!(described, "{}.{} has no description", path, name);
+                assert_described(property, &format!("{}.{}", path, name));
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_field_is_described() {
+        let schema = serde_json::to_value(config_schema()).unwrap();
+
+        assert_described(&schema, "config");
+        for (name, definition) in schema["definitions"].as_object().unwrap() {
+            assert_described(definition, name);
+        }
+    }
+
+    #[test]
+    fn test_schema_tracks_sections() {
+        let schema = serde_json::to_value(config_schema()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+
+        for section in [
+            "version",
+            "workflows",
+            "commits",
+            "sync",
+            "cleanup",
+            "remote",
+            "lint",
+            "hooks",
+            "ai",
+        ] {
+            assert!(properties.contains_key(section), "missing {}", section);
+        }
+        assert_eq!(
+            schema["definitions"]["SyncStrategy"]["oneOf"]
+                .as_array()
+                .map(Vec::len),
+            Some(2)
+        );
+    }
+}
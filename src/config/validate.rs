@@ -0,0 +1,595 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::de::{DeTable, DeValue};
+use toml::Spanned;
+
+use super::{Config, HooksConfig, LayeredConfig};
+use crate::git::GitBackend;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// A single problem found in the configuration, located as precisely as the
+/// check allows.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: Option<PathBuf>,
+    /// 1-based line and column.
+    pub position: Option<(usize, usize)>,
+    pub key: Option<String>,
+    pub message: String,
+    /// The segments of `key`, which may contain dots themselves.
+    path: Vec<String>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(severity: Severity, key: Option<String>, message: String) -> Self {
+        let path = key
+            .as_deref()
+            .map(|key| key.split('.').map(String::from).collect())
+            .unwrap_or_default();
+        Self {
+            severity,
+            file: None,
+            position: None,
+            key,
+            message,
+            path,
+        }
+    }
+
+    /// A diagnostic for the key made of `path`, for keys such as path globs
+    /// that contain dots.
+    fn at(severity: Severity, path: &[&str], message: String) -> Self {
+        Self {
+            severity,
+            file: None,
+            position: None,
+            key: Some(path.join(".")),
+            message,
+            path: path.iter().map(|segment| segment.to_string()).collect(),
+        }
+    }
+
+    /// A warning about a whole file.
+    pub fn warning(file: &Path, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            file: Some(file.to_path_buf()),
+            position: None,
+            key: None,
+            message,
+            path: Vec::new(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.position) {
+            (Some(file), Some((line, column))) => {
+                write!(f, "{}:{}:{}: ", file.display(), line, column)?
+            }
+            (Some(file), None) => write!(f, "{}: ", file.display())?,
+            _ => {}
+        }
+        if let Some(key) = &self.key {
+            write!(f, "`{}`: ", key)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Checks one configuration file on its own: syntax, value types and keys
+/// that `Config` does not know about.
+pub fn check_file(file: &Path, content: &str) -> Vec<Diagnostic> {
+    let mut unknown = Vec::new();
+    let result = toml::de::Deserializer::parse(content).and_then(|deserializer| {
+        serde_ignored::deserialize(deserializer, |path| {
+            let mut segments = Vec::new();
+            path_segments(&path, &mut segments);
+            unknown.push(segments);
+        })
+    });
+
+    let mut diagnostics = Vec::new();
+    let result: Result<Config, toml::de::Error> = result;
+    if let Err(error) = result {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            file: Some(file.to_path_buf()),
+            position: error.span().map(|span| line_column(content, span.start)),
+            key: None,
+            message: error.message().to_string(),
+            path: Vec::new(),
+        });
+        return diagnostics;
+    }
+
+    for path in unknown {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            file: Some(file.to_path_buf()),
+            position: locate_key(content, &path),
+            message: "unknown key, it will be ignored".to_string(),
+            key: Some(path.join(".")),
+            path,
+        });
+    }
+
+    diagnostics.sort_by_key(|d| d.position);
+    diagnostics
+}
+
+/// Checks the merged configuration for settings that parse but cannot work.
+/// Repository checks are skipped when `repo` is `None`.
+pub fn check_semantics(layered: &LayeredConfig, repo: Option<&dyn GitBackend>) -> Vec<Diagnostic> {
+    let config = &layered.config;
+    let workflows = &config.workflows;
+    let mut diagnostics = Vec::new();
+
+    let prefixes = [
+        (
+            "workflows.feature_branch_prefix",
+            &workflows.feature_branch_prefix,
+        ),
+        (
+            "workflows.hotfix_branch_prefix",
+            &workflows.hotfix_branch_prefix,
+        ),
+        (
+            "workflows.release_branch_prefix",
+            &workflows.release_branch_prefix,
+        ),
+    ];
+    for (key, prefix) in prefixes {
+        let example = format!("{}example", prefix);
+        if !is_valid_branch_name(&example) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                Some(key.to_string()),
+                format!(
+                    "prefix '{}' produces invalid branch names such as '{}'",
+                    prefix, example
+                ),
+            ));
+        }
+    }
+
+    let mut branches = vec![("workflows.main_branch", &workflows.main_branch)];
+    if let Some(develop) = &workflows.develop_branch {
+        branches.push(("workflows.develop_branch", develop));
+    }
+
+    for (key, branch) in &branches {
+        if !is_valid_branch_name(branch) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                Some(key.to_string()),
+                format!("'{}' is not a valid branch name", branch),
+            ));
+        }
+    }
+
+    if !workflows.worktree_path.contains("{branch}") {
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            Some("workflows.worktree_path".to_string()),
+            "without '{branch}', every worktree gets the same path; pass a path to --worktree"
+                .to_string(),
+        ));
+    }
+
+    let protected = &config.cleanup.protect_branches;
+    if !protected.contains(&workflows.main_branch) {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            Some("cleanup.protect_branches".to_string()),
+            format!(
+                "main branch '{}' is not protected and could be deleted by 'gwf cleanup'",
+                workflows.main_branch
+            ),
+        ));
+    }
+    if let Some(develop) = &workflows.develop_branch {
+        if !protected.contains(develop) {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                Some("cleanup.protect_branches".to_string()),
+                format!("develop branch '{}' is not protected", develop),
+            ));
+        }
+    }
+
+    if config.sync.update_fork && !config.remote.is_fork() {
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            Some("sync.update_fork".to_string()),
+            format!(
+                "has no effect while remote.push and remote.upstream are both '{}'",
+                config.remote.push
+            ),
+        ));
+    }
+
+    let commits = &config.commits;
+    if commits.conventional && commits.types.is_empty() {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            Some("commits.types".to_string()),
+            "no commit types to choose from while commits.conventional is on".to_string(),
+        ));
+    }
+    for (index, kind) in commits.types.iter().enumerate() {
+        if commits.types[..index]
+            .iter()
+            .any(|other| other.name == kind.name)
+        {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                Some("commits.types".to_string()),
+                format!("type '{}' is listed more than once", kind.name),
+            ));
+        }
+    }
+    for (pattern, scope) in &commits.scope_paths {
+        let key = ["commits", "scope_paths", pattern.as_str()];
+        if let Err(error) = glob::Pattern::new(pattern) {
+            diagnostics.push(Diagnostic::at(
+                Severity::Error,
+                &key,
+                format!("invalid path pattern: {}", error.msg),
+            ));
+        } else if !commits.scopes.is_empty() && !commits.scopes.contains(scope) {
+            diagnostics.push(Diagnostic::at(
+                Severity::Error,
+                &key,
+                format!("scope '{}' is not in commits.scopes", scope),
+            ));
+        }
+    }
+
+    if config.lint.types.as_ref().is_some_and(Vec::is_empty) {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            Some("lint.types".to_string()),
+            "allows no commit type; remove it to accept the types `gwf commit` offers".to_string(),
+        ));
+    }
+
+    if let Some(ai) = &config.ai {
+        match ai.provider.as_str() {
+            "openai" => {}
+            "command" if ai.command.is_none() => diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                Some("ai.command".to_string()),
+                "must be set while ai.provider is 'command'".to_string(),
+            )),
+            "command" => {}
+            other => diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                Some("ai.provider".to_string()),
+                format!("unknown provider '{}'; use 'openai' or 'command'", other),
+            )),
+        }
+    }
+
+    for hook in HooksConfig::NAMES {
+        let commands = config.hooks.commands(hook).unwrap_or_default();
+        for command in commands
+            .iter()
+            .filter(|command| command.starts_with("gwf:"))
+        {
+            let key = Some(format!("hooks.{}", hook.replace('-', "_")));
+            match HooksConfig::BUILTINS
+                .iter()
+                .find(|(name, _)| name == command)
+            {
+                None => diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    key,
+                    format!(
+                        "unknown built-in action '{}'; use one of {}",
+                        command,
+                        HooksConfig::BUILTINS
+                            .iter()
+                            .map(|(name, _)| *name)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                )),
+                Some((_, expected)) if expected != hook => diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    key,
+                    format!("'{}' only works in the {} hook", command, expected),
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+
+    for (name, profile) in &config.profiles {
+        for pattern in &profile.remotes {
+            if let Err(error) = glob::Pattern::new(pattern) {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    Some(format!("profiles.{}.remotes", name)),
+                    format!("invalid remote pattern '{}': {}", pattern, error.msg),
+                ));
+            }
+        }
+    }
+
+    if let Some(repo) = repo {
+        // Without any remote there is nothing to push to yet, which is fine.
+        let remotes = repo.list_remotes().unwrap_or_default();
+        if !remotes.is_empty() {
+            let roles = [
+                ("remote.push", &config.remote.push),
+                ("remote.upstream", &config.remote.upstream),
+            ];
+            for (key, remote) in roles {
+                if !remotes.contains(remote) {
+                    diagnostics.push(Diagnostic::new(
+                        Severity::Error,
+                        Some(key.to_string()),
+                        format!(
+                            "remote '{}' does not exist; add it with 'git remote add {} <url>'",
+                            remote, remote
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let local = repo.list_branches(false).unwrap_or_default();
+        // A fresh clone or CI checkout may have no local branches at all.
+        if !local.is_empty() {
+            for (key, branch) in &branches {
+                if !local.contains(branch) {
+                    let severity = if *key == "workflows.main_branch" {
+                        Severity::Error
+                    } else {
+                        Severity::Warning
+                    };
+                    diagnostics.push(Diagnostic::new(
+                        severity,
+                        Some(key.to_string()),
+                        format!("branch '{}' does not exist locally", branch),
+                    ));
+                }
+            }
+        }
+    }
+
+    for diagnostic in &mut diagnostics {
+        locate_in_layers(layered, diagnostic);
+    }
+
+    diagnostics
+}
+
+fn is_valid_branch_name(name: &str) -> bool {
+    git2::Reference::is_valid_name(&format!("refs/heads/{}", name))
+}
+
+/// Points a diagnostic at the highest-precedence file that sets its key.
+fn locate_in_layers(layered: &LayeredConfig, diagnostic: &mut Diagnostic) {
+    if diagnostic.path.is_empty() {
+        return;
+    }
+
+    for (_, path) in layered.paths.files().into_iter().rev() {
+        let Ok(content) = fs::read_to_string(path) else {
+            continue;
+        };
+        if let Some(position) = locate_key(&content, &diagnostic.path) {
+            diagnostic.file = Some(path.to_path_buf());
+            diagnostic.position = Some(position);
+            return;
+        }
+    }
+}
+
+fn path_segments(path: &serde_ignored::Path, out: &mut Vec<String>) {
+    match path {
+        serde_ignored::Path::Root => {}
+        serde_ignored::Path::Seq { parent, index } => {
+            path_segments(parent, out);
+            out.push(index.to_string());
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            path_segments(parent, out);
+            out.push(key.clone());
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => path_segments(parent, out),
+    }
+}
+
+/// Finds the position of the key made of `path` in a TOML document.
+pub fn locate_key<S: AsRef<str>>(content: &str, path: &[S]) -> Option<(usize, usize)> {
+    let root = DeTable::parse(content).ok()?;
+    let mut table = root.get_ref();
+    let mut segments = path.iter().map(AsRef::as_ref).peekable();
+
+    while let Some(segment) = segments.next() {
+        let (found_key, value) = table.iter().find(|(k, _)| k.get_ref() == segment)?;
+        if segments.peek().is_none() {
+            return Some(line_column(content, found_key.span().start));
+        }
+        table = as_table(value)?;
+    }
+
+    None
+}
+
+fn as_table<'a, 'i>(value: &'a Spanned<DeValue<'i>>) -> Option<&'a DeTable<'i>> {
+    value.get_ref().as_table()
+}
+
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map(|line| line.chars().count())
+        .unwrap_or(0)
+        + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigPaths;
+
+    /// Runs the semantic checks on `toml` as the only configuration file.
+    fn semantic_diagnostics(toml: &str, repo: Option<&dyn GitBackend>) -> Vec<Diagnostic> {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join(".gwf.toml"), toml).unwrap();
+        let mut paths = ConfigPaths::for_directory(dir.path(), dir.path());
+        paths.global = None;
+        let layered = LayeredConfig::load_from(paths, Vec::new(), &[]).unwrap();
+
+        check_semantics(&layered, repo)
+    }
+
+    fn error_keys(diagnostics: &[Diagnostic]) -> Vec<&str> {
+        diagnostics
+            .iter()
+            .filter(|d| d.is_error())
+            .filter_map(|d| d.key.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn test_unknown_keys_are_located() {
+        let content = "[sync]\nstrategy = \"merge\"\nauto_stsh = false\n\n[ai]\nmodle = \"x\"\n";
+        let diagnostics = check_file(Path::new(".gwf.toml"), content);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].key.as_deref(), Some("sync.auto_stsh"));
+        assert_eq!(diagnostics[0].position, Some((3, 1)));
+        assert_eq!(diagnostics[1].key.as_deref(), Some("ai.modle"));
+        assert_eq!(diagnostics[1].position, Some((6, 1)));
+        assert!(diagnostics.iter().all(|d| !d.is_error()));
+    }
+
+    #[test]
+    fn test_type_errors_have_positions() {
+        let content = "[cleanup]\ndays_until_stale = \"thirty\"\n";
+        let diagnostics = check_file(Path::new(".gwf.toml"), content);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+        assert_eq!(diagnostics[0].position, Some((2, 20)));
+    }
+
+    #[test]
+    fn test_semantic_checks() {
+        let diagnostics = semantic_diagnostics(
+            "[workflows]\nfeature_branch_prefix = \"feat..\"\nmain_branch = \"trunk\"\n",
+            None,
+        );
+
+        assert_eq!(
+            error_keys(&diagnostics),
+            vec![
+                "workflows.feature_branch_prefix",
+                "cleanup.protect_branches"
+            ]
+        );
+        assert_eq!(diagnostics[0].position, Some((2, 1)));
+    }
+
+    #[test]
+    fn test_remote_roles_must_exist() {
+        let repo = crate::git::MemoryRepo::new();
+        repo.commit_on("main", "Initial commit");
+        repo.add_remote("origin", "git@github.com:me/api.git");
+
+        let diagnostics = semantic_diagnostics("[remote]\nupstream = \"upstream\"\n", Some(&repo));
+        let errors: Vec<_> = diagnostics.iter().filter(|d| d.is_error()).collect();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].key.as_deref(), Some("remote.upstream"));
+        assert_eq!(errors[0].position, Some((2, 1)));
+    }
+
+    #[test]
+    fn test_hook_actions_are_checked() {
+        let diagnostics = semantic_diagnostics(
+            "[hooks]\ncommit_msg = [\"gwf:lint\", \"gwf:spellcheck\"]\npre_commit = [\"gwf:lint\"]\n",
+            None,
+        );
+        let errors: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.is_error())
+            .map(|d| (d.key.as_deref().unwrap_or_default(), d.message.as_str()))
+            .collect();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].0, "hooks.pre_commit");
+        assert!(errors[0].1.contains("only works in the commit-msg hook"));
+        assert_eq!(errors[1].0, "hooks.commit_msg");
+        assert!(errors[1]
+            .1
+            .contains("unknown built-in action 'gwf:spellcheck'"));
+    }
+
+    #[test]
+    fn test_commit_types_and_scope_paths_are_checked() {
+        let diagnostics = semantic_diagnostics(
+            r#"[commits]
+scopes = ["api", "web"]
+types = [{ name = "feat" }, { name = "fix" }, { name = "feat" }]
+
+[commits.scope_paths]
+"crates/api/**" = "api"
+"crates/db/**" = "db"
+"crates/[web/**" = "web"
+"docs/*.md" = "docs"
+"#,
+            None,
+        );
+
+        assert_eq!(
+            error_keys(&diagnostics),
+            [
+                "commits.types",
+                "commits.scope_paths.crates/[web/**",
+                "commits.scope_paths.crates/db/**",
+                "commits.scope_paths.docs/*.md"
+            ]
+        );
+        let docs = diagnostics.last().unwrap();
+        assert_eq!(docs.position, Some((9, 1)));
+    }
+
+    #[test]
+    fn test_ai_provider_is_checked() {
+        let diagnostics = semantic_diagnostics("[ai]\nprovider = \"command\"\n", None);
+        assert_eq!(error_keys(&diagnostics), ["ai.command"]);
+    }
+}
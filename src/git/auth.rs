@@ -0,0 +1,335 @@
+use git2::{
+    CertificateCheckStatus, Config, Cred, CredentialType, ErrorClass, ErrorCode, RemoteCallbacks,
+};
+use std::fmt;
+use std::path::PathBuf;
+
+use super::error::{GitError, Result};
+
+/// Key files tried, in order, when the ssh-agent has no usable key.
+const SSH_KEY_FILES: &[&str] = &["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Reads an environment variable; replaced in tests.
+type Env = Box<dyn Fn(&str) -> Option<String>>;
+
+/// A credential offered to a remote. Listed in
+/// [`GitError::AuthenticationFailed`] so users can see what was attempted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthMethod {
+    SshAgent,
+    SshKey(PathBuf),
+    CredentialHelper,
+    /// A token from the named environment variable.
+    Token(String),
+    /// The platform's integrated authentication (NTLM or Negotiate).
+    Default,
+}
+
+impl fmt::Display for AuthMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthMethod::SshAgent => write!(f, "ssh-agent"),
+            AuthMethod::SshKey(path) => write!(f, "SSH key {}", path.display()),
+            AuthMethod::CredentialHelper => write!(f, "git credential helper"),
+            AuthMethod::Token(variable) => write!(f, "token from ${}", variable),
+            AuthMethod::Default => write!(f, "system default credentials"),
+        }
+    }
+}
+
+/// Answers libgit2's credential requests for one connection, offering each
+/// method at most once:
+///
+/// 1. SSH: the ssh-agent, then `$GWF_SSH_KEY` and the default key files in
+///    `~/.ssh`, unlocked with `$GWF_SSH_PASSPHRASE` if set.
+/// 2. HTTPS: the configured git credential helpers, then a token from
+///    `$GWF_GIT_TOKEN` or the host's usual variable (`$GITHUB_TOKEN`,
+///    `$GH_TOKEN`, `$GITLAB_TOKEN`).
+/// 3. The platform's integrated authentication, if the server offers it.
+pub(crate) struct Credentials {
+    config: Config,
+    env: Env,
+    ssh_dir: Option<PathBuf>,
+    tried: Vec<AuthMethod>,
+}
+
+impl Credentials {
+    pub(crate) fn new(config: Config) -> Self {
+        Self {
+            config,
+            env: Box::new(|name| std::env::var(name).ok()),
+            ssh_dir: dirs::home_dir().map(|home| home.join(".ssh")),
+            tried: Vec::new(),
+        }
+    }
+
+    /// Callbacks for a fetch or push that authenticate with these
+    /// credentials and apply `http.sslVerify`.
+    pub(crate) fn callbacks(&mut self) -> RemoteCallbacks<'_> {
+        let verify = ssl_verify(&self.config, &*self.env);
+
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(move |url, username, allowed| self.next(url, username, allowed));
+        callbacks.certificate_check(move |_, _| {
+            Ok(if verify {
+                CertificateCheckStatus::CertificatePassthrough
+            } else {
+                CertificateCheckStatus::CertificateOk
+            })
+        });
+        callbacks
+    }
+
+    /// Turns the error of a failed network operation into a `GitError`
+    /// naming the credentials that were tried.
+    pub(crate) fn error(&self, url: &str, context: &str, error: git2::Error) -> GitError {
+        if error.code() == ErrorCode::Auth {
+            return GitError::AuthenticationFailed {
+                url: url.to_string(),
+                tried: self.tried.clone(),
+            };
+        }
+        if error.code() == ErrorCode::Certificate || error.class() == ErrorClass::Ssl {
+            return GitError::Certificate {
+                url: url.to_string(),
+                source: error,
+            };
+        }
+
+        GitError::Git {
+            context: context.to_string(),
+            source: error,
+        }
+    }
+
+    fn next(
+        &mut self,
+        url: &str,
+        username: Option<&str>,
+        allowed: CredentialType,
+    ) -> std::result::Result<Cred, git2::Error> {
+        let username = username
+            .map(String::from)
+            .or_else(|| self.config.get_string("credential.username").ok());
+
+        // SSH asks for the user name first when the URL has none.
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(username.as_deref().unwrap_or("git"));
+        }
+
+        if allowed.contains(CredentialType::SSH_KEY) {
+            let user = username.as_deref().unwrap_or("git");
+            if self.has_agent() && self.try_method(AuthMethod::SshAgent) {
+                return Cred::ssh_key_from_agent(user);
+            }
+
+            let passphrase = (self.env)("GWF_SSH_PASSPHRASE");
+            for key in self.ssh_keys() {
+                if self.try_method(AuthMethod::SshKey(key.clone())) {
+                    return Cred::ssh_key(user, None, &key, passphrase.as_deref());
+                }
+            }
+        }
+
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            if !self.tried.contains(&AuthMethod::CredentialHelper) {
+                if let Ok(cred) = Cred::credential_helper(&self.config, url, username.as_deref()) {
+                    self.tried.push(AuthMethod::CredentialHelper);
+                    return Ok(cred);
+                }
+            }
+
+            for variable in token_variables(url) {
+                let Some(token) = (self.env)(variable).filter(|token| !token.is_empty()) else {
+                    continue;
+                };
+                if self.try_method(AuthMethod::Token(variable.to_string())) {
+                    let user = username.unwrap_or_else(|| token_user(url).to_string());
+                    return Cred::userpass_plaintext(&user, &token);
+                }
+            }
+        }
+
+        if allowed.contains(CredentialType::DEFAULT) && self.try_method(AuthMethod::Default) {
+            return Cred::default();
+        }
+
+        Err(git2::Error::new(
+            ErrorCode::Auth,
+            ErrorClass::Callback,
+            "no more credentials to try",
+        ))
+    }
+
+    /// Records `method` as tried; false if it already was.
+    fn try_method(&mut self, method: AuthMethod) -> bool {
+        if self.tried.contains(&method) {
+            return false;
+        }
+        self.tried.push(method);
+        true
+    }
+
+    fn has_agent(&self) -> bool {
+        cfg!(windows) || (self.env)("SSH_AUTH_SOCK").is_some()
+    }
+
+    fn ssh_keys(&self) -> Vec<PathBuf> {
+        let mut keys: Vec<PathBuf> = (self.env)("GWF_SSH_KEY")
+            .map(PathBuf::from)
+            .into_iter()
+            .collect();
+        if let Some(dir) = &self.ssh_dir {
+            keys.extend(SSH_KEY_FILES.iter().map(|name| dir.join(name)));
+        }
+        keys.retain(|key| key.is_file());
+        keys
+    }
+}
+
+/// Environment variables that may hold a token for the host in `url`.
+fn token_variables(url: &str) -> Vec<&'static str> {
+    let mut variables = vec!["GWF_GIT_TOKEN"];
+    if url.contains("github") {
+        variables.extend(["GITHUB_TOKEN", "GH_TOKEN"]);
+    } else if url.contains("gitlab") {
+        variables.push("GITLAB_TOKEN");
+    }
+    variables
+}
+
+/// The user name hosts expect alongside a token.
+fn token_user(url: &str) -> &'static str {
+    if url.contains("gitlab") {
+        "oauth2"
+    } else {
+        "x-access-token"
+    }
+}
+
+fn ssl_verify(config: &Config, env: &dyn Fn(&str) -> Option<String>) -> bool {
+    if env("GIT_SSL_NO_VERIFY").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    config.get_bool("http.sslVerify").unwrap_or(true)
+}
+
+/// Points libgit2 at the CA bundle or directory configured in
+/// `http.sslCAInfo` and `http.sslCAPath` (or `$GIT_SSL_CAINFO` and
+/// `$GIT_SSL_CAPATH`), as git does for HTTPS remotes.
+pub(crate) fn configure_certificates(config: &Config) -> Result<()> {
+    let setting = |variable: &str, key: &str| {
+        std::env::var(variable)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .or_else(|| {
+                config
+                    .get_path(key)
+                    .ok()
+                    .map(|path| path.display().to_string())
+            })
+    };
+
+    // SAFETY: libgit2 reads these options when it opens a connection. They
+    // are only changed here, right before gwf connects to a remote, and
+    // never while another connection is in progress.
+    if let Some(file) = setting("GIT_SSL_CAINFO", "http.sslCAInfo") {
+        unsafe { git2::opts::set_ssl_cert_file(file.as_str()) }?;
+    }
+    if let Some(dir) = setting("GIT_SSL_CAPATH", "http.sslCAPath") {
+        unsafe { git2::opts::set_ssl_cert_dir(dir.as_str()) }?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn credentials(env: &[(&str, &str)], ssh_dir: Option<PathBuf>) -> Credentials {
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        Credentials {
+            config: Config::new().unwrap(),
+            env: Box::new(move |name| env.get(name).cloned()),
+            ssh_dir,
+            tried: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_ssh_tries_agent_then_each_key_once() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("id_rsa"), "").unwrap();
+        let mut credentials = credentials(
+            &[("SSH_AUTH_SOCK", "/tmp/agent.sock")],
+            Some(dir.path().to_path_buf()),
+        );
+        let url = "git@github.com:acme/api.git";
+
+        while credentials
+            .next(url, Some("git"), CredentialType::SSH_KEY)
+            .is_ok()
+        {}
+
+        assert_eq!(
+            credentials.tried,
+            vec![
+                AuthMethod::SshAgent,
+                AuthMethod::SshKey(dir.path().join("id_rsa"))
+            ]
+        );
+        let error = credentials.error(
+            url,
+            "Failed to fetch",
+            git2::Error::new(ErrorCode::Auth, ErrorClass::Ssh, "denied"),
+        );
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Authentication failed for {} (tried: ssh-agent, SSH key {})",
+                url,
+                dir.path().join("id_rsa").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_https_uses_host_token() {
+        let mut credentials =
+            credentials(&[("GITLAB_TOKEN", "secret"), ("GITHUB_TOKEN", "")], None);
+
+        let github = "https://github.com/acme/api.git";
+        assert!(credentials
+            .next(github, None, CredentialType::USER_PASS_PLAINTEXT)
+            .is_err());
+        assert!(credentials.tried.is_empty());
+
+        let gitlab = "https://gitlab.com/acme/api.git";
+        assert!(credentials
+            .next(gitlab, None, CredentialType::USER_PASS_PLAINTEXT)
+            .is_ok());
+        assert_eq!(
+            credentials.tried,
+            vec![AuthMethod::Token("GITLAB_TOKEN".to_string())]
+        );
+        assert_eq!(
+            credentials
+                .error(
+                    gitlab,
+                    "Failed to fetch",
+                    git2::Error::new(ErrorCode::Auth, ErrorClass::Http, "401")
+                )
+                .to_string(),
+            format!(
+                "Authentication failed for {} (tried: token from $GITLAB_TOKEN)",
+                gitlab
+            )
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use git2::Oid;
+use std::path::{Path, PathBuf};
+
+use super::{
+    BisectMark, BisectStep, BranchActivity, CommitInfo, CommitOptions, FetchObserver, GitRepo,
+    LogOptions, MergeOutcome, PushOptions, RepoState, ResetMode, Result, StashEntry, StatusSummary,
+    WorktreeInfo,
+};
+
+/// The repository operations commands use. Commands take a backend instead
+/// of opening a repository themselves, so they can be run against
+/// [`MemoryRepo`](super::MemoryRepo) or a mock in tests.
+///
+/// See the inherent methods of [`GitRepo`] for what each operation does.
+// mockall needs the lifetimes of `Option<&str>` arguments spelled out.
+#[allow(clippy::needless_lifetimes)]
+#[cfg_attr(test, mockall::automock)]
+pub trait GitBackend {
+    fn workdir(&self) -> Result<PathBuf>;
+    fn current_branch(&self) -> Result<String>;
+    fn state(&self) -> Result<RepoState>;
+    fn list_remotes(&self) -> Result<Vec<String>>;
+    fn remote_urls(&self) -> Result<Vec<String>>;
+    fn remote_url(&self, remote: &str) -> Result<String>;
+    fn remote_default_branch(&self, remote: &str) -> Option<String>;
+    fn config_bool(&self, name: &str) -> Option<bool>;
+    fn config_string(&self, name: &str) -> Option<String>;
+
+    fn create_branch<'a>(&self, name: &str, from: Option<&'a str>) -> Result<()>;
+    fn checkout(&self, branch_name: &str) -> Result<()>;
+    fn list_branches(&self, include_remote: bool) -> Result<Vec<String>>;
+    fn is_branch_merged(&self, branch_name: &str, into: &str) -> Result<bool>;
+    fn delete_branch(&self, branch_name: &str) -> Result<()>;
+
+    fn add_worktree(&self, branch: &str, path: &Path) -> Result<WorktreeInfo>;
+    fn worktrees(&self) -> Result<Vec<WorktreeInfo>>;
+    fn remove_worktree(&self, target: &str, force: bool) -> Result<WorktreeInfo>;
+    fn prune_worktrees(&self) -> Result<Vec<WorktreeInfo>>;
+
+    fn has_uncommitted_changes(&self) -> Result<bool>;
+    fn status(&self) -> Result<StatusSummary>;
+    fn staged_files(&self) -> Result<Vec<String>>;
+    fn staged_diff(&self, amend: bool) -> Result<String>;
+    fn ahead_behind(&self, local: &str, upstream: &str) -> Result<Option<(usize, usize)>>;
+
+    fn fetch(&self, remote: &str, prune: bool, progress: &mut dyn FetchObserver) -> Result<()>;
+    fn push(&self, remote: &str, refs: &[String], options: &PushOptions) -> Result<()>;
+    fn upstream(&self, branch: &str) -> Option<String>;
+    fn merge(&self, upstream: &str) -> Result<MergeOutcome>;
+    fn fast_forward(&self, branch: &str, upstream: &str) -> Result<MergeOutcome>;
+    fn rebase(&self, upstream: &str) -> Result<()>;
+    fn abort_merge(&self) -> Result<()>;
+    fn reset(&self, revision: &str, mode: ResetMode) -> Result<()>;
+    fn stash_save(&mut self, message: &str) -> Result<Option<Oid>>;
+    fn stash_pop(&mut self, stash: Oid) -> Result<()>;
+    fn stashes(&mut self) -> Result<Vec<StashEntry>>;
+    fn commit(&self, message: &str, options: &CommitOptions) -> Result<Oid>;
+
+    fn log(&self, options: &LogOptions) -> Result<Vec<CommitInfo>>;
+    fn recent_branches(&self, limit: usize) -> Result<Vec<BranchActivity>>;
+    fn tags(&self) -> Result<Vec<String>>;
+    fn latest_tag(&self) -> Result<Option<String>>;
+    fn create_tag<'a>(&self, name: &str, message: &str, target: Option<&'a str>) -> Result<Oid>;
+
+    fn is_bisecting(&self) -> bool;
+    fn bisect_start(&self, bad: &str, good: &[String]) -> Result<BisectStep>;
+    fn bisect_mark<'a>(&self, mark: BisectMark, revision: Option<&'a str>) -> Result<BisectStep>;
+    fn bisect_reset(&self) -> Result<()>;
+    fn bisect_run(&self, command: &Path) -> Result<CommitInfo>;
+}
+
+impl GitBackend for GitRepo {
+    fn workdir(&self) -> Result<PathBuf> {
+        GitRepo::workdir(self)
+    }
+
+    fn current_branch(&self) -> Result<String> {
+        GitRepo::current_branch(self)
+    }
+
+    fn state(&self) -> Result<RepoState> {
+        GitRepo::state(self)
+    }
+
+    fn list_remotes(&self) -> Result<Vec<String>> {
+        GitRepo::list_remotes(self)
+    }
+
+    fn remote_urls(&self) -> Result<Vec<String>> {
+        GitRepo::remote_urls(self)
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<String> {
+        GitRepo::remote_url(self, remote)
+    }
+
+    fn remote_default_branch(&self, remote: &str) -> Option<String> {
+        GitRepo::remote_default_branch(self, remote)
+    }
+
+    fn config_bool(&self, name: &str) -> Option<bool> {
+        GitRepo::config_bool(self, name)
+    }
+
+    fn config_string(&self, name: &str) -> Option<String> {
+        GitRepo::config_string(self, name)
+    }
+
+    fn create_branch(&self, name: &str, from: Option<&str>) -> Result<()> {
+        GitRepo::create_branch(self, name, from)
+    }
+
+    fn checkout(&self, branch_name: &str) -> Result<()> {
+        GitRepo::checkout(self, branch_name)
+    }
+
+    fn list_branches(&self, include_remote: bool) -> Result<Vec<String>> {
+        GitRepo::list_branches(self, include_remote)
+    }
+
+    fn is_branch_merged(&self, branch_name: &str, into: &str) -> Result<bool> {
+        GitRepo::is_branch_merged(self, branch_name, into)
+    }
+
+    fn delete_branch(&self, branch_name: &str) -> Result<()> {
+        GitRepo::delete_branch(self, branch_name)
+    }
+
+    fn add_worktree(&self, branch: &str, path: &Path) -> Result<WorktreeInfo> {
+        GitRepo::add_worktree(self, branch, path)
+    }
+
+    fn worktrees(&self) -> Result<Vec<WorktreeInfo>> {
+        GitRepo::worktrees(self)
+    }
+
+    fn remove_worktree(&self, target: &str, force: bool) -> Result<WorktreeInfo> {
+        GitRepo::remove_worktree(self, target, force)
+    }
+
+    fn prune_worktrees(&self) -> Result<Vec<WorktreeInfo>> {
+        GitRepo::prune_worktrees(self)
+    }
+
+    fn has_uncommitted_changes(&self) -> Result<bool> {
+        GitRepo::has_uncommitted_changes(self)
+    }
+
+    fn status(&self) -> Result<StatusSummary> {
+        GitRepo::status(self)
+    }
+
+    fn staged_files(&self) -> Result<Vec<String>> {
+        GitRepo::staged_files(self)
+    }
+
+    fn staged_diff(&self, amend: bool) -> Result<String> {
+        GitRepo::staged_diff(self, amend)
+    }
+
+    fn ahead_behind(&self, local: &str, upstream: &str) -> Result<Option<(usize, usize)>> {
+        GitRepo::ahead_behind(self, local, upstream)
+    }
+
+    fn fetch(&self, remote: &str, prune: bool, progress: &mut dyn FetchObserver) -> Result<()> {
+        GitRepo::fetch(self, remote, prune, progress)
+    }
+
+    fn push(&self, remote: &str, refs: &[String], options: &PushOptions) -> Result<()> {
+        GitRepo::push(self, remote, refs, options)
+    }
+
+    fn upstream(&self, branch: &str) -> Option<String> {
+        GitRepo::upstream(self, branch)
+    }
+
+    fn merge(&self, upstream: &str) -> Result<MergeOutcome> {
+        GitRepo::merge(self, upstream)
+    }
+
+    fn fast_forward(&self, branch: &str, upstream: &str) -> Result<MergeOutcome> {
+        GitRepo::fast_forward(self, branch, upstream)
+    }
+
+    fn rebase(&self, upstream: &str) -> Result<()> {
+        GitRepo::rebase(self, upstream)
+    }
+
+    fn abort_merge(&self) -> Result<()> {
+        GitRepo::abort_merge(self)
+    }
+
+    fn reset(&self, revision: &str, mode: ResetMode) -> Result<()> {
+        GitRepo::reset(self, revision, mode)
+    }
+
+    fn stash_save(&mut self, message: &str) -> Result<Option<Oid>> {
+        GitRepo::stash_save(self, message)
+    }
+
+    fn stash_pop(&mut self, stash: Oid) -> Result<()> {
+        GitRepo::stash_pop(self, stash)
+    }
+
+    fn stashes(&mut self) -> Result<Vec<StashEntry>> {
+        GitRepo::stashes(self)
+    }
+
+    fn commit(&self, message: &str, options: &CommitOptions) -> Result<Oid> {
+        GitRepo::commit(self, message, options)
+    }
+
+    fn log(&self, options: &LogOptions) -> Result<Vec<CommitInfo>> {
+        GitRepo::log(self, options)
+    }
+
+    fn recent_branches(&self, limit: usize) -> Result<Vec<BranchActivity>> {
+        GitRepo::recent_branches(self, limit)
+    }
+
+    fn tags(&self) -> Result<Vec<String>> {
+        GitRepo::tags(self)
+    }
+
+    fn latest_tag(&self) -> Result<Option<String>> {
+        GitRepo::latest_tag(self)
+    }
+
+    fn create_tag(&self, name: &str, message: &str, target: Option<&str>) -> Result<Oid> {
+        GitRepo::create_tag(self, name, message, target)
+    }
+
+    fn is_bisecting(&self) -> bool {
+        GitRepo::is_bisecting(self)
+    }
+
+    fn bisect_start(&self, bad: &str, good: &[String]) -> Result<BisectStep> {
+        GitRepo::bisect_start(self, bad, good)
+    }
+
+    fn bisect_mark(&self, mark: BisectMark, revision: Option<&str>) -> Result<BisectStep> {
+        GitRepo::bisect_mark(self, mark, revision)
+    }
+
+    fn bisect_reset(&self) -> Result<()> {
+        GitRepo::bisect_reset(self)
+    }
+
+    fn bisect_run(&self, command: &Path) -> Result<CommitInfo> {
+        GitRepo::bisect_run(self, command)
+    }
+}
@@ -0,0 +1,263 @@
+use git2::build::CheckoutBuilder;
+use git2::{Oid, Sort};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use super::error::{GitContext, GitError, Result};
+use super::history::CommitInfo;
+use super::GitRepo;
+
+/// State files written by `git bisect`; keeping them compatible lets the
+/// session be continued with plain git.
+const STATE_FILES: &[&str] = &[
+    "BISECT_START",
+    "BISECT_TERMS",
+    "BISECT_LOG",
+    "BISECT_NAMES",
+    "BISECT_EXPECTED_REV",
+    "BISECT_ANCESTORS_OK",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BisectMark {
+    Good,
+    Bad,
+    Skip,
+}
+
+impl BisectMark {
+    fn term(self) -> &'static str {
+        match self {
+            BisectMark::Good => "good",
+            BisectMark::Bad => "bad",
+            BisectMark::Skip => "skip",
+        }
+    }
+}
+
+/// Where a bisect session stands after a mark.
+#[derive(Debug, Clone)]
+pub enum BisectStep {
+    /// `commit` is checked out and waits to be marked. `remaining` is the
+    /// number of revisions that could still be the first bad one.
+    Testing {
+        commit: CommitInfo,
+        remaining: usize,
+    },
+    /// The first bad commit.
+    Found(CommitInfo),
+    /// Bisecting needs a bad and at least one good revision.
+    NeedsMarks,
+}
+
+impl GitRepo {
+    pub fn is_bisecting(&self) -> bool {
+        self.repo.path().join("BISECT_START").exists()
+    }
+
+    /// Starts a session between `bad` and `good`, remembering the current
+    /// branch so `bisect_reset` can return to it.
+    pub fn bisect_start(&self, bad: &str, good: &[String]) -> Result<BisectStep> {
+        let bad = self.find_commit(bad)?.id();
+        let good = good
+            .iter()
+            .map(|revision| self.find_commit(revision).map(|commit| commit.id()))
+            .collect::<Result<Vec<Oid>>>()?;
+
+        let start = if self.is_bisecting() {
+            fs::read_to_string(self.repo.path().join("BISECT_START"))?
+        } else {
+            match self.current_branch() {
+                Ok(branch) => branch,
+                Err(_) => self.find_commit("HEAD")?.id().to_string(),
+            }
+        };
+        self.clear_bisect_state()?;
+
+        let dir = self.repo.path();
+        fs::write(dir.join("BISECT_START"), format!("{}\n", start.trim()))?;
+        fs::write(dir.join("BISECT_TERMS"), "bad\ngood\n")?;
+        fs::write(dir.join("BISECT_NAMES"), "\n")?;
+        fs::write(dir.join("BISECT_LOG"), "git bisect start\n")?;
+
+        self.record_mark(BisectMark::Bad, bad)?;
+        for oid in good {
+            self.record_mark(BisectMark::Good, oid)?;
+        }
+
+        self.bisect_next()
+    }
+
+    /// Marks `revision`, or the checked out commit, and moves to the next
+    /// commit to test.
+    pub fn bisect_mark(&self, mark: BisectMark, revision: Option<&str>) -> Result<BisectStep> {
+        if !self.is_bisecting() {
+            return Err(GitError::NotBisecting);
+        }
+
+        let oid = self.find_commit(revision.unwrap_or("HEAD"))?.id();
+        self.record_mark(mark, oid)?;
+        self.bisect_next()
+    }
+
+    /// Ends the session and returns to where it was started.
+    pub fn bisect_reset(&self) -> Result<()> {
+        if !self.is_bisecting() {
+            return Err(GitError::NotBisecting);
+        }
+
+        let start = fs::read_to_string(self.repo.path().join("BISECT_START"))?;
+        let start = start.trim();
+        if self
+            .repo
+            .find_branch(start, git2::BranchType::Local)
+            .is_ok()
+        {
+            self.checkout(start)?;
+        } else {
+            self.checkout_detached(self.find_commit(start)?.id())?;
+        }
+
+        self.clear_bisect_state()?;
+        for file in STATE_FILES {
+            let path = self.repo.path().join(file);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks commits automatically with `command`, like `git bisect run`: exit
+    /// code 0 is good, 125 skips the commit, 1-127 is bad, anything else
+    /// stops the session.
+    pub fn bisect_run(&self, command: &Path) -> Result<CommitInfo> {
+        let workdir = self.workdir()?;
+        let mut step = self.bisect_next()?;
+
+        loop {
+            match step {
+                BisectStep::Found(commit) => return Ok(commit),
+                BisectStep::NeedsMarks => return Err(GitError::NotBisecting),
+                BisectStep::Testing { .. } => {}
+            }
+
+            let status = Command::new(command).current_dir(&workdir).status()?;
+            let mark = match status.code() {
+                Some(0) => BisectMark::Good,
+                Some(125) => BisectMark::Skip,
+                Some(code) if (1..128).contains(&code) => BisectMark::Bad,
+                _ => {
+                    return Err(GitError::BisectAborted(format!(
+                        "{} exited with {}",
+                        command.display(),
+                        status
+                    )))
+                }
+            };
+            step = self.bisect_mark(mark, None)?;
+        }
+    }
+
+    /// Picks the next commit to test: the candidate nearest the middle of
+    /// the revisions between the good and bad marks.
+    fn bisect_next(&self) -> Result<BisectStep> {
+        let Some(bad) = self.bisect_refs("refs/bisect/bad")?.into_iter().next() else {
+            return Ok(BisectStep::NeedsMarks);
+        };
+        let good = self.bisect_refs("refs/bisect/good-*")?;
+        if good.is_empty() {
+            return Ok(BisectStep::NeedsMarks);
+        }
+        let skipped = self.bisect_refs("refs/bisect/skip-*")?;
+
+        let mut revwalk = self.repo.revwalk().git_context("Failed to walk history")?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+        revwalk.push(bad)?;
+        for oid in &good {
+            revwalk.hide(*oid)?;
+        }
+        let candidates = revwalk.collect::<std::result::Result<Vec<Oid>, _>>()?;
+
+        let testable: Vec<(usize, Oid)> = candidates
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, oid)| *oid != bad && !skipped.contains(oid))
+            .collect();
+
+        if testable.is_empty() {
+            let possible: Vec<&Oid> = candidates
+                .iter()
+                .filter(|oid| **oid == bad || skipped.contains(oid))
+                .collect();
+            if possible.len() == 1 {
+                let commit = self.repo.find_commit(bad)?;
+                return Ok(BisectStep::Found(CommitInfo::from_commit(&commit)));
+            }
+            return Err(GitError::BisectInconclusive(
+                possible.iter().map(|oid| oid.to_string()).collect(),
+            ));
+        }
+
+        let middle = candidates.len() / 2;
+        let (_, next) = testable
+            .iter()
+            .min_by_key(|(position, _)| position.abs_diff(middle))
+            .copied()
+            .expect("testable is not empty");
+
+        self.checkout_detached(next)?;
+        let commit = self.repo.find_commit(next)?;
+        Ok(BisectStep::Testing {
+            commit: CommitInfo::from_commit(&commit),
+            remaining: candidates.len() - 1,
+        })
+    }
+
+    fn record_mark(&self, mark: BisectMark, oid: Oid) -> Result<()> {
+        let name = match mark {
+            BisectMark::Bad => "refs/bisect/bad".to_string(),
+            _ => format!("refs/bisect/{}-{}", mark.term(), oid),
+        };
+        self.repo
+            .reference(&name, oid, true, "bisect")
+            .git_context("Failed to record bisect mark")?;
+
+        let mut log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.repo.path().join("BISECT_LOG"))?;
+        writeln!(log, "git bisect {} {}", mark.term(), oid)?;
+        Ok(())
+    }
+
+    fn bisect_refs(&self, glob: &str) -> Result<Vec<Oid>> {
+        let mut oids = Vec::new();
+        for reference in self.repo.references_glob(glob)? {
+            if let Some(oid) = reference?.target() {
+                oids.push(oid);
+            }
+        }
+        Ok(oids)
+    }
+
+    fn clear_bisect_state(&self) -> Result<()> {
+        for reference in self.repo.references_glob("refs/bisect/*")? {
+            reference?.delete()?;
+        }
+        Ok(())
+    }
+
+    fn checkout_detached(&self, oid: Oid) -> Result<()> {
+        let commit = self.repo.find_commit(oid)?;
+        self.repo
+            .checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+            .git_context(format!("Failed to check out {}", oid))?;
+        self.repo
+            .set_head_detached(oid)
+            .git_context("Failed to update HEAD")
+    }
+}
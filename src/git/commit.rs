@@ -0,0 +1,266 @@
+use git2::{Commit, Oid};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use super::error::{GitContext, GitError, Result};
+use super::GitRepo;
+
+/// How `GitRepo::commit` builds the commit.
+#[derive(Debug, Default, Clone)]
+pub struct CommitOptions {
+    /// Replace the commit at HEAD instead of adding a new one.
+    pub amend: bool,
+    /// Sign the commit with the program selected by `gpg.format`.
+    pub sign: bool,
+    /// Key to sign with; falls back to `user.signingkey`.
+    pub signing_key: Option<String>,
+    /// Skip the pre-commit and commit-msg hooks, like `git commit --no-verify`.
+    pub no_verify: bool,
+}
+
+impl GitRepo {
+    /// Commits the index to the current branch. Like `git commit`, this runs
+    /// the pre-commit, commit-msg and post-commit hooks.
+    pub fn commit(&self, message: &str, options: &CommitOptions) -> Result<Oid> {
+        let mut message = message.to_string();
+        if !options.no_verify {
+            self.run_hook("pre-commit", &[])?;
+            message = self.run_commit_msg_hook(&message)?;
+        }
+
+        let message = git2::message_prettify(&message, None)?;
+        if message.trim().is_empty() {
+            return Err(GitError::EmptyMessage);
+        }
+
+        // Hooks may have staged files, so read the index from disk again.
+        let mut index = self.repo.index()?;
+        index.read(false).git_context("Failed to read the index")?;
+        if index.has_conflicts() {
+            return Err(GitError::Conflicts(self.conflicted_paths()?));
+        }
+        let tree_id = index.write_tree().git_context("Failed to write tree")?;
+        let tree = self.repo.find_tree(tree_id)?;
+
+        let committer = self
+            .repo
+            .signature()
+            .git_context("Failed to determine the git user (set user.name and user.email)")?;
+        let head = self
+            .repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+
+        let (parents, author): (Vec<Commit>, _) = match head {
+            Some(head) if options.amend => (head.parents().collect(), head.author().to_owned()),
+            None if options.amend => return Err(GitError::RevisionNotFound("HEAD".to_string())),
+            Some(head) if head.tree_id() == tree_id => return Err(GitError::NothingToCommit),
+            None if index.is_empty() => return Err(GitError::NothingToCommit),
+            head => (head.into_iter().collect(), committer.clone()),
+        };
+        let parent_refs: Vec<&Commit> = parents.iter().collect();
+
+        let oid = if options.sign {
+            let buffer = self
+                .repo
+                .commit_create_buffer(&author, &committer, &message, &tree, &parent_refs)
+                .git_context("Failed to create commit")?;
+            let content = String::from_utf8_lossy(&buffer).into_owned();
+            let signature = self.sign(&content, options.signing_key.as_deref())?;
+            self.repo
+                .commit_signed(&content, &signature, None)
+                .git_context("Failed to create signed commit")?
+        } else {
+            self.repo
+                .commit(None, &author, &committer, &message, &tree, &parent_refs)
+                .git_context("Failed to create commit")?
+        };
+
+        let summary = message.lines().next().unwrap_or_default();
+        let reflog = if options.amend {
+            format!("commit (amend): {}", summary)
+        } else if parents.is_empty() {
+            format!("commit (initial): {}", summary)
+        } else {
+            format!("commit: {}", summary)
+        };
+        self.update_head(oid, &reflog)?;
+
+        // Like git, a failing post-commit hook does not undo the commit.
+        self.run_hook("post-commit", &[]).ok();
+
+        Ok(oid)
+    }
+
+    /// Runs a hook from `core.hooksPath` or `.git/hooks`, if one is installed.
+    pub fn run_hook(&self, name: &str, args: &[&str]) -> Result<()> {
+        let Some(path) = self.hook_path(name) else {
+            return Ok(());
+        };
+
+        let workdir = self
+            .workdir()
+            .unwrap_or_else(|_| self.repo.path().to_path_buf());
+        let status = Command::new(&path)
+            .args(args)
+            .current_dir(workdir)
+            .status()?;
+
+        if !status.success() {
+            return Err(GitError::HookFailed(name.to_string()));
+        }
+        Ok(())
+    }
+
+    fn hook_path(&self, name: &str) -> Option<PathBuf> {
+        let path = self.hooks_dir().join(name);
+        is_executable(&path).then_some(path)
+    }
+
+    /// Where git looks for hooks: `core.hooksPath`, relative paths resolved
+    /// against the working directory, or `.git/hooks`.
+    pub fn hooks_dir(&self) -> PathBuf {
+        match self.config_string("core.hooksPath") {
+            Some(dir) => {
+                let dir = PathBuf::from(dir);
+                match self.workdir() {
+                    Ok(workdir) if dir.is_relative() => workdir.join(dir),
+                    _ => dir,
+                }
+            }
+            None => self.default_hooks_dir(),
+        }
+    }
+
+    /// `.git/hooks`, shared by every worktree of the repository.
+    pub fn default_hooks_dir(&self) -> PathBuf {
+        self.repo.commondir().join("hooks")
+    }
+
+    /// Passes the message through the commit-msg hook, which may rewrite it.
+    fn run_commit_msg_hook(&self, message: &str) -> Result<String> {
+        if self.hook_path("commit-msg").is_none() {
+            return Ok(message.to_string());
+        }
+
+        let path = self.repo.path().join("COMMIT_EDITMSG");
+        fs::write(&path, message)?;
+        self.run_hook("commit-msg", &[&path.to_string_lossy()])?;
+
+        Ok(fs::read_to_string(&path)?)
+    }
+
+    /// Points the current branch, or a detached HEAD, at `oid`.
+    pub(super) fn update_head(&self, oid: Oid, reflog: &str) -> Result<()> {
+        let head = self.repo.find_reference("HEAD")?;
+        match head.symbolic_target() {
+            Some(branch) => {
+                self.repo
+                    .reference(branch, oid, true, reflog)
+                    .git_context("Failed to update the current branch")?;
+            }
+            None => self
+                .repo
+                .set_head_detached(oid)
+                .git_context("Failed to update HEAD")?,
+        }
+        Ok(())
+    }
+
+    /// Produces a detached signature for `content` the way git would, using
+    /// `gpg.format` and the matching `gpg.*.program`.
+    fn sign(&self, content: &str, key: Option<&str>) -> Result<String> {
+        let key = key
+            .map(String::from)
+            .or_else(|| self.config_string("user.signingkey"));
+        let format = self
+            .config_string("gpg.format")
+            .unwrap_or_else(|| "openpgp".to_string());
+
+        let (program, args) = match format.as_str() {
+            "ssh" => {
+                let key = key.ok_or_else(|| {
+                    GitError::Signing(
+                        "gpg.format is ssh but user.signingkey is not set".to_string(),
+                    )
+                })?;
+                let program = self
+                    .config_string("gpg.ssh.program")
+                    .unwrap_or_else(|| "ssh-keygen".to_string());
+                let args = ["-Y", "sign", "-n", "git", "-f", &key].map(String::from);
+                (program, args.to_vec())
+            }
+            "x509" => {
+                let program = self
+                    .config_string("gpg.x509.program")
+                    .unwrap_or_else(|| "gpgsm".to_string());
+                (program, gpg_args(key))
+            }
+            _ => {
+                let program = self
+                    .config_string("gpg.openpgp.program")
+                    .or_else(|| self.config_string("gpg.program"))
+                    .unwrap_or_else(|| "gpg".to_string());
+                (program, gpg_args(key))
+            }
+        };
+
+        let mut child = Command::new(&program)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| GitError::Signing(format!("could not run {}: {}", program, error)))?;
+
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(content.as_bytes())?;
+        let output = child.wait_with_output()?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let created = format == "ssh" || stderr.contains("[GNUPG:] SIG_CREATED");
+        if !output.status.success() || !created {
+            let reason = stderr
+                .lines()
+                .filter(|line| !line.starts_with("[GNUPG:]"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Err(GitError::Signing(format!(
+                "{} failed: {}",
+                program,
+                reason.trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+fn gpg_args(key: Option<String>) -> Vec<String> {
+    let mut args = vec!["--status-fd=2".to_string()];
+    match key {
+        Some(key) => args.extend(["-bsau".to_string(), key]),
+        None => args.push("-bsa".to_string()),
+    }
+    args
+}
+
+#[cfg(unix)]
+pub(crate) fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file()
+}
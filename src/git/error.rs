@@ -0,0 +1,138 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+use super::auth::AuthMethod;
+use super::push::RejectedRef;
+use super::state::Operation;
+
+/// Errors from repository operations. Callers that need to react to a
+/// specific failure can match on the variant; everything else carries the
+/// underlying libgit2 error as its source.
+#[derive(Debug, Error)]
+pub enum GitError {
+    #[error("Not in a git repository")]
+    NotARepository(#[source] git2::Error),
+
+    #[error("Repository has no working directory (bare repository)")]
+    BareRepository,
+
+    #[error("HEAD is not pointing to a branch (detached HEAD state)")]
+    DetachedHead,
+
+    #[error("A {0} is in progress; {}", .0.hint())]
+    OperationInProgress(Operation),
+
+    #[error("Branch '{0}' not found")]
+    BranchNotFound(String),
+
+    #[error("Remote '{0}' not found")]
+    RemoteNotFound(String),
+
+    #[error("Revision '{0}' not found")]
+    RevisionNotFound(String),
+
+    #[error("Tag '{0}' already exists")]
+    TagExists(String),
+
+    #[error("Nothing to commit (use 'git add' to stage changes)")]
+    NothingToCommit,
+
+    #[error("Aborting commit due to empty commit message")]
+    EmptyMessage,
+
+    #[error("Branch '{0}' has diverged from its upstream and needs a merge")]
+    Diverged(String),
+
+    #[error("Conflicts in {}", .0.join(", "))]
+    Conflicts(Vec<String>),
+
+    #[error("No merge in progress")]
+    NoMergeInProgress,
+
+    #[error("No bisect in progress")]
+    NotBisecting,
+
+    #[error("Only skipped commits are left to test; the first bad commit is one of {}", .0.join(", "))]
+    BisectInconclusive(Vec<String>),
+
+    #[error("Bisect run stopped: {0}")]
+    BisectAborted(String),
+
+    #[error("'{}' already exists; choose another worktree path", .0.display())]
+    WorktreePathExists(PathBuf),
+
+    #[error("No worktree named, on branch or at '{0}'")]
+    WorktreeNotFound(String),
+
+    #[error("Worktree at '{}' is locked; unlock it with 'git worktree unlock'", .0.display())]
+    WorktreeLocked(PathBuf),
+
+    #[error("Worktree at '{}' has uncommitted changes (use --force to remove it anyway)", .0.display())]
+    WorktreeDirty(PathBuf),
+
+    #[error("The {0} hook failed")]
+    HookFailed(String),
+
+    #[error("Failed to sign: {0}")]
+    Signing(String),
+
+    #[error("Push rejected:{}", describe_rejections(.0))]
+    PushRejected(Vec<RejectedRef>),
+
+    #[error("Authentication failed for {url} ({})", describe_attempts(.tried))]
+    AuthenticationFailed { url: String, tried: Vec<AuthMethod> },
+
+    #[error("Could not verify the certificate of {url}; set http.sslCAInfo to your CA bundle")]
+    Certificate {
+        url: String,
+        #[source]
+        source: git2::Error,
+    },
+
+    #[error("{context}")]
+    Git {
+        context: String,
+        #[source]
+        source: git2::Error,
+    },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Other(#[from] git2::Error),
+}
+
+fn describe_attempts(tried: &[AuthMethod]) -> String {
+    if tried.is_empty() {
+        return "no credentials available; start ssh-agent, configure a git credential helper \
+                or set GWF_GIT_TOKEN"
+            .to_string();
+    }
+
+    let tried: Vec<String> = tried.iter().map(ToString::to_string).collect();
+    format!("tried: {}", tried.join(", "))
+}
+
+fn describe_rejections(rejected: &[RejectedRef]) -> String {
+    rejected
+        .iter()
+        .map(|rejection| format!("\n  {}: {}", rejection.name, rejection.reason))
+        .collect()
+}
+
+pub type Result<T> = std::result::Result<T, GitError>;
+
+/// Attaches a message to a libgit2 error, like `anyhow::Context` does.
+pub trait GitContext<T> {
+    fn git_context(self, context: impl Into<String>) -> Result<T>;
+}
+
+impl<T> GitContext<T> for std::result::Result<T, git2::Error> {
+    fn git_context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|source| GitError::Git {
+            context: context.into(),
+            source,
+        })
+    }
+}
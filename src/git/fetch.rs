@@ -0,0 +1,126 @@
+use git2::{FetchOptions, FetchPrune};
+use std::cell::RefCell;
+
+use super::auth::{self, Credentials};
+use super::error::{GitError, Result};
+use super::GitRepo;
+
+/// Progress of a fetch, as reported while it runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchProgress {
+    /// Objects received from the remote and deltas resolved so far.
+    Transfer(TransferProgress),
+    /// A line of the remote's own output, e.g. `Counting objects: 45%`.
+    Remote(String),
+}
+
+/// Receives the progress of a fetch. Implemented for closures, so a
+/// fetch can be watched with `&mut |progress: FetchProgress| ...`.
+pub trait FetchObserver {
+    fn observe(&mut self, progress: FetchProgress);
+}
+
+impl<F: FnMut(FetchProgress)> FetchObserver for F {
+    fn observe(&mut self, progress: FetchProgress) {
+        self(progress)
+    }
+}
+
+/// Counters of an ongoing fetch, like the ones `git fetch` prints.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub received_objects: usize,
+    pub total_objects: usize,
+    pub indexed_deltas: usize,
+    pub total_deltas: usize,
+    pub received_bytes: usize,
+}
+
+impl TransferProgress {
+    /// True once every object is received and only deltas are left to
+    /// resolve.
+    pub fn resolving_deltas(&self) -> bool {
+        self.total_deltas > 0 && self.received_objects == self.total_objects
+    }
+}
+
+impl From<git2::Progress<'_>> for TransferProgress {
+    fn from(progress: git2::Progress<'_>) -> Self {
+        Self {
+            received_objects: progress.received_objects(),
+            total_objects: progress.total_objects(),
+            indexed_deltas: progress.indexed_deltas(),
+            total_deltas: progress.total_deltas(),
+            received_bytes: progress.received_bytes(),
+        }
+    }
+}
+
+/// The last line of a chunk of remote output. Remotes redraw their
+/// progress with `\r`, so a chunk may hold several versions of one line.
+fn last_line(text: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(text)
+        .split(['\r', '\n'])
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .map(String::from)
+}
+
+impl GitRepo {
+    /// Fetches the configured refspecs of `remote`. With `prune`, remote
+    /// tracking branches that no longer exist on the remote are removed.
+    /// `progress` is told as objects arrive and the remote reports on its
+    /// side of the transfer.
+    pub fn fetch(&self, remote: &str, prune: bool, progress: &mut dyn FetchObserver) -> Result<()> {
+        let mut remote = self
+            .repo
+            .find_remote(remote)
+            .map_err(|_| GitError::RemoteNotFound(remote.to_string()))?;
+        let url = remote.url().unwrap_or_default().to_string();
+
+        let config = self.repo.config()?;
+        auth::configure_certificates(&config)?;
+        let mut credentials = Credentials::new(config);
+
+        // Both callbacks report to `progress`, so they share it.
+        let progress = RefCell::new(progress);
+        let result = {
+            let mut callbacks = credentials.callbacks();
+            callbacks.transfer_progress(|stats| {
+                progress
+                    .borrow_mut()
+                    .observe(FetchProgress::Transfer(stats.into()));
+                true
+            });
+            callbacks.sideband_progress(|text| {
+                if let Some(line) = last_line(text) {
+                    progress.borrow_mut().observe(FetchProgress::Remote(line));
+                }
+                true
+            });
+
+            let mut options = FetchOptions::new();
+            options.remote_callbacks(callbacks);
+            if prune {
+                options.prune(FetchPrune::On);
+            }
+            remote.fetch(&[] as &[&str], Some(&mut options), None)
+        };
+
+        result.map_err(|error| credentials.error(&url, "Failed to fetch from remote", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_line() {
+        assert_eq!(
+            last_line(b"Counting objects:  50% (1/2)\rCounting objects: 100% (2/2), done.\n"),
+            Some("Counting objects: 100% (2/2), done.".to_string())
+        );
+        assert_eq!(last_line(b"\r\n"), None);
+    }
+}
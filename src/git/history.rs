@@ -0,0 +1,198 @@
+use git2::{Commit, DescribeFormatOptions, DescribeOptions, ErrorCode, Oid, Sort};
+
+use super::error::{GitContext, GitError, Result};
+use super::GitRepo;
+
+/// A commit as shown in logs and reports.
+#[derive(Debug, Clone)]
+pub struct CommitInfo {
+    pub id: Oid,
+    pub short_id: String,
+    pub summary: String,
+    /// The full message, summary included.
+    pub message: String,
+    pub author_name: String,
+    pub author_email: String,
+    /// Commit time as a Unix timestamp.
+    pub time: i64,
+}
+
+impl CommitInfo {
+    pub(crate) fn from_commit(commit: &Commit) -> Self {
+        let author = commit.author();
+        Self {
+            id: commit.id(),
+            short_id: commit
+                .as_object()
+                .short_id()
+                .ok()
+                .and_then(|id| id.as_str().map(String::from))
+                .unwrap_or_else(|| commit.id().to_string()),
+            summary: String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default())
+                .into_owned(),
+            message: String::from_utf8_lossy(commit.message_bytes()).into_owned(),
+            author_name: String::from_utf8_lossy(author.name_bytes()).into_owned(),
+            author_email: String::from_utf8_lossy(author.email_bytes()).into_owned(),
+            time: commit.time().seconds(),
+        }
+    }
+}
+
+/// Which commits `GitRepo::log` returns, newest first.
+#[derive(Debug, Default, Clone)]
+pub struct LogOptions {
+    /// Only commits in this range, `A..B` like `git log A..B` with an empty
+    /// side meaning HEAD, or those reachable from a single revision.
+    /// Replaces `branches`, `remotes` and HEAD as starting points.
+    pub range: Option<String>,
+    /// Start from every local branch, like `git log --branches`. HEAD is
+    /// used when neither this nor `remotes` is set.
+    pub branches: bool,
+    /// Start from every remote-tracking branch, like `git log --remotes`.
+    pub remotes: bool,
+    /// Stop at commits older than this Unix timestamp.
+    pub since: Option<i64>,
+    /// Only commits whose author email matches exactly.
+    pub author_email: Option<String>,
+    pub no_merges: bool,
+    pub limit: Option<usize>,
+}
+
+/// A local branch and the time of its latest commit.
+#[derive(Debug, Clone)]
+pub struct BranchActivity {
+    pub name: String,
+    pub time: i64,
+}
+
+/// Splits `A..B` into the revision to exclude and the one to start from,
+/// with an empty side meaning HEAD.
+pub(crate) fn split_range<'a>(range: &'a str) -> (Option<&'a str>, &'a str) {
+    let or_head = |revision: &'a str| {
+        if revision.is_empty() {
+            "HEAD"
+        } else {
+            revision
+        }
+    };
+    match range.split_once("..") {
+        Some((hide, push)) => (Some(or_head(hide)), or_head(push)),
+        None => (None, range),
+    }
+}
+
+impl GitRepo {
+    /// Walks history like `git log`. An unborn HEAD has no history and
+    /// yields an empty list.
+    pub fn log(&self, options: &LogOptions) -> Result<Vec<CommitInfo>> {
+        let mut revwalk = self.repo.revwalk().git_context("Failed to walk history")?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+
+        if let Some(range) = &options.range {
+            let (hide, push) = split_range(range);
+            if let Some(hide) = hide {
+                revwalk.hide(self.find_commit(hide)?.id())?;
+            }
+            revwalk.push(self.find_commit(push)?.id())?;
+        } else {
+            if options.branches {
+                revwalk.push_glob("refs/heads")?;
+            }
+            if options.remotes {
+                revwalk.push_glob("refs/remotes")?;
+            }
+            if !options.branches && !options.remotes && revwalk.push_head().is_err() {
+                return Ok(Vec::new());
+            }
+        }
+
+        let mut commits = Vec::new();
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+
+            if options
+                .since
+                .is_some_and(|since| commit.time().seconds() < since)
+            {
+                break;
+            }
+            if options.no_merges && commit.parent_count() > 1 {
+                continue;
+            }
+            let info = CommitInfo::from_commit(&commit);
+            if let Some(email) = &options.author_email {
+                if !info.author_email.eq_ignore_ascii_case(email) {
+                    continue;
+                }
+            }
+
+            commits.push(info);
+            if options.limit.is_some_and(|limit| commits.len() >= limit) {
+                break;
+            }
+        }
+
+        Ok(commits)
+    }
+
+    /// Local branches ordered by their latest commit, most recent first.
+    pub fn recent_branches(&self, limit: usize) -> Result<Vec<BranchActivity>> {
+        let mut branches = Vec::new();
+        for name in self.list_branches(false)? {
+            let time = self
+                .find_commit(&format!("refs/heads/{}", name))?
+                .time()
+                .seconds();
+            branches.push(BranchActivity { name, time });
+        }
+
+        branches.sort_by_key(|branch| std::cmp::Reverse(branch.time));
+        branches.truncate(limit);
+        Ok(branches)
+    }
+
+    pub fn tags(&self) -> Result<Vec<String>> {
+        let names = self
+            .repo
+            .tag_names(None)
+            .git_context("Failed to list tags")?;
+
+        Ok(names.iter().flatten().map(String::from).collect())
+    }
+
+    /// The most recent tag reachable from HEAD, like `git describe --tags
+    /// --abbrev=0`.
+    pub fn latest_tag(&self) -> Result<Option<String>> {
+        let mut options = DescribeOptions::new();
+        options.describe_tags();
+
+        let describe = match self.repo.describe(&options) {
+            Ok(describe) => describe,
+            Err(error) if error.code() == ErrorCode::NotFound => return Ok(None),
+            Err(error) => return Err(error).git_context("Failed to describe HEAD"),
+        };
+        let name = describe.format(Some(DescribeFormatOptions::new().abbreviated_size(0)))?;
+
+        Ok(Some(name))
+    }
+
+    /// Creates an annotated tag on `target`, or on HEAD when `None`.
+    pub fn create_tag(&self, name: &str, message: &str, target: Option<&str>) -> Result<Oid> {
+        let target = self.find_commit(target.unwrap_or("HEAD"))?;
+        let tagger = self
+            .repo
+            .signature()
+            .git_context("Failed to determine the git user (set user.name and user.email)")?;
+
+        match self
+            .repo
+            .tag(name, target.as_object(), &tagger, message, false)
+        {
+            Ok(oid) => Ok(oid),
+            Err(error) if error.code() == ErrorCode::Exists => {
+                Err(GitError::TagExists(name.to_string()))
+            }
+            Err(error) => Err(error).git_context(format!("Failed to create tag '{}'", name)),
+        }
+    }
+}
@@ -0,0 +1,1068 @@
+use git2::{ObjectType, Oid};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use super::history::split_range;
+use super::worktree::worktree_name;
+use super::{
+    BisectMark, BisectStep, BranchActivity, CommitInfo, CommitOptions, FetchObserver, GitBackend,
+    GitError, HeadState, LogOptions, MergeOutcome, Operation, PushOptions, RejectedRef, RepoState,
+    ResetMode, Result, StashEntry, StatusSummary, WorktreeInfo,
+};
+
+/// A [`GitBackend`] that keeps a small repository in memory, so command logic
+/// can be tested without creating one on disk. Build the history with
+/// [`commit_on`](Self::commit_on) and friends, run the code under test, then
+/// inspect branches, stashes and commits.
+///
+/// There is no remote and no working tree: remote-tracking branches are set
+/// with [`set_remote_branch`](Self::set_remote_branch), `fetch` only checks
+/// that the remote exists, `push` moves the remote-tracking branches, and
+/// file changes are just the counts given to [`set_status`](Self::set_status).
+/// `workdir` and `bisect_run` fail with [`GitError::BareRepository`].
+/// Worktrees are only recorded; they have no files, so none are ever dirty
+/// or prunable.
+#[derive(Debug, Default)]
+pub struct MemoryRepo {
+    state: RefCell<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    commits: HashMap<Oid, MemoryCommit>,
+    branches: BTreeMap<String, Oid>,
+    remote_branches: BTreeMap<String, Oid>,
+    remotes: BTreeMap<String, String>,
+    tags: BTreeMap<String, Oid>,
+    config: HashMap<String, String>,
+    head: Head,
+    orig_head: Option<Oid>,
+    status: StatusSummary,
+    staged: Vec<String>,
+    stashes: Vec<MemoryStash>,
+    bisect: Option<Bisect>,
+    operation: Option<Operation>,
+    pushed: Vec<String>,
+    worktrees: Vec<WorktreeInfo>,
+    objects: u64,
+}
+
+#[derive(Debug, Clone)]
+struct MemoryCommit {
+    info: CommitInfo,
+    parents: Vec<Oid>,
+}
+
+/// A stash entry; `message` is the reflog message, such as
+/// `On main: wip`.
+#[derive(Debug)]
+struct MemoryStash {
+    oid: Oid,
+    message: String,
+    status: StatusSummary,
+}
+
+#[derive(Debug, Clone)]
+enum Head {
+    Branch(String),
+    Detached(Oid),
+}
+
+impl Default for Head {
+    fn default() -> Self {
+        Head::Branch("main".to_string())
+    }
+}
+
+#[derive(Debug)]
+struct Bisect {
+    start: Head,
+    bad: Option<Oid>,
+    good: Vec<Oid>,
+    skip: Vec<Oid>,
+}
+
+/// Commit times start here and advance a minute per commit, so history
+/// order and time order agree.
+const EPOCH: i64 = 1_700_000_000;
+
+impl MemoryRepo {
+    /// An empty repository whose HEAD points at an unborn `main`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a commit on top of `branch`, creating the branch if needed.
+    pub fn commit_on(&self, branch: &str, summary: &str) -> Oid {
+        let mut state = self.state.borrow_mut();
+        let parents = state.branches.get(branch).copied().into_iter().collect();
+        let oid = state.new_commit(summary, parents, None);
+        state.branches.insert(branch.to_string(), oid);
+        oid
+    }
+
+    /// Adds a merge of `other` into `branch`.
+    pub fn merge_on(&self, branch: &str, other: &str, summary: &str) -> Oid {
+        let mut state = self.state.borrow_mut();
+        let parents = [branch, other]
+            .iter()
+            .filter_map(|name| state.branches.get(*name).copied())
+            .collect();
+        let oid = state.new_commit(summary, parents, None);
+        state.branches.insert(branch.to_string(), oid);
+        oid
+    }
+
+    pub fn add_remote(&self, name: &str, url: &str) {
+        let mut state = self.state.borrow_mut();
+        state.remotes.insert(name.to_string(), url.to_string());
+    }
+
+    /// Points a remote-tracking branch such as `origin/main` at `oid`, as if
+    /// it had been fetched.
+    pub fn set_remote_branch(&self, name: &str, oid: Oid) {
+        let mut state = self.state.borrow_mut();
+        state.remote_branches.insert(name.to_string(), oid);
+    }
+
+    /// Sets the uncommitted changes reported by `status`.
+    pub fn set_status(&self, status: StatusSummary) {
+        self.state.borrow_mut().status = status;
+    }
+
+    /// Records `paths` as staged; the next commit clears them.
+    pub fn stage(&self, paths: &[&str]) {
+        let mut state = self.state.borrow_mut();
+        state.staged = paths.iter().map(|path| path.to_string()).collect();
+    }
+
+    pub fn set_config(&self, name: &str, value: &str) {
+        let mut state = self.state.borrow_mut();
+        state.config.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn branch_tip(&self, name: &str) -> Option<Oid> {
+        self.state.borrow().branches.get(name).copied()
+    }
+
+    pub fn parents(&self, oid: Oid) -> Vec<Oid> {
+        let state = self.state.borrow();
+        state
+            .commits
+            .get(&oid)
+            .map(|commit| commit.parents.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn summary(&self, oid: Oid) -> Option<String> {
+        let state = self.state.borrow();
+        state
+            .commits
+            .get(&oid)
+            .map(|commit| commit.info.summary.clone())
+    }
+
+    /// Pretends `operation` stopped halfway, as a conflicting `git rebase`
+    /// or `git cherry-pick` run outside gwf would leave it.
+    pub fn set_operation(&self, operation: Option<Operation>) {
+        self.state.borrow_mut().operation = operation;
+    }
+
+    pub fn stash_count(&self) -> usize {
+        self.state.borrow().stashes.len()
+    }
+
+    /// Every ref pushed so far, in order.
+    pub fn pushed(&self) -> Vec<String> {
+        self.state.borrow().pushed.clone()
+    }
+}
+
+impl State {
+    fn next_object(&mut self, kind: ObjectType) -> Oid {
+        self.objects += 1;
+        Oid::hash_object(kind, format!("memory {}", self.objects).as_bytes())
+            .expect("hashing does not need a repository")
+    }
+
+    fn new_commit(&mut self, message: &str, parents: Vec<Oid>, author: Option<&CommitInfo>) -> Oid {
+        let id = self.next_object(ObjectType::Commit);
+        let hex = id.to_string();
+        let (author_name, author_email) = match author {
+            Some(author) => (author.author_name.clone(), author.author_email.clone()),
+            None => (
+                self.config_or("user.name", "Test User"),
+                self.config_or("user.email", "test@example.com"),
+            ),
+        };
+
+        let info = CommitInfo {
+            id,
+            short_id: hex[..7].to_string(),
+            summary: message.lines().next().unwrap_or_default().to_string(),
+            message: message.to_string(),
+            author_name,
+            author_email,
+            time: EPOCH + 60 * self.commits.len() as i64,
+        };
+        self.commits.insert(id, MemoryCommit { info, parents });
+        id
+    }
+
+    fn config_or(&self, name: &str, default: &str) -> String {
+        self.config
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| default.to_string())
+    }
+
+    fn head_oid(&self) -> Option<Oid> {
+        match &self.head {
+            Head::Branch(name) => self.branches.get(name).copied(),
+            Head::Detached(oid) => Some(*oid),
+        }
+    }
+
+    fn current_branch(&self) -> Result<String> {
+        match &self.head {
+            Head::Branch(name) => Ok(name.clone()),
+            Head::Detached(_) => Err(GitError::DetachedHead),
+        }
+    }
+
+    /// Moves the current branch, or a detached HEAD, to `oid`.
+    fn set_head_target(&mut self, oid: Oid) {
+        match &self.head {
+            Head::Branch(name) => {
+                self.branches.insert(name.clone(), oid);
+            }
+            Head::Detached(_) => self.head = Head::Detached(oid),
+        }
+    }
+
+    /// Resolves branch and tag names, `HEAD`, `ORIG_HEAD`, (abbreviated)
+    /// commit ids and a `~N` suffix.
+    fn resolve(&self, revision: &str) -> Result<Oid> {
+        let not_found = || GitError::RevisionNotFound(revision.to_string());
+
+        let (base, generations) = match revision.split_once('~') {
+            Some((base, "")) => (base, 1),
+            Some((base, count)) => (base, count.parse().map_err(|_| not_found())?),
+            None => (revision, 0),
+        };
+
+        let mut oid = match base {
+            "HEAD" => self.head_oid(),
+            "ORIG_HEAD" => self.orig_head,
+            _ => {
+                let name = base
+                    .strip_prefix("refs/heads/")
+                    .or_else(|| base.strip_prefix("refs/remotes/"))
+                    .or_else(|| base.strip_prefix("refs/tags/"))
+                    .unwrap_or(base);
+                self.branches
+                    .get(name)
+                    .or_else(|| self.remote_branches.get(name))
+                    .or_else(|| self.tags.get(name))
+                    .copied()
+                    .or_else(|| self.find_by_prefix(name))
+            }
+        }
+        .ok_or_else(not_found)?;
+
+        for _ in 0..generations {
+            oid = *self.commits[&oid].parents.first().ok_or_else(not_found)?;
+        }
+        Ok(oid)
+    }
+
+    fn find_by_prefix(&self, prefix: &str) -> Option<Oid> {
+        if prefix.len() < 4 {
+            return None;
+        }
+        let mut matches = self
+            .commits
+            .keys()
+            .filter(|oid| oid.to_string().starts_with(prefix));
+        let oid = matches.next()?;
+        matches.next().is_none().then_some(*oid)
+    }
+
+    fn ancestors(&self, oid: Oid) -> HashSet<Oid> {
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([oid]);
+        while let Some(oid) = queue.pop_front() {
+            if seen.insert(oid) {
+                queue.extend(self.commits[&oid].parents.iter().copied());
+            }
+        }
+        seen
+    }
+
+    fn info(&self, oid: Oid) -> CommitInfo {
+        self.commits[&oid].info.clone()
+    }
+
+    fn bisect_next(&mut self) -> Result<BisectStep> {
+        let bisect = self.bisect.as_ref().ok_or(GitError::NotBisecting)?;
+        let Some(bad) = bisect.bad else {
+            return Ok(BisectStep::NeedsMarks);
+        };
+        if bisect.good.is_empty() {
+            return Ok(BisectStep::NeedsMarks);
+        }
+
+        let mut excluded = HashSet::new();
+        for good in &bisect.good {
+            excluded.extend(self.ancestors(*good));
+        }
+        let mut candidates: Vec<Oid> = self
+            .ancestors(bad)
+            .into_iter()
+            .filter(|oid| !excluded.contains(oid))
+            .collect();
+        candidates.sort_by_key(|oid| std::cmp::Reverse(self.commits[oid].info.time));
+
+        let testable: Vec<(usize, Oid)> = candidates
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, oid)| *oid != bad && !bisect.skip.contains(oid))
+            .collect();
+
+        if testable.is_empty() {
+            if candidates.len() == 1 {
+                return Ok(BisectStep::Found(self.info(bad)));
+            }
+            return Err(GitError::BisectInconclusive(
+                candidates.iter().map(|oid| oid.to_string()).collect(),
+            ));
+        }
+
+        let middle = candidates.len() / 2;
+        let (_, next) = testable
+            .iter()
+            .min_by_key(|(position, _)| position.abs_diff(middle))
+            .copied()
+            .expect("testable is not empty");
+
+        self.head = Head::Detached(next);
+        Ok(BisectStep::Testing {
+            commit: self.info(next),
+            remaining: candidates.len() - 1,
+        })
+    }
+}
+
+impl GitBackend for MemoryRepo {
+    fn workdir(&self) -> Result<PathBuf> {
+        Err(GitError::BareRepository)
+    }
+
+    fn current_branch(&self) -> Result<String> {
+        self.state.borrow().current_branch()
+    }
+
+    fn state(&self) -> Result<RepoState> {
+        let state = self.state.borrow();
+        let head = match &state.head {
+            Head::Branch(name) if state.branches.contains_key(name) => {
+                HeadState::Branch(name.clone())
+            }
+            Head::Branch(name) => HeadState::Unborn(name.clone()),
+            Head::Detached(oid) => HeadState::Detached(*oid),
+        };
+        let operation = state
+            .operation
+            .clone()
+            .or_else(|| state.bisect.as_ref().map(|_| Operation::Bisect));
+
+        Ok(RepoState { head, operation })
+    }
+
+    fn list_remotes(&self) -> Result<Vec<String>> {
+        Ok(self.state.borrow().remotes.keys().cloned().collect())
+    }
+
+    fn remote_urls(&self) -> Result<Vec<String>> {
+        let state = self.state.borrow();
+        let mut remotes: Vec<(&String, &String)> = state.remotes.iter().collect();
+        remotes.sort_by_key(|(name, _)| *name != "origin");
+
+        Ok(remotes.into_iter().map(|(_, url)| url.clone()).collect())
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<String> {
+        let state = self.state.borrow();
+        state
+            .remotes
+            .get(remote)
+            .cloned()
+            .ok_or_else(|| GitError::RemoteNotFound(remote.to_string()))
+    }
+
+    fn remote_default_branch(&self, _remote: &str) -> Option<String> {
+        None
+    }
+
+    fn config_bool(&self, name: &str) -> Option<bool> {
+        let state = self.state.borrow();
+        match state.config.get(name)?.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Some(true),
+            "false" | "no" | "off" | "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    fn config_string(&self, name: &str) -> Option<String> {
+        self.state.borrow().config.get(name).cloned()
+    }
+
+    fn create_branch(&self, name: &str, from: Option<&str>) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let target = match from {
+            Some(from) => *state
+                .branches
+                .get(from)
+                .ok_or_else(|| GitError::BranchNotFound(from.to_string()))?,
+            None => state.head_oid().ok_or(GitError::DetachedHead)?,
+        };
+
+        if state.branches.contains_key(name) {
+            return Err(GitError::Git {
+                context: format!("Failed to create branch '{}'", name),
+                source: git2::Error::from_str("a branch with that name already exists"),
+            });
+        }
+        state.branches.insert(name.to_string(), target);
+        Ok(())
+    }
+
+    fn checkout(&self, branch_name: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        if !state.branches.contains_key(branch_name) {
+            return Err(GitError::BranchNotFound(branch_name.to_string()));
+        }
+        if state
+            .worktrees
+            .iter()
+            .any(|worktree| worktree.branch.as_deref() == Some(branch_name))
+        {
+            return Err(GitError::Git {
+                context: "Failed to update HEAD".to_string(),
+                source: git2::Error::from_str("the branch is checked out in a linked worktree"),
+            });
+        }
+        state.head = Head::Branch(branch_name.to_string());
+        Ok(())
+    }
+
+    fn list_branches(&self, include_remote: bool) -> Result<Vec<String>> {
+        let state = self.state.borrow();
+        let branches = if include_remote {
+            &state.remote_branches
+        } else {
+            &state.branches
+        };
+        Ok(branches.keys().cloned().collect())
+    }
+
+    fn is_branch_merged(&self, branch_name: &str, into: &str) -> Result<bool> {
+        let state = self.state.borrow();
+        let target = |name: &str| {
+            state
+                .branches
+                .get(name)
+                .copied()
+                .ok_or_else(|| GitError::BranchNotFound(name.to_string()))
+        };
+        let branch = target(branch_name)?;
+        let into = target(into)?;
+
+        Ok(state.ancestors(into).contains(&branch))
+    }
+
+    fn delete_branch(&self, branch_name: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        if !state.branches.contains_key(branch_name) {
+            return Err(GitError::BranchNotFound(branch_name.to_string()));
+        }
+        if matches!(&state.head, Head::Branch(name) if name == branch_name) {
+            return Err(GitError::Git {
+                context: format!("Failed to delete branch '{}'", branch_name),
+                source: git2::Error::from_str("cannot delete the current branch"),
+            });
+        }
+        if state
+            .worktrees
+            .iter()
+            .any(|worktree| worktree.branch.as_deref() == Some(branch_name))
+        {
+            return Err(GitError::Git {
+                context: format!("Failed to delete branch '{}'", branch_name),
+                source: git2::Error::from_str("the branch is checked out in a linked worktree"),
+            });
+        }
+        state.branches.remove(branch_name);
+        Ok(())
+    }
+
+    fn add_worktree(&self, branch: &str, path: &Path) -> Result<WorktreeInfo> {
+        let mut state = self.state.borrow_mut();
+        if !state.branches.contains_key(branch) {
+            return Err(GitError::BranchNotFound(branch.to_string()));
+        }
+        if state.worktrees.iter().any(|worktree| worktree.path == path) {
+            return Err(GitError::WorktreePathExists(path.to_path_buf()));
+        }
+
+        let worktree = WorktreeInfo {
+            name: worktree_name(branch),
+            path: path.to_path_buf(),
+            branch: Some(branch.to_string()),
+            locked: false,
+            prunable: false,
+        };
+        state.worktrees.push(worktree.clone());
+        Ok(worktree)
+    }
+
+    fn worktrees(&self) -> Result<Vec<WorktreeInfo>> {
+        Ok(self.state.borrow().worktrees.clone())
+    }
+
+    fn remove_worktree(&self, target: &str, _force: bool) -> Result<WorktreeInfo> {
+        let mut state = self.state.borrow_mut();
+        let index = state
+            .worktrees
+            .iter()
+            .position(|worktree| worktree.matches(target))
+            .ok_or_else(|| GitError::WorktreeNotFound(target.to_string()))?;
+        Ok(state.worktrees.remove(index))
+    }
+
+    fn prune_worktrees(&self) -> Result<Vec<WorktreeInfo>> {
+        Ok(Vec::new())
+    }
+
+    fn has_uncommitted_changes(&self) -> Result<bool> {
+        Ok(!self.state.borrow().status.is_clean())
+    }
+
+    fn status(&self) -> Result<StatusSummary> {
+        Ok(self.state.borrow().status)
+    }
+
+    fn staged_files(&self) -> Result<Vec<String>> {
+        Ok(self.state.borrow().staged.clone())
+    }
+
+    /// A patch header per staged path; the memory repository keeps no file
+    /// contents.
+    fn staged_diff(&self, _amend: bool) -> Result<String> {
+        let state = self.state.borrow();
+        Ok(state
+            .staged
+            .iter()
+            .map(|path| format!("diff --git a/{0} b/{0}\n", path))
+            .collect())
+    }
+
+    fn ahead_behind(&self, local: &str, upstream: &str) -> Result<Option<(usize, usize)>> {
+        let state = self.state.borrow();
+        let Ok(upstream) = state.resolve(upstream) else {
+            return Ok(None);
+        };
+        let local = state.ancestors(state.resolve(local)?);
+        let upstream = state.ancestors(upstream);
+
+        Ok(Some((
+            local.difference(&upstream).count(),
+            upstream.difference(&local).count(),
+        )))
+    }
+
+    fn fetch(&self, remote: &str, _prune: bool, _progress: &mut dyn FetchObserver) -> Result<()> {
+        if !self.state.borrow().remotes.contains_key(remote) {
+            return Err(GitError::RemoteNotFound(remote.to_string()));
+        }
+        Ok(())
+    }
+
+    fn push(&self, remote: &str, refs: &[String], options: &PushOptions) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        if !state.remotes.contains_key(remote) {
+            return Err(GitError::RemoteNotFound(remote.to_string()));
+        }
+
+        let mut rejected = Vec::new();
+        for name in refs {
+            let oid = state.resolve(name)?;
+            let Some(branch) = name.strip_prefix("refs/heads/") else {
+                continue;
+            };
+            let tracking = format!("{}/{}", remote, branch);
+            let fast_forward = state
+                .remote_branches
+                .get(&tracking)
+                .is_none_or(|old| state.ancestors(oid).contains(old));
+            if !fast_forward && !options.force_with_lease {
+                rejected.push(RejectedRef {
+                    name: name.clone(),
+                    reason: "non-fast-forward; sync with the remote first".to_string(),
+                });
+            }
+        }
+        if !rejected.is_empty() {
+            return Err(GitError::PushRejected(rejected));
+        }
+
+        for name in refs {
+            state.pushed.push(name.clone());
+            let Some(branch) = name.strip_prefix("refs/heads/") else {
+                continue;
+            };
+            let oid = state.branches[branch];
+            state
+                .remote_branches
+                .insert(format!("{}/{}", remote, branch), oid);
+            if options.set_upstream {
+                let config = &mut state.config;
+                config.insert(format!("branch.{}.remote", branch), remote.to_string());
+                config.insert(format!("branch.{}.merge", branch), name.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn upstream(&self, branch: &str) -> Option<String> {
+        let state = self.state.borrow();
+        let remote = state.config.get(&format!("branch.{}.remote", branch))?;
+        let merge = state.config.get(&format!("branch.{}.merge", branch))?;
+        Some(format!(
+            "{}/{}",
+            remote,
+            merge.strip_prefix("refs/heads/").unwrap_or(merge)
+        ))
+    }
+
+    fn merge(&self, upstream: &str) -> Result<MergeOutcome> {
+        let mut state = self.state.borrow_mut();
+        let target = state.resolve(upstream)?;
+        let branch = state.current_branch()?;
+        let Some(head) = state.head_oid() else {
+            state.set_head_target(target);
+            return Ok(MergeOutcome::FastForward(target));
+        };
+
+        if state.ancestors(head).contains(&target) {
+            return Ok(MergeOutcome::UpToDate);
+        }
+        state.orig_head = Some(head);
+        if state.ancestors(target).contains(&head) {
+            state.set_head_target(target);
+            return Ok(MergeOutcome::FastForward(target));
+        }
+
+        let kind = if state.remote_branches.contains_key(upstream) {
+            "remote-tracking branch"
+        } else {
+            "branch"
+        };
+        let message = format!("Merge {} '{}' into {}", kind, upstream, branch);
+        let merge = state.new_commit(&message, vec![head, target], None);
+        state.set_head_target(merge);
+        Ok(MergeOutcome::Merged(merge))
+    }
+
+    fn fast_forward(&self, branch: &str, upstream: &str) -> Result<MergeOutcome> {
+        let mut state = self.state.borrow_mut();
+        let target = state.resolve(upstream)?;
+        let local = state
+            .branches
+            .get(branch)
+            .copied()
+            .ok_or_else(|| GitError::BranchNotFound(branch.to_string()))?;
+
+        if state.ancestors(local).contains(&target) {
+            return Ok(MergeOutcome::UpToDate);
+        }
+        if !state.ancestors(target).contains(&local) {
+            return Err(GitError::Diverged(branch.to_string()));
+        }
+        if state.current_branch().ok().as_deref() == Some(branch) {
+            state.orig_head = Some(local);
+        }
+        state.branches.insert(branch.to_string(), target);
+        Ok(MergeOutcome::FastForward(target))
+    }
+
+    fn rebase(&self, upstream: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let target = state.resolve(upstream)?;
+        let head = state.resolve("HEAD")?;
+
+        let upstream_history = state.ancestors(target);
+        if upstream_history.contains(&head) {
+            state.set_head_target(target);
+            return Ok(());
+        }
+
+        let mut replay = Vec::new();
+        let mut next = Some(head);
+        while let Some(oid) = next.filter(|oid| !upstream_history.contains(oid)) {
+            replay.push(oid);
+            next = state.commits[&oid].parents.first().copied();
+        }
+
+        let mut tip = target;
+        for oid in replay.into_iter().rev() {
+            let original = state.commits[&oid].clone();
+            if original.parents.len() > 1 {
+                continue;
+            }
+            tip = state.new_commit(&original.info.summary, vec![tip], Some(&original.info));
+        }
+        state.set_head_target(tip);
+        Ok(())
+    }
+
+    fn abort_merge(&self) -> Result<()> {
+        // Merges here either complete or fail before changing anything.
+        Err(GitError::NoMergeInProgress)
+    }
+
+    fn reset(&self, revision: &str, mode: ResetMode) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let target = state.resolve(revision)?;
+
+        state.orig_head = state.head_oid();
+        state.set_head_target(target);
+        if mode == ResetMode::Hard {
+            state.status = StatusSummary::default();
+        }
+        Ok(())
+    }
+
+    /// Takes every change `status` reports, untracked files included, like
+    /// `GitRepo::stash_save`.
+    fn stash_save(&mut self, message: &str) -> Result<Option<Oid>> {
+        let state = self.state.get_mut();
+        if state.status.is_clean() {
+            return Ok(None);
+        }
+
+        let branch = state
+            .current_branch()
+            .unwrap_or_else(|_| "(no branch)".to_string());
+        let oid = state.next_object(ObjectType::Commit);
+        state.stashes.push(MemoryStash {
+            oid,
+            message: format!("On {}: {}", branch, message),
+            status: std::mem::take(&mut state.status),
+        });
+        Ok(Some(oid))
+    }
+
+    fn stash_pop(&mut self, stash: Oid) -> Result<()> {
+        let state = self.state.get_mut();
+        let position = state
+            .stashes
+            .iter()
+            .position(|entry| entry.oid == stash)
+            .ok_or_else(|| GitError::RevisionNotFound(stash.to_string()))?;
+
+        state.status = state.stashes.remove(position).status;
+        Ok(())
+    }
+
+    fn stashes(&mut self) -> Result<Vec<StashEntry>> {
+        let state = self.state.get_mut();
+        Ok(state
+            .stashes
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(index, entry)| StashEntry::new(index, entry.oid, &entry.message))
+            .collect())
+    }
+
+    fn commit(&self, message: &str, options: &CommitOptions) -> Result<Oid> {
+        let mut state = self.state.borrow_mut();
+        if message.trim().is_empty() {
+            return Err(GitError::EmptyMessage);
+        }
+
+        let head = state.head_oid();
+        let oid = if options.amend {
+            let head = head.ok_or_else(|| GitError::RevisionNotFound("HEAD".to_string()))?;
+            let original = state.commits[&head].clone();
+            state.new_commit(message, original.parents, Some(&original.info))
+        } else {
+            if state.status.is_clean() {
+                return Err(GitError::NothingToCommit);
+            }
+            state.new_commit(message, head.into_iter().collect(), None)
+        };
+
+        state.set_head_target(oid);
+        state.status = StatusSummary::default();
+        state.staged.clear();
+        Ok(oid)
+    }
+
+    fn log(&self, options: &LogOptions) -> Result<Vec<CommitInfo>> {
+        let state = self.state.borrow();
+        let mut starts: Vec<Oid> = Vec::new();
+        let mut hidden = HashSet::new();
+        if let Some(range) = &options.range {
+            let (hide, push) = split_range(range);
+            if let Some(hide) = hide {
+                hidden = state.ancestors(state.resolve(hide)?);
+            }
+            starts.push(state.resolve(push)?);
+        } else {
+            if options.branches {
+                starts.extend(state.branches.values());
+            }
+            if options.remotes {
+                starts.extend(state.remote_branches.values());
+            }
+            if !options.branches && !options.remotes {
+                starts.extend(state.head_oid());
+            }
+        }
+
+        let mut reachable = HashSet::new();
+        for oid in starts {
+            reachable.extend(state.ancestors(oid));
+        }
+        reachable.retain(|oid| !hidden.contains(oid));
+
+        let mut commits: Vec<&MemoryCommit> = reachable
+            .iter()
+            .map(|oid| &state.commits[oid])
+            .filter(|commit| options.since.is_none_or(|since| commit.info.time >= since))
+            .filter(|commit| !options.no_merges || commit.parents.len() < 2)
+            .filter(|commit| {
+                options
+                    .author_email
+                    .as_ref()
+                    .is_none_or(|email| commit.info.author_email.eq_ignore_ascii_case(email))
+            })
+            .collect();
+        commits.sort_by_key(|commit| std::cmp::Reverse(commit.info.time));
+        if let Some(limit) = options.limit {
+            commits.truncate(limit);
+        }
+
+        Ok(commits
+            .into_iter()
+            .map(|commit| commit.info.clone())
+            .collect())
+    }
+
+    fn recent_branches(&self, limit: usize) -> Result<Vec<BranchActivity>> {
+        let state = self.state.borrow();
+        let mut branches: Vec<BranchActivity> = state
+            .branches
+            .iter()
+            .map(|(name, oid)| BranchActivity {
+                name: name.clone(),
+                time: state.commits[oid].info.time,
+            })
+            .collect();
+
+        branches.sort_by_key(|branch| std::cmp::Reverse(branch.time));
+        branches.truncate(limit);
+        Ok(branches)
+    }
+
+    fn tags(&self) -> Result<Vec<String>> {
+        Ok(self.state.borrow().tags.keys().cloned().collect())
+    }
+
+    fn latest_tag(&self) -> Result<Option<String>> {
+        let state = self.state.borrow();
+        let Some(head) = state.head_oid() else {
+            return Ok(None);
+        };
+
+        // Breadth-first from HEAD, so the first tagged commit is the nearest.
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([head]);
+        while let Some(oid) = queue.pop_front() {
+            if !seen.insert(oid) {
+                continue;
+            }
+            if let Some((name, _)) = state.tags.iter().find(|(_, target)| **target == oid) {
+                return Ok(Some(name.clone()));
+            }
+            queue.extend(state.commits[&oid].parents.iter().copied());
+        }
+        Ok(None)
+    }
+
+    fn create_tag(&self, name: &str, _message: &str, target: Option<&str>) -> Result<Oid> {
+        let mut state = self.state.borrow_mut();
+        if state.tags.contains_key(name) {
+            return Err(GitError::TagExists(name.to_string()));
+        }
+
+        let target = state.resolve(target.unwrap_or("HEAD"))?;
+        state.tags.insert(name.to_string(), target);
+        Ok(state.next_object(ObjectType::Tag))
+    }
+
+    fn is_bisecting(&self) -> bool {
+        self.state.borrow().bisect.is_some()
+    }
+
+    fn bisect_start(&self, bad: &str, good: &[String]) -> Result<BisectStep> {
+        let mut state = self.state.borrow_mut();
+        let bad = state.resolve(bad)?;
+        let good = good
+            .iter()
+            .map(|revision| state.resolve(revision))
+            .collect::<Result<Vec<Oid>>>()?;
+
+        let start = match state.bisect.take() {
+            Some(bisect) => bisect.start,
+            None => state.head.clone(),
+        };
+        state.bisect = Some(Bisect {
+            start,
+            bad: Some(bad),
+            good,
+            skip: Vec::new(),
+        });
+        state.bisect_next()
+    }
+
+    fn bisect_mark(&self, mark: BisectMark, revision: Option<&str>) -> Result<BisectStep> {
+        let mut state = self.state.borrow_mut();
+        let oid = state.resolve(revision.unwrap_or("HEAD"))?;
+        let bisect = state.bisect.as_mut().ok_or(GitError::NotBisecting)?;
+
+        match mark {
+            BisectMark::Good => bisect.good.push(oid),
+            BisectMark::Bad => bisect.bad = Some(oid),
+            BisectMark::Skip => bisect.skip.push(oid),
+        }
+        state.bisect_next()
+    }
+
+    fn bisect_reset(&self) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let bisect = state.bisect.take().ok_or(GitError::NotBisecting)?;
+        state.head = bisect.start;
+        Ok(())
+    }
+
+    fn bisect_run(&self, _command: &Path) -> Result<CommitInfo> {
+        Err(GitError::BareRepository)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebase_replays_local_commits() {
+        let repo = MemoryRepo::new();
+        let base = repo.commit_on("main", "Initial commit");
+        repo.create_branch("upstream", Some("main")).unwrap();
+        repo.create_branch("feature", Some("main")).unwrap();
+        let upstream = repo.commit_on("upstream", "Upstream change");
+        repo.checkout("feature").unwrap();
+        repo.commit_on("feature", "Local change");
+        repo.set_remote_branch("origin/feature", upstream);
+
+        assert_eq!(
+            repo.ahead_behind("feature", "origin/feature").unwrap(),
+            Some((1, 1))
+        );
+        repo.rebase("origin/feature").unwrap();
+
+        let tip = repo.branch_tip("feature").unwrap();
+        assert_eq!(repo.parents(tip), vec![upstream]);
+        assert_eq!(repo.summary(tip).as_deref(), Some("Local change"));
+        assert!(repo.is_branch_merged("main", "feature").unwrap());
+        assert_eq!(repo.parents(upstream), vec![base]);
+    }
+
+    #[test]
+    fn test_merge_stash_and_tags() {
+        let mut repo = MemoryRepo::new();
+        let first = repo.commit_on("main", "First");
+        repo.create_tag("v1.0.0", "Release", None).unwrap();
+        repo.commit_on("other", "Other");
+        repo.create_branch("topic", Some("main")).unwrap();
+        repo.commit_on("main", "Second");
+
+        repo.set_status(StatusSummary {
+            modified: 2,
+            ..Default::default()
+        });
+        let stash = repo.stash_save("wip").unwrap().unwrap();
+        assert!(!repo.has_uncommitted_changes().unwrap());
+        assert_eq!(repo.stash_save("again").unwrap(), None);
+
+        // New, untracked files are stashed too.
+        repo.set_status(StatusSummary {
+            added: 1,
+            ..Default::default()
+        });
+        let untracked = repo.stash_save("untracked").unwrap().unwrap();
+        assert!(!repo.has_uncommitted_changes().unwrap());
+        repo.stash_pop(untracked).unwrap();
+        assert_eq!(repo.status().unwrap().added, 1);
+        repo.set_status(StatusSummary::default());
+
+        repo.merge("topic").unwrap();
+        repo.merge("other").unwrap();
+        let tip = repo.branch_tip("main").unwrap();
+        assert_eq!(repo.parents(tip).len(), 2);
+        assert_eq!(
+            repo.summary(tip).as_deref(),
+            Some("Merge branch 'other' into main")
+        );
+
+        repo.stash_pop(stash).unwrap();
+        assert_eq!(repo.status().unwrap().modified, 2);
+        assert_eq!(repo.stash_count(), 0);
+        assert_eq!(repo.latest_tag().unwrap().as_deref(), Some("v1.0.0"));
+        assert_eq!(repo.log(&LogOptions::default()).unwrap().len(), 4);
+        assert_ne!(first, tip);
+    }
+
+    #[test]
+    fn test_push_tracks_upstream() {
+        let repo = MemoryRepo::new();
+        repo.add_remote("origin", "git@example.com:acme/api.git");
+        let base = repo.commit_on("main", "Initial commit");
+        repo.commit_on("main", "Local change");
+        let refs = vec!["refs/heads/main".to_string()];
+
+        repo.set_remote_branch("origin/main", repo.commit_on("other", "Remote change"));
+        assert!(matches!(
+            repo.push("origin", &refs, &PushOptions::default()),
+            Err(GitError::PushRejected(_))
+        ));
+
+        repo.set_remote_branch("origin/main", base);
+        let options = PushOptions {
+            set_upstream: true,
+            ..Default::default()
+        };
+        repo.push("origin", &refs, &options).unwrap();
+        assert_eq!(repo.pushed(), refs);
+        assert_eq!(repo.upstream("main").as_deref(), Some("origin/main"));
+        assert_eq!(
+            repo.ahead_behind("main", "origin/main").unwrap(),
+            Some((0, 0))
+        );
+    }
+}
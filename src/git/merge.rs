@@ -0,0 +1,186 @@
+use git2::build::CheckoutBuilder;
+use git2::{AnnotatedCommit, Oid, RepositoryState};
+
+use super::error::{GitContext, GitError, Result};
+use super::{conflicts_in, FetchProgress, GitRepo, ResetMode};
+
+/// What `GitRepo::merge` or `GitRepo::pull` did to the current branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// The branch already contained every upstream commit.
+    UpToDate,
+    /// The branch was moved forward to the upstream commit.
+    FastForward(Oid),
+    /// The histories had diverged; this merge commit joins them.
+    Merged(Oid),
+}
+
+impl GitRepo {
+    /// Merges `upstream` into the current branch like `git merge --no-edit`:
+    /// fast-forwards when possible and otherwise commits a three-way merge.
+    /// The merge is computed before the working tree is touched, so on a
+    /// conflict nothing changes and the conflicting paths are returned.
+    pub fn merge(&self, upstream: &str) -> Result<MergeOutcome> {
+        let commit = self.find_commit(upstream)?;
+        let annotated = self
+            .repo
+            .find_annotated_commit(commit.id())
+            .git_context("Failed to prepare merge")?;
+
+        let kind = if self
+            .repo
+            .find_reference(&format!("refs/remotes/{}", upstream))
+            .is_ok()
+        {
+            "remote-tracking branch"
+        } else {
+            "branch"
+        };
+        let into = self.current_branch()?;
+        let message = format!("Merge {} '{}' into {}", kind, upstream, into);
+
+        self.merge_commit(upstream, &annotated, &message)
+    }
+
+    /// Fetches `branch` from `remote` and merges it into the current branch,
+    /// like `git pull --no-rebase`.
+    pub fn pull(&self, remote: &str, branch: &str) -> Result<MergeOutcome> {
+        self.fetch(remote, false, &mut |_: FetchProgress| {})?;
+
+        let upstream = format!("{}/{}", remote, branch);
+        let reference = self
+            .repo
+            .find_reference(&format!("refs/remotes/{}", upstream))
+            .map_err(|_| GitError::BranchNotFound(upstream.clone()))?;
+        let annotated = self
+            .repo
+            .reference_to_annotated_commit(&reference)
+            .git_context("Failed to prepare merge")?;
+
+        let url = self
+            .repo
+            .find_remote(remote)
+            .ok()
+            .and_then(|remote| remote.url().map(String::from))
+            .unwrap_or_else(|| remote.to_string());
+        let message = format!("Merge branch '{}' of {}", branch, url);
+
+        self.merge_commit(&upstream, &annotated, &message)
+    }
+
+    /// Moves `branch` forward to `upstream`, like `git fetch . upstream:branch`.
+    /// The branch does not need to be checked out; if it is, the working tree
+    /// is updated too. Fails with [`GitError::Diverged`] when `branch` has
+    /// commits that `upstream` lacks.
+    pub fn fast_forward(&self, branch: &str, upstream: &str) -> Result<MergeOutcome> {
+        let local = self.branch_target(branch)?;
+        let target = self.find_commit(upstream)?;
+
+        if local == target.id() || self.repo.graph_descendant_of(local, target.id())? {
+            return Ok(MergeOutcome::UpToDate);
+        }
+        if !self.repo.graph_descendant_of(target.id(), local)? {
+            return Err(GitError::Diverged(branch.to_string()));
+        }
+
+        let message = format!("merge {}: Fast-forward", upstream);
+        if self.current_branch().ok().as_deref() == Some(branch) {
+            self.checkout_merged(target.tree_id())?;
+            self.save_orig_head("merge")?;
+            self.update_head(target.id(), &message)?;
+        } else {
+            self.repo
+                .reference(
+                    &format!("refs/heads/{}", branch),
+                    target.id(),
+                    true,
+                    &message,
+                )
+                .git_context(format!("Failed to update branch '{}'", branch))?;
+        }
+        Ok(MergeOutcome::FastForward(target.id()))
+    }
+
+    /// Abandons an in-progress merge and restores the pre-merge state.
+    pub fn abort_merge(&self) -> Result<()> {
+        if self.repo.state() != RepositoryState::Merge {
+            return Err(GitError::NoMergeInProgress);
+        }
+
+        self.reset("HEAD", ResetMode::Hard)?;
+        self.repo
+            .cleanup_state()
+            .git_context("Failed to clean up merge state")
+    }
+
+    fn merge_commit(
+        &self,
+        name: &str,
+        upstream: &AnnotatedCommit,
+        message: &str,
+    ) -> Result<MergeOutcome> {
+        let (analysis, preference) = self
+            .repo
+            .merge_analysis(&[upstream])
+            .git_context("Failed to analyze merge")?;
+
+        if analysis.is_up_to_date() {
+            return Ok(MergeOutcome::UpToDate);
+        }
+        if analysis.is_unborn() || (analysis.is_fast_forward() && !preference.is_no_fast_forward())
+        {
+            let target = self.repo.find_commit(upstream.id())?;
+            self.checkout_merged(target.tree_id())?;
+            self.save_orig_head("merge")?;
+            self.update_head(target.id(), &format!("merge {}: Fast-forward", name))?;
+            return Ok(MergeOutcome::FastForward(target.id()));
+        }
+        if preference.is_fastforward_only() {
+            return Err(GitError::Diverged(self.current_branch()?));
+        }
+
+        let ours = self.find_commit("HEAD")?;
+        let theirs = self.repo.find_commit(upstream.id())?;
+        let mut index = self
+            .repo
+            .merge_commits(&ours, &theirs, None)
+            .git_context("Failed to merge")?;
+        if index.has_conflicts() {
+            return Err(GitError::Conflicts(conflicts_in(&index)?));
+        }
+
+        let tree_id = index
+            .write_tree_to(&self.repo)
+            .git_context("Failed to write merge tree")?;
+        let tree = self.repo.find_tree(tree_id)?;
+        let signature = self
+            .repo
+            .signature()
+            .git_context("Failed to determine the git user (set user.name and user.email)")?;
+        let oid = self
+            .repo
+            .commit(
+                None,
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &[&ours, &theirs],
+            )
+            .git_context("Failed to create merge commit")?;
+
+        self.checkout_merged(tree_id)?;
+        self.save_orig_head("merge")?;
+        self.update_head(oid, &format!("merge {}: Merge made by libgit2", name))?;
+        Ok(MergeOutcome::Merged(oid))
+    }
+
+    /// Updates the index and working tree to `tree`, refusing to overwrite
+    /// local changes to the files the merge touches.
+    fn checkout_merged(&self, tree: Oid) -> Result<()> {
+        let tree = self.repo.find_tree(tree)?;
+        self.repo
+            .checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))
+            .git_context("Local changes would be overwritten by the merge; commit or stash them")
+    }
+}
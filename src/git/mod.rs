@@ -1,41 +1,99 @@
-use anyhow::{bail, Context, Result};
-use git2::{BranchType, Repository};
-use std::path::Path;
+use git2::{BranchType, ErrorCode, Oid, Repository, Status, StatusOptions};
+use std::path::{Path, PathBuf};
+
+pub mod auth;
+pub mod backend;
+pub mod bisect;
+pub mod commit;
+pub mod error;
+pub mod fetch;
+pub mod history;
+pub mod memory;
+pub mod merge;
+pub mod push;
+pub mod stash;
+pub mod state;
+pub mod worktree;
+
+pub use auth::AuthMethod;
+pub use backend::GitBackend;
+pub use bisect::{BisectMark, BisectStep};
+pub use commit::CommitOptions;
+pub use error::{GitError, Result};
+pub use fetch::{FetchObserver, FetchProgress, TransferProgress};
+pub use history::{BranchActivity, CommitInfo, LogOptions};
+pub use memory::MemoryRepo;
+pub use merge::MergeOutcome;
+pub use push::{PushOptions, RejectedRef};
+pub use stash::StashEntry;
+pub use state::{HeadState, Operation, RepoState};
+pub use worktree::WorktreeInfo;
+
+use error::GitContext;
 
 pub struct GitRepo {
     repo: Repository,
 }
 
+/// Changed files, as counted by `GitRepo::status`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StatusSummary {
+    pub modified: usize,
+    pub added: usize,
+    pub deleted: usize,
+}
+
+impl StatusSummary {
+    pub fn is_clean(&self) -> bool {
+        self.modified == 0 && self.added == 0 && self.deleted == 0
+    }
+}
+
+/// How far `GitRepo::reset` goes, as in `git reset --soft/--mixed/--hard`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetMode {
+    /// Move the branch only; staged and working tree changes are kept.
+    Soft,
+    /// Move the branch and reset the index; working tree changes are kept.
+    Mixed,
+    /// Move the branch and discard every change.
+    Hard,
+}
+
 impl GitRepo {
     pub fn open_current() -> Result<Self> {
         let repo = Repository::open_from_env()
             .or_else(|_| Repository::discover("."))
-            .context("Failed to open git repository")?;
+            .map_err(GitError::NotARepository)?;
 
         Ok(Self { repo })
     }
 
     #[allow(dead_code)]
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let repo = Repository::open(path).context("Failed to open git repository")?;
+        let repo = Repository::open(path).map_err(GitError::NotARepository)?;
 
         Ok(Self { repo })
     }
 
-    pub fn current_branch(&self) -> Result<String> {
-        let head = self.repo.head().context("Failed to get HEAD reference")?;
-
-        if !head.is_branch() {
-            bail!("HEAD is not pointing to a branch (detached HEAD state)");
-        }
+    /// Root of the working tree, e.g. where the shared `.gwf.toml` lives.
+    pub fn workdir(&self) -> Result<PathBuf> {
+        let workdir = self.repo.workdir().ok_or(GitError::BareRepository)?;
 
-        let branch_name = head.shorthand().context("Failed to get branch name")?;
+        Ok(workdir.to_path_buf())
+    }
 
-        Ok(branch_name.to_string())
+    /// The checked out branch, even if it has no commits yet. Fails with
+    /// [`GitError::DetachedHead`] when HEAD points at a commit.
+    pub fn current_branch(&self) -> Result<String> {
+        match self.head_state()? {
+            HeadState::Branch(name) | HeadState::Unborn(name) => Ok(name),
+            HeadState::Detached(_) => Err(GitError::DetachedHead),
+        }
     }
 
     pub fn list_remotes(&self) -> Result<Vec<String>> {
-        let remotes = self.repo.remotes().context("Failed to list remotes")?;
+        let remotes = self.repo.remotes().git_context("Failed to list remotes")?;
 
         let remote_names: Vec<String> = remotes
             .iter()
@@ -45,31 +103,87 @@ impl GitRepo {
         Ok(remote_names)
     }
 
+    /// URLs of every remote, `origin` first.
+    pub fn remote_urls(&self) -> Result<Vec<String>> {
+        let mut names = self.list_remotes()?;
+        names.sort_by_key(|name| name != "origin");
+
+        let urls = names
+            .iter()
+            .filter_map(|name| self.repo.find_remote(name).ok())
+            .filter_map(|remote| remote.url().map(String::from))
+            .collect();
+
+        Ok(urls)
+    }
+
+    pub fn remote_url(&self, remote: &str) -> Result<String> {
+        self.repo
+            .find_remote(remote)
+            .ok()
+            .and_then(|remote| remote.url().map(String::from))
+            .ok_or_else(|| GitError::RemoteNotFound(remote.to_string()))
+    }
+
+    /// The branch `refs/remotes/<remote>/HEAD` points to, i.e. the remote's
+    /// default branch as recorded by `git clone`.
+    pub fn remote_default_branch(&self, remote: &str) -> Option<String> {
+        let reference = self
+            .repo
+            .find_reference(&format!("refs/remotes/{}/HEAD", remote))
+            .ok()?;
+        let target = reference.symbolic_target()?;
+
+        target
+            .strip_prefix(&format!("refs/remotes/{}/", remote))
+            .map(String::from)
+    }
+
+    /// Reads a boolean from the git configuration, e.g. `commit.gpgsign`.
+    pub fn config_bool(&self, name: &str) -> Option<bool> {
+        self.repo.config().ok()?.get_bool(name).ok()
+    }
+
+    pub fn config_string(&self, name: &str) -> Option<String> {
+        self.repo.config().ok()?.get_string(name).ok()
+    }
+
+    /// Sets a value in the repository's own `.git/config`.
+    pub fn set_config_string(&self, name: &str, value: &str) -> Result<()> {
+        let mut config = self.repo.config()?.open_level(git2::ConfigLevel::Local)?;
+        config
+            .set_str(name, value)
+            .git_context(format!("Failed to set {}", name))
+    }
+
+    /// Removes a value from the repository's own `.git/config`, if it is set.
+    pub fn unset_config(&self, name: &str) -> Result<()> {
+        let mut config = self.repo.config()?.open_level(git2::ConfigLevel::Local)?;
+        match config.remove(name) {
+            Err(error) if error.code() == ErrorCode::NotFound => Ok(()),
+            result => result.git_context(format!("Failed to unset {}", name)),
+        }
+    }
+
     pub fn create_branch(&self, name: &str, from: Option<&str>) -> Result<()> {
         let target = if let Some(from_branch) = from {
-            let from_ref = self
-                .repo
-                .find_branch(from_branch, BranchType::Local)
-                .context(format!("Branch '{}' not found", from_branch))?;
-            from_ref
-                .get()
-                .target()
-                .context("Failed to get branch target")?
+            self.branch_target(from_branch)?
         } else {
             self.repo
-                .head()?
+                .head()
+                .git_context("Failed to get HEAD reference")?
                 .target()
-                .context("Failed to get HEAD target")?
+                .ok_or(GitError::DetachedHead)?
         };
 
         let commit = self
             .repo
             .find_commit(target)
-            .context("Failed to find commit")?;
+            .git_context("Failed to find commit")?;
 
         self.repo
             .branch(name, &commit, false)
-            .context(format!("Failed to create branch '{}'", name))?;
+            .git_context(format!("Failed to create branch '{}'", name))?;
 
         Ok(())
     }
@@ -78,26 +192,123 @@ impl GitRepo {
         let obj = self
             .repo
             .revparse_single(&format!("refs/heads/{}", branch_name))
-            .context(format!("Failed to find branch '{}'", branch_name))?;
+            .map_err(|_| GitError::BranchNotFound(branch_name.to_string()))?;
 
         self.repo
             .checkout_tree(&obj, None)
-            .context("Failed to checkout tree")?;
+            .git_context("Failed to checkout tree")?;
 
         self.repo
             .set_head(&format!("refs/heads/{}", branch_name))
-            .context("Failed to update HEAD")?;
+            .git_context("Failed to update HEAD")?;
 
         Ok(())
     }
 
+    /// Whether the index or working tree has changes, untracked files
+    /// included, which is exactly what `stash_save` would stash. Ignored
+    /// files such as build output do not count.
     pub fn has_uncommitted_changes(&self) -> Result<bool> {
+        Ok(!self.worktree_statuses()?.is_empty())
+    }
+
+    /// Counts changed files in the index and working tree.
+    pub fn status(&self) -> Result<StatusSummary> {
+        let statuses = self.worktree_statuses()?;
+
+        let mut summary = StatusSummary::default();
+        for entry in statuses.iter() {
+            let status = entry.status();
+            if status.intersects(Status::WT_MODIFIED | Status::INDEX_MODIFIED) {
+                summary.modified += 1;
+            }
+            if status.intersects(Status::WT_NEW | Status::INDEX_NEW) {
+                summary.added += 1;
+            }
+            if status.intersects(Status::WT_DELETED | Status::INDEX_DELETED) {
+                summary.deleted += 1;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Staged, unstaged and untracked changes, leaving out ignored files,
+    /// which libgit2 lists by default.
+    fn worktree_statuses(&self) -> Result<git2::Statuses<'_>> {
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+        self.repo
+            .statuses(Some(&mut options))
+            .git_context("Failed to get repository status")
+    }
+
+    /// Paths with staged changes, relative to the repository root.
+    pub fn staged_files(&self) -> Result<Vec<String>> {
         let statuses = self
             .repo
             .statuses(None)
-            .context("Failed to get repository status")?;
+            .git_context("Failed to get repository status")?;
+        let staged = Status::INDEX_NEW
+            | Status::INDEX_MODIFIED
+            | Status::INDEX_DELETED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE;
+
+        Ok(statuses
+            .iter()
+            .filter(|entry| entry.status().intersects(staged))
+            .filter_map(|entry| entry.path().map(String::from))
+            .collect())
+    }
+
+    /// The staged changes as a patch, compared with HEAD, or with HEAD's
+    /// parent when `amend` is set so the patch covers the amended commit.
+    pub fn staged_diff(&self, amend: bool) -> Result<String> {
+        let head = self
+            .repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+        let base = match head {
+            Some(head) if amend => head.parent(0).ok(),
+            head => head,
+        };
+        let tree = base.map(|commit| commit.tree()).transpose()?;
+        let diff = self
+            .repo
+            .diff_tree_to_index(tree.as_ref(), None, None)
+            .git_context("Failed to diff the index")?;
 
-        Ok(!statuses.is_empty())
+        let mut patch = String::new();
+        diff.print(git2::DiffFormat::Patch, |_, _, line| {
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                patch.push(line.origin());
+            }
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })
+        .git_context("Failed to format the diff")?;
+        Ok(patch)
+    }
+
+    /// How many commits `local` has that `upstream` lacks, and the reverse.
+    /// `None` when `upstream` does not exist, e.g. a branch never pushed.
+    pub fn ahead_behind(&self, local: &str, upstream: &str) -> Result<Option<(usize, usize)>> {
+        let Ok(upstream) = self.find_commit(upstream) else {
+            return Ok(None);
+        };
+        let local = self.find_commit(local)?;
+
+        let counts = self
+            .repo
+            .graph_ahead_behind(local.id(), upstream.id())
+            .git_context("Failed to compare with upstream")?;
+
+        Ok(Some(counts))
     }
 
     pub fn list_branches(&self, include_remote: bool) -> Result<Vec<String>> {
@@ -120,30 +331,13 @@ impl GitRepo {
     }
 
     pub fn is_branch_merged(&self, branch_name: &str, into: &str) -> Result<bool> {
-        let branch = self
-            .repo
-            .find_branch(branch_name, BranchType::Local)
-            .context(format!("Branch '{}' not found", branch_name))?;
-
-        let branch_oid = branch
-            .get()
-            .target()
-            .context("Failed to get branch target")?;
-
-        let into_branch = self
-            .repo
-            .find_branch(into, BranchType::Local)
-            .context(format!("Branch '{}' not found", into))?;
-
-        let into_oid = into_branch
-            .get()
-            .target()
-            .context("Failed to get target branch target")?;
+        let branch_oid = self.branch_target(branch_name)?;
+        let into_oid = self.branch_target(into)?;
 
         let base = self
             .repo
             .merge_base(branch_oid, into_oid)
-            .context("Failed to find merge base")?;
+            .git_context("Failed to find merge base")?;
 
         Ok(base == branch_oid)
     }
@@ -152,59 +346,608 @@ impl GitRepo {
         let mut branch = self
             .repo
             .find_branch(branch_name, BranchType::Local)
-            .context(format!("Branch '{}' not found", branch_name))?;
+            .map_err(|_| GitError::BranchNotFound(branch_name.to_string()))?;
 
         branch
             .delete()
-            .context(format!("Failed to delete branch '{}'", branch_name))?;
+            .git_context(format!("Failed to delete branch '{}'", branch_name))?;
 
         Ok(())
     }
 
-    pub fn fetch(&self, remote: &str) -> Result<()> {
-        let mut remote = self
-            .repo
-            .find_remote(remote)
-            .context(format!("Remote '{}' not found", remote))?;
+    /// Moves the current branch to `revision`, recording the previous
+    /// position in `ORIG_HEAD` like git does.
+    pub fn reset(&self, revision: &str, mode: ResetMode) -> Result<()> {
+        let target = self.find_commit(revision)?;
+        self.save_orig_head("reset")?;
 
-        remote
-            .fetch(&[] as &[&str], None, None)
-            .context("Failed to fetch from remote")?;
+        let reset_type = match mode {
+            ResetMode::Soft => git2::ResetType::Soft,
+            ResetMode::Mixed => git2::ResetType::Mixed,
+            ResetMode::Hard => git2::ResetType::Hard,
+        };
+        self.repo
+            .reset(target.as_object(), reset_type, None)
+            .git_context(format!("Failed to reset to '{}'", revision))
+    }
 
+    /// Records the current HEAD commit in `ORIG_HEAD` before `action` moves
+    /// the branch, so `git reset --hard ORIG_HEAD` undoes it.
+    fn save_orig_head(&self, action: &str) -> Result<()> {
+        if let Some(head) = self.repo.head().ok().and_then(|head| head.target()) {
+            self.repo
+                .reference(
+                    "ORIG_HEAD",
+                    head,
+                    true,
+                    &format!("{}: saving ORIG_HEAD", action),
+                )
+                .git_context("Failed to record ORIG_HEAD")?;
+        }
         Ok(())
     }
 
-    #[allow(dead_code)]
-    pub fn pull(&self, remote: &str, branch: &str) -> Result<()> {
-        self.fetch(remote)?;
-
-        let fetch_head = self
+    /// Replays the commits of the current branch on top of `upstream`. On a
+    /// conflict the rebase is aborted and the conflicting paths returned.
+    pub fn rebase(&self, upstream: &str) -> Result<()> {
+        let upstream_commit = self.find_commit(upstream)?;
+        let upstream = self
             .repo
-            .find_reference("FETCH_HEAD")
-            .context("Failed to find FETCH_HEAD")?;
-
-        let fetch_commit = self
+            .find_annotated_commit(upstream_commit.id())
+            .git_context("Failed to prepare rebase")?;
+        let committer = self
             .repo
-            .reference_to_annotated_commit(&fetch_head)
-            .context("Failed to get fetch commit")?;
+            .signature()
+            .git_context("Failed to determine the git user")?;
 
-        let analysis = self
+        let mut rebase = self
             .repo
-            .merge_analysis(&[&fetch_commit])
-            .context("Failed to analyze merge")?;
-
-        if analysis.0.is_up_to_date() {
-            println!("Already up to date");
-        } else if analysis.0.is_fast_forward() {
-            let refname = format!("refs/heads/{}", branch);
-            let mut reference = self.repo.find_reference(&refname)?;
-            reference.set_target(fetch_commit.id(), "Fast-forward")?;
-            self.repo
-                .checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
-        } else {
-            bail!("Merge required - not implemented in this example");
+            .rebase(None, Some(&upstream), None, None)
+            .git_context("Failed to start rebase")?;
+
+        while let Some(operation) = rebase.next() {
+            if let Err(error) = operation {
+                rebase.abort().ok();
+                return Err(error).git_context("Failed to apply commit during rebase");
+            }
+
+            let conflicts = self.conflicted_paths()?;
+            if !conflicts.is_empty() {
+                rebase.abort().ok();
+                return Err(GitError::Conflicts(conflicts));
+            }
+
+            match rebase.commit(None, &committer, None) {
+                Ok(_) => {}
+                // The change is already upstream; nothing to replay.
+                Err(error) if error.code() == ErrorCode::Applied => {}
+                Err(error) => {
+                    rebase.abort().ok();
+                    return Err(error).git_context("Failed to commit during rebase");
+                }
+            }
         }
 
-        Ok(())
+        rebase
+            .finish(Some(&committer))
+            .git_context("Failed to finish rebase")
+    }
+
+    fn conflicted_paths(&self) -> Result<Vec<String>> {
+        conflicts_in(&self.repo.index()?)
+    }
+
+    fn branch_target(&self, branch_name: &str) -> Result<Oid> {
+        self.repo
+            .find_branch(branch_name, BranchType::Local)
+            .ok()
+            .and_then(|branch| branch.get().target())
+            .ok_or_else(|| GitError::BranchNotFound(branch_name.to_string()))
+    }
+
+    fn find_commit(&self, revision: &str) -> Result<git2::Commit<'_>> {
+        self.repo
+            .revparse_single(revision)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|_| GitError::RevisionNotFound(revision.to_string()))
+    }
+}
+
+/// Paths with conflicts in `index`, sorted.
+fn conflicts_in(index: &git2::Index) -> Result<Vec<String>> {
+    if !index.has_conflicts() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths = Vec::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        let entry = conflict.our.or(conflict.their).or(conflict.ancestor);
+        if let Some(entry) = entry {
+            paths.push(String::from_utf8_lossy(&entry.path).into_owned());
+        }
+    }
+    paths.sort();
+    paths.dedup();
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn test_repo() -> (TempDir, GitRepo) {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_str("core.hooksPath", "no-hooks").unwrap();
+
+        (dir, GitRepo { repo })
+    }
+
+    fn commit_file(repo: &GitRepo, name: &str, contents: &str, message: &str) -> Oid {
+        fs::write(repo.workdir().unwrap().join(name), contents).unwrap();
+        let mut index = repo.repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+
+        repo.commit(message, &CommitOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_staged_files() {
+        let (_dir, repo) = test_repo();
+        commit_file(&repo, "a.txt", "a", "Initial commit");
+        let workdir = repo.workdir().unwrap();
+        fs::create_dir(workdir.join("src")).unwrap();
+        fs::write(workdir.join("src/lib.rs"), "lib").unwrap();
+        fs::write(workdir.join("a.txt"), "changed").unwrap();
+        fs::write(workdir.join("untracked.txt"), "new").unwrap();
+
+        let mut index = repo.repo.index().unwrap();
+        index.add_path(Path::new("src/lib.rs")).unwrap();
+        index.write().unwrap();
+
+        assert_eq!(repo.staged_files().unwrap(), ["src/lib.rs"]);
+
+        let diff = repo.staged_diff(false).unwrap();
+        assert!(diff.starts_with("diff --git a/src/lib.rs b/src/lib.rs\n"));
+        assert!(diff.contains("+lib"));
+        assert!(!diff.contains("a.txt"));
+    }
+
+    #[test]
+    fn test_commit_and_nothing_to_commit() {
+        let (_dir, repo) = test_repo();
+
+        assert!(matches!(
+            repo.commit("empty", &CommitOptions::default()),
+            Err(GitError::NothingToCommit)
+        ));
+
+        let first = commit_file(&repo, "a.txt", "a", "Add a");
+        assert!(matches!(
+            repo.commit("again", &CommitOptions::default()),
+            Err(GitError::NothingToCommit)
+        ));
+
+        let amended = repo
+            .commit(
+                "Add a file",
+                &CommitOptions {
+                    amend: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_ne!(first, amended);
+
+        let log = repo.log(&LogOptions::default()).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].summary, "Add a file");
+        assert_eq!(log[0].author_email, "test@example.com");
+    }
+
+    #[test]
+    fn test_reset_and_tags() {
+        let (_dir, repo) = test_repo();
+        let first = commit_file(&repo, "a.txt", "1", "First");
+        repo.create_tag("v1.0.0", "Release 1.0.0", None).unwrap();
+        let second = commit_file(&repo, "a.txt", "2", "Second");
+
+        assert_eq!(repo.latest_tag().unwrap().as_deref(), Some("v1.0.0"));
+        assert!(matches!(
+            repo.create_tag("v1.0.0", "again", None),
+            Err(GitError::TagExists(_))
+        ));
+
+        repo.reset("HEAD~1", ResetMode::Hard).unwrap();
+        assert_eq!(repo.find_commit("HEAD").unwrap().id(), first);
+        assert_eq!(repo.find_commit("ORIG_HEAD").unwrap().id(), second);
+        let contents = fs::read_to_string(repo.workdir().unwrap().join("a.txt")).unwrap();
+        assert_eq!(contents, "1");
+    }
+
+    #[test]
+    fn test_bisect_finds_first_bad_commit() {
+        let (_dir, repo) = test_repo();
+        let commits: Vec<Oid> = (0..8)
+            .map(|n| commit_file(&repo, "n.txt", &n.to_string(), &format!("Commit {}", n)))
+            .collect();
+        let branch = repo.current_branch().unwrap();
+
+        let good = commits[0].to_string();
+        let mut step = repo.bisect_start("HEAD", &[good]).unwrap();
+        let found = loop {
+            step = match step {
+                BisectStep::Testing { commit, .. } => {
+                    let position = commits.iter().position(|oid| *oid == commit.id).unwrap();
+                    let mark = if position >= 5 {
+                        BisectMark::Bad
+                    } else {
+                        BisectMark::Good
+                    };
+                    repo.bisect_mark(mark, None).unwrap()
+                }
+                BisectStep::Found(commit) => break commit,
+                BisectStep::NeedsMarks => panic!("bisect lost its marks"),
+            };
+        };
+        assert_eq!(found.id, commits[5]);
+
+        repo.bisect_reset().unwrap();
+        assert!(!repo.is_bisecting());
+        assert_eq!(repo.current_branch().unwrap(), branch);
+    }
+
+    #[test]
+    fn test_push_sets_upstream_and_leases() {
+        let (_dir, repo) = test_repo();
+        let remote_dir = TempDir::new().unwrap();
+        let remote = Repository::init_bare(remote_dir.path()).unwrap();
+        repo.repo
+            .remote("origin", remote_dir.path().to_str().unwrap())
+            .unwrap();
+
+        let first = commit_file(&repo, "a.txt", "a", "Add a");
+        let branch = repo.current_branch().unwrap();
+        let refs = vec![format!("refs/heads/{}", branch)];
+        let upstream = PushOptions {
+            set_upstream: true,
+            ..Default::default()
+        };
+        repo.push("origin", &refs, &upstream).unwrap();
+        assert_eq!(repo.upstream(&branch), Some(format!("origin/{}", branch)));
+        assert_eq!(remote.refname_to_id(&refs[0]).unwrap(), first);
+
+        commit_file(&repo, "b.txt", "b", "Add b");
+        repo.push("origin", &refs, &PushOptions::default()).unwrap();
+        repo.reset(&first.to_string(), ResetMode::Hard).unwrap();
+        let rewritten = commit_file(&repo, "c.txt", "c", "Add c");
+        assert!(matches!(
+            repo.push("origin", &refs, &PushOptions::default()),
+            Err(GitError::PushRejected(_))
+        ));
+
+        let lease = PushOptions {
+            force_with_lease: true,
+            ..Default::default()
+        };
+        repo.push("origin", &refs, &lease).unwrap();
+        assert_eq!(remote.refname_to_id(&refs[0]).unwrap(), rewritten);
+
+        remote.reference(&refs[0], first, true, "test").unwrap();
+        let Err(GitError::PushRejected(rejected)) = repo.push("origin", &refs, &lease) else {
+            panic!("push over a moved remote branch should be rejected");
+        };
+        assert_eq!(rejected[0].name, refs[0]);
+        assert_eq!(remote.refname_to_id(&refs[0]).unwrap(), first);
+    }
+
+    #[test]
+    fn test_merge_creates_commit_or_reports_conflicts() {
+        let (_dir, repo) = test_repo();
+        commit_file(&repo, "a.txt", "a", "Add a");
+        let branch = repo.current_branch().unwrap();
+        repo.create_branch("topic", None).unwrap();
+        repo.create_branch("other", None).unwrap();
+
+        repo.checkout("topic").unwrap();
+        let theirs = commit_file(&repo, "b.txt", "b", "Add b");
+        repo.checkout(&branch).unwrap();
+        let ours = commit_file(&repo, "c.txt", "c", "Add c");
+
+        let MergeOutcome::Merged(merge) = repo.merge("topic").unwrap() else {
+            panic!("diverged branches should get a merge commit");
+        };
+        let commit = repo.repo.find_commit(merge).unwrap();
+        assert_eq!(commit.parent_ids().collect::<Vec<_>>(), vec![ours, theirs]);
+        assert_eq!(
+            commit.summary(),
+            Some(format!("Merge branch 'topic' into {}", branch).as_str())
+        );
+        assert!(repo.workdir().unwrap().join("b.txt").exists());
+        assert!(!repo.has_uncommitted_changes().unwrap());
+        assert_eq!(repo.merge("topic").unwrap(), MergeOutcome::UpToDate);
+
+        repo.checkout("other").unwrap();
+        commit_file(&repo, "a.txt", "theirs", "Change a");
+        repo.checkout(&branch).unwrap();
+        commit_file(&repo, "a.txt", "ours", "Change a differently");
+        let head = repo.find_commit("HEAD").unwrap().id();
+
+        assert!(matches!(
+            repo.merge("other"),
+            Err(GitError::Conflicts(paths)) if paths == ["a.txt"]
+        ));
+        assert_eq!(repo.find_commit("HEAD").unwrap().id(), head);
+        assert_eq!(
+            fs::read_to_string(repo.workdir().unwrap().join("a.txt")).unwrap(),
+            "ours"
+        );
+        assert_eq!(repo.repo.state(), git2::RepositoryState::Clean);
+    }
+
+    #[test]
+    fn test_merge_can_be_undone_with_orig_head() {
+        let (_dir, repo) = test_repo();
+        commit_file(&repo, "a.txt", "a", "Add a");
+        let branch = repo.current_branch().unwrap();
+        repo.create_branch("topic", None).unwrap();
+        repo.create_branch("behind", None).unwrap();
+        repo.checkout("topic").unwrap();
+        commit_file(&repo, "b.txt", "b", "Add b");
+        repo.checkout(&branch).unwrap();
+        let before = commit_file(&repo, "c.txt", "c", "Add c");
+
+        // An older reset leaves ORIG_HEAD somewhere else first.
+        repo.reset("HEAD~1", ResetMode::Mixed).unwrap();
+        repo.reset("ORIG_HEAD", ResetMode::Mixed).unwrap();
+        assert!(matches!(
+            repo.merge("topic").unwrap(),
+            MergeOutcome::Merged(_)
+        ));
+        repo.reset("ORIG_HEAD", ResetMode::Hard).unwrap();
+        assert_eq!(repo.find_commit("HEAD").unwrap().id(), before);
+
+        repo.checkout("behind").unwrap();
+        let behind = repo.find_commit("HEAD").unwrap().id();
+        assert!(matches!(
+            repo.fast_forward("behind", &branch).unwrap(),
+            MergeOutcome::FastForward(_)
+        ));
+        repo.reset("ORIG_HEAD", ResetMode::Hard).unwrap();
+        assert_eq!(repo.find_commit("HEAD").unwrap().id(), behind);
+    }
+
+    #[test]
+    fn test_pull_fast_forwards() {
+        let (_dir, repo) = test_repo();
+        let remote_dir = TempDir::new().unwrap();
+        Repository::init_bare(remote_dir.path()).unwrap();
+        repo.repo
+            .remote("origin", remote_dir.path().to_str().unwrap())
+            .unwrap();
+
+        let first = commit_file(&repo, "a.txt", "a", "Add a");
+        let second = commit_file(&repo, "b.txt", "b", "Add b");
+        let branch = repo.current_branch().unwrap();
+        repo.push(
+            "origin",
+            &[format!("refs/heads/{}", branch)],
+            &PushOptions::default(),
+        )
+        .unwrap();
+        repo.reset(&first.to_string(), ResetMode::Hard).unwrap();
+
+        assert_eq!(
+            repo.pull("origin", &branch).unwrap(),
+            MergeOutcome::FastForward(second)
+        );
+        assert!(repo.workdir().unwrap().join("b.txt").exists());
+        assert_eq!(
+            repo.pull("origin", &branch).unwrap(),
+            MergeOutcome::UpToDate
+        );
+    }
+
+    #[test]
+    fn test_fetch_reports_transfer_progress() {
+        let (_source_dir, source) = test_repo();
+        commit_file(&source, "a.txt", "a", "Add a");
+        commit_file(&source, "b.txt", "b", "Add b");
+
+        let (_dir, repo) = test_repo();
+        repo.repo
+            .remote("origin", source.workdir().unwrap().to_str().unwrap())
+            .unwrap();
+        let mut last = None;
+        repo.fetch("origin", false, &mut |progress: FetchProgress| {
+            if let FetchProgress::Transfer(transfer) = progress {
+                last = Some(transfer);
+            }
+        })
+        .unwrap();
+
+        let last = last.expect("fetch should report its progress");
+        assert!(last.total_objects > 0);
+        assert_eq!(last.received_objects, last.total_objects);
+        assert!(last.received_bytes > 0);
+    }
+
+    #[test]
+    fn test_state_of_unborn_detached_and_interrupted_repositories() {
+        let (_dir, repo) = test_repo();
+        let state = repo.state().unwrap();
+        let HeadState::Unborn(branch) = &state.head else {
+            panic!("expected an unborn branch, got {:?}", state.head);
+        };
+        assert_eq!(repo.current_branch().unwrap(), *branch);
+        assert_eq!(state.operation, None);
+
+        let oid = commit_file(&repo, "a.txt", "a", "Add a");
+        assert_eq!(
+            repo.state().unwrap().head,
+            HeadState::Branch(branch.clone())
+        );
+
+        repo.repo.set_head_detached(oid).unwrap();
+        assert_eq!(repo.state().unwrap().head, HeadState::Detached(oid));
+        assert!(matches!(repo.current_branch(), Err(GitError::DetachedHead)));
+
+        let rebase = repo.repo.path().join("rebase-merge");
+        fs::create_dir(&rebase).unwrap();
+        fs::write(rebase.join("head-name"), "refs/heads/feature/login\n").unwrap();
+        let state = repo.state().unwrap();
+        assert_eq!(
+            state.operation,
+            Some(Operation::Rebase {
+                branch: Some("feature/login".to_string())
+            })
+        );
+        let error = state.ensure_idle().unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("A rebase of 'feature/login' is in progress"));
+    }
+
+    #[test]
+    fn test_stashes_record_their_branch() {
+        let (_dir, mut repo) = test_repo();
+        commit_file(&repo, "a.txt", "a", "Add a");
+        repo.create_branch("feature/login", None).unwrap();
+        let main = repo.current_branch().unwrap();
+        let workdir = repo.workdir().unwrap();
+
+        fs::write(workdir.join("a.txt"), "on main").unwrap();
+        let first = repo.stash_save("gwf autostash").unwrap().unwrap();
+        repo.checkout("feature/login").unwrap();
+        fs::write(workdir.join("a.txt"), "on feature").unwrap();
+        repo.stash_save("wip").unwrap().unwrap();
+
+        let stashes = repo.stashes().unwrap();
+        assert_eq!(stashes.len(), 2);
+        assert_eq!(stashes[0].branch.as_deref(), Some("feature/login"));
+        assert_eq!(stashes[0].message, "wip");
+        assert_eq!(stashes[1].index, 1);
+        assert_eq!(stashes[1].branch, Some(main.clone()));
+        assert_eq!(stashes[1].message, "gwf autostash");
+
+        repo.checkout(&main).unwrap();
+        repo.stash_pop(first).unwrap();
+        assert_eq!(
+            fs::read_to_string(workdir.join("a.txt")).unwrap(),
+            "on main"
+        );
+        assert_eq!(repo.stashes().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_stash_includes_untracked_files() {
+        let (_dir, mut repo) = test_repo();
+        commit_file(&repo, "a.txt", "a", "Add a");
+        let workdir = repo.workdir().unwrap();
+
+        fs::write(workdir.join("new.txt"), "new").unwrap();
+        assert!(repo.has_uncommitted_changes().unwrap());
+        let stash = repo.stash_save("gwf autostash").unwrap().unwrap();
+        assert!(!workdir.join("new.txt").exists());
+        assert!(!repo.has_uncommitted_changes().unwrap());
+
+        repo.stash_pop(stash).unwrap();
+        assert_eq!(fs::read_to_string(workdir.join("new.txt")).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_ignored_files_are_not_changes() {
+        let (_dir, mut repo) = test_repo();
+        commit_file(&repo, ".gitignore", "target/\n", "Ignore build output");
+        let workdir = repo.workdir().unwrap();
+
+        fs::create_dir(workdir.join("target")).unwrap();
+        fs::write(workdir.join("target/app"), "binary").unwrap();
+        assert!(!repo.has_uncommitted_changes().unwrap());
+        assert!(repo.status().unwrap().is_clean());
+        assert_eq!(repo.stash_save("gwf autostash").unwrap(), None);
+
+        fs::write(workdir.join("notes.txt"), "new").unwrap();
+        assert!(repo.has_uncommitted_changes().unwrap());
+        assert!(repo.stash_save("gwf autostash").unwrap().is_some());
+        assert!(!repo.has_uncommitted_changes().unwrap());
+        assert!(workdir.join("target/app").exists());
+    }
+
+    #[test]
+    fn test_stash_entry_without_branch() {
+        let entry = StashEntry::new(0, Oid::zero(), "WIP on (no branch): 1234567 Fix");
+        assert_eq!(entry.branch, None);
+        assert_eq!(entry.message, "1234567 Fix");
+
+        let entry = StashEntry::new(3, Oid::zero(), "custom message");
+        assert_eq!(entry.branch, None);
+        assert_eq!(entry.message, "custom message");
+    }
+
+    #[test]
+    fn test_worktree_add_remove_and_prune() {
+        let (_dir, repo) = test_repo();
+        commit_file(&repo, "a.txt", "a", "Add a");
+        repo.create_branch("feature/login", None).unwrap();
+        repo.create_branch("hotfix/crash", None).unwrap();
+        let worktrees = TempDir::new().unwrap();
+        let root = worktrees.path().join("nested");
+
+        let login = repo
+            .add_worktree("feature/login", &root.join("login"))
+            .unwrap();
+        assert_eq!(login.name, "feature-login");
+        assert!(login.path.join("a.txt").exists());
+        repo.add_worktree("hotfix/crash", &root.join("crash"))
+            .unwrap();
+        assert!(matches!(
+            repo.add_worktree("hotfix/crash", &root.join("crash")),
+            Err(GitError::WorktreePathExists(_))
+        ));
+
+        let branches: Vec<_> = repo
+            .worktrees()
+            .unwrap()
+            .into_iter()
+            .map(|worktree| worktree.branch)
+            .collect();
+        assert!(branches.contains(&Some("feature/login".to_string())));
+        assert!(branches.contains(&Some("hotfix/crash".to_string())));
+
+        fs::write(login.path.join("a.txt"), "changed").unwrap();
+        assert!(matches!(
+            repo.remove_worktree("feature/login", false),
+            Err(GitError::WorktreeDirty(_))
+        ));
+        repo.remove_worktree("feature/login", true).unwrap();
+        assert!(!login.path.exists());
+        repo.delete_branch("feature/login").unwrap();
+
+        fs::remove_dir_all(root.join("crash")).unwrap();
+        let pruned = repo.prune_worktrees().unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].name, "hotfix-crash");
+        assert!(repo.worktrees().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            worktree::normalize_path(Path::new("/src/api/../api-feature-x")),
+            PathBuf::from("/src/api-feature-x")
+        );
+        assert_eq!(
+            worktree::normalize_path(Path::new("../../a/./b")),
+            PathBuf::from("../../a/b")
+        );
     }
 }
@@ -0,0 +1,203 @@
+use git2::{Direction, ErrorCode, Oid};
+use std::cell::RefCell;
+
+use super::auth::{self, Credentials};
+use super::error::{GitContext, GitError, Result};
+use super::GitRepo;
+
+/// How `GitRepo::push` updates the remote.
+#[derive(Debug, Default, Clone)]
+pub struct PushOptions {
+    /// Record each pushed branch as the upstream of the local branch, like
+    /// `git push --set-upstream`.
+    pub set_upstream: bool,
+    /// Overwrite remote branches even if that loses commits, but only when
+    /// they are still where our remote-tracking branches say, like
+    /// `git push --force-with-lease`.
+    pub force_with_lease: bool,
+}
+
+/// A ref the remote refused to update, with the reason it gave.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedRef {
+    pub name: String,
+    pub reason: String,
+}
+
+impl GitRepo {
+    /// Pushes `refs` (full names such as `refs/heads/main` or
+    /// `refs/tags/v1.0.0`) to the same names on `remote`. Every ref the
+    /// remote rejects is listed in `GitError::PushRejected`.
+    pub fn push(&self, remote: &str, refs: &[String], options: &PushOptions) -> Result<()> {
+        let mut connection = self
+            .repo
+            .find_remote(remote)
+            .map_err(|_| GitError::RemoteNotFound(remote.to_string()))?;
+        let url = connection
+            .pushurl()
+            .or(connection.url())
+            .unwrap_or_default()
+            .to_string();
+
+        let config = self.repo.config()?;
+        auth::configure_certificates(&config)?;
+
+        if options.force_with_lease {
+            self.check_leases(&mut connection, &url, remote, refs)?;
+        }
+
+        let refspecs: Vec<String> = refs
+            .iter()
+            .map(|name| {
+                let force = if options.force_with_lease { "+" } else { "" };
+                format!("{}{}:{}", force, name, name)
+            })
+            .collect();
+
+        let rejected = RefCell::new(Vec::new());
+        let mut credentials = Credentials::new(config);
+        let result = {
+            let mut callbacks = credentials.callbacks();
+            callbacks.push_update_reference(|name, status| {
+                if let Some(reason) = status {
+                    rejected.borrow_mut().push(RejectedRef {
+                        name: name.to_string(),
+                        reason: reason.to_string(),
+                    });
+                }
+                Ok(())
+            });
+
+            let mut push_options = git2::PushOptions::new();
+            push_options.remote_callbacks(callbacks);
+            connection.push(&refspecs, Some(&mut push_options))
+        };
+
+        match result {
+            Ok(()) => {}
+            Err(error) if error.code() == ErrorCode::NotFastForward => {
+                return Err(GitError::PushRejected(
+                    refs.iter()
+                        .map(|name| RejectedRef {
+                            name: name.clone(),
+                            reason: "non-fast-forward; sync with the remote first".to_string(),
+                        })
+                        .collect(),
+                ));
+            }
+            Err(error) => return Err(credentials.error(&url, "Failed to push to remote", error)),
+        }
+
+        let rejected = rejected.into_inner();
+        if !rejected.is_empty() {
+            return Err(GitError::PushRejected(rejected));
+        }
+
+        for name in refs {
+            let Some(branch) = name.strip_prefix("refs/heads/") else {
+                continue;
+            };
+            self.update_tracking_branch(remote, branch)?;
+            if options.set_upstream {
+                self.set_upstream(branch, remote)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records `remote` as the upstream of `branch` in `branch.<name>.remote`
+    /// and `branch.<name>.merge`, where `git pull` and `gwf sync` look.
+    pub fn set_upstream(&self, branch: &str, remote: &str) -> Result<()> {
+        let mut config = self.repo.config()?;
+        config
+            .set_str(&format!("branch.{}.remote", branch), remote)
+            .git_context("Failed to set upstream")?;
+        config
+            .set_str(
+                &format!("branch.{}.merge", branch),
+                &format!("refs/heads/{}", branch),
+            )
+            .git_context("Failed to set upstream")?;
+        Ok(())
+    }
+
+    /// The remote-tracking branch `branch` follows, such as `origin/main`,
+    /// if an upstream is configured.
+    pub fn upstream(&self, branch: &str) -> Option<String> {
+        let name = self
+            .repo
+            .branch_upstream_name(&format!("refs/heads/{}", branch))
+            .ok()?;
+        name.as_str()?
+            .strip_prefix("refs/remotes/")
+            .map(String::from)
+    }
+
+    /// Refuses to overwrite branches that moved on the remote since we last
+    /// fetched. libgit2 cannot make the check part of the push itself, so a
+    /// push racing with ours between the two connections is not detected.
+    fn check_leases(
+        &self,
+        connection: &mut git2::Remote,
+        url: &str,
+        remote: &str,
+        refs: &[String],
+    ) -> Result<()> {
+        let config = self.repo.config()?;
+        let mut credentials = Credentials::new(config);
+        let heads: Vec<(String, Oid)> = connection
+            .connect_auth(Direction::Push, Some(credentials.callbacks()), None)
+            .and_then(|session| {
+                Ok(session
+                    .list()?
+                    .iter()
+                    .map(|head| (head.name().to_string(), head.oid()))
+                    .collect())
+            })
+            .map_err(|error| credentials.error(url, "Failed to connect to remote", error))?;
+
+        let mut stale = Vec::new();
+        for name in refs {
+            let Some(branch) = name.strip_prefix("refs/heads/") else {
+                continue;
+            };
+            let expected = self
+                .repo
+                .refname_to_id(&format!("refs/remotes/{}/{}", remote, branch))
+                .ok();
+            let actual = heads
+                .iter()
+                .find(|(head, _)| head == name)
+                .map(|(_, oid)| *oid);
+
+            if actual.is_some() && actual != expected {
+                stale.push(RejectedRef {
+                    name: name.clone(),
+                    reason: "stale info; the remote branch has new commits, fetch first"
+                        .to_string(),
+                });
+            }
+        }
+
+        if !stale.is_empty() {
+            return Err(GitError::PushRejected(stale));
+        }
+        Ok(())
+    }
+
+    fn update_tracking_branch(&self, remote: &str, branch: &str) -> Result<()> {
+        let oid = self
+            .repo
+            .refname_to_id(&format!("refs/heads/{}", branch))
+            .git_context(format!("Failed to find branch '{}'", branch))?;
+        self.repo
+            .reference(
+                &format!("refs/remotes/{}/{}", remote, branch),
+                oid,
+                true,
+                "update by push",
+            )
+            .git_context("Failed to update remote-tracking branch")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use git2::{ErrorCode, Oid, StashFlags};
+
+use super::error::{GitContext, GitError, Result};
+use super::GitRepo;
+
+/// An entry of the stash list, newest first like `git stash list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StashEntry {
+    /// Position in the stash list, as in `stash@{index}`.
+    pub index: usize,
+    pub oid: Oid,
+    /// The branch that was checked out when the changes were stashed;
+    /// `None` if HEAD was detached.
+    pub branch: Option<String>,
+    pub message: String,
+}
+
+impl StashEntry {
+    /// Parses the reflog message git records for a stash, `On <branch>:
+    /// <message>` or `WIP on <branch>: <summary>`.
+    pub fn new(index: usize, oid: Oid, reflog: &str) -> Self {
+        let described = reflog
+            .strip_prefix("On ")
+            .or_else(|| reflog.strip_prefix("WIP on "))
+            .and_then(|rest| rest.split_once(": "));
+
+        let (branch, message) = match described {
+            Some((branch, message)) => {
+                let branch = (branch != "(no branch)").then(|| branch.to_string());
+                (branch, message.to_string())
+            }
+            None => (None, reflog.to_string()),
+        };
+
+        Self {
+            index,
+            oid,
+            branch,
+            message,
+        }
+    }
+}
+
+impl GitRepo {
+    /// Stashes staged and unstaged changes and untracked files, like `git
+    /// stash push --include-untracked`, so the working tree is left as clean
+    /// as `has_uncommitted_changes` requires. Returns `None` when there was
+    /// nothing to stash.
+    pub fn stash_save(&mut self, message: &str) -> Result<Option<Oid>> {
+        let signature = self
+            .repo
+            .signature()
+            .git_context("Failed to determine the git user")?;
+
+        match self
+            .repo
+            .stash_save(&signature, message, Some(StashFlags::INCLUDE_UNTRACKED))
+        {
+            Ok(oid) => Ok(Some(oid)),
+            Err(error) if error.code() == ErrorCode::NotFound => Ok(None),
+            Err(error) => Err(error).git_context("Failed to stash changes"),
+        }
+    }
+
+    /// Applies and drops the stash entry created as `stash`. On conflicts the
+    /// entry is kept so nothing is lost.
+    pub fn stash_pop(&mut self, stash: Oid) -> Result<()> {
+        let index = self
+            .stashes()?
+            .into_iter()
+            .find(|entry| entry.oid == stash)
+            .map(|entry| entry.index)
+            .ok_or_else(|| GitError::RevisionNotFound(stash.to_string()))?;
+
+        self.repo
+            .stash_pop(index, None)
+            .git_context("Failed to restore stashed changes; they are kept in the stash")
+    }
+
+    /// The stash list, newest entry first.
+    pub fn stashes(&mut self) -> Result<Vec<StashEntry>> {
+        let mut stashes = Vec::new();
+        self.repo
+            .stash_foreach(|index, message, oid| {
+                stashes.push(StashEntry::new(index, *oid, message));
+                true
+            })
+            .git_context("Failed to list stashes")?;
+
+        Ok(stashes)
+    }
+}
@@ -0,0 +1,172 @@
+use git2::{ErrorCode, Oid, RepositoryState};
+use std::fmt;
+use std::fs;
+
+use super::error::{GitContext, GitError, Result};
+use super::GitRepo;
+
+/// What HEAD points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadState {
+    /// A branch with at least one commit.
+    Branch(String),
+    /// A branch without commits yet, as right after `git init`.
+    Unborn(String),
+    /// A commit rather than a branch, e.g. while bisecting or rebasing.
+    Detached(Oid),
+}
+
+impl fmt::Display for HeadState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadState::Branch(name) => write!(f, "{}", name),
+            HeadState::Unborn(name) => write!(f, "{} (no commits yet)", name),
+            HeadState::Detached(oid) => write!(f, "detached HEAD at {:.7}", oid),
+        }
+    }
+}
+
+/// A multi-step git operation that stopped halfway, usually on a conflict,
+/// and waits to be continued or aborted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Merge,
+    /// Rebasing `branch`; `None` if git did not record which branch.
+    Rebase {
+        branch: Option<String>,
+    },
+    CherryPick,
+    Revert,
+    /// Applying patches with `git am`.
+    ApplyMailbox,
+    Bisect,
+}
+
+impl Operation {
+    /// How to get out of the operation, for error messages.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Operation::Merge => {
+                "resolve the conflicts and commit, or run 'git merge --abort'"
+            }
+            Operation::Rebase { .. } => {
+                "resolve the conflicts and run 'git rebase --continue', or 'git rebase --abort'"
+            }
+            Operation::CherryPick => {
+                "resolve the conflicts and run 'git cherry-pick --continue', or 'git cherry-pick --abort'"
+            }
+            Operation::Revert => {
+                "resolve the conflicts and run 'git revert --continue', or 'git revert --abort'"
+            }
+            Operation::ApplyMailbox => "run 'git am --continue', or 'git am --abort'",
+            Operation::Bisect => "finish it with 'gwf bisect', or run 'git bisect reset'",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Merge => write!(f, "merge"),
+            Operation::Rebase {
+                branch: Some(branch),
+            } => write!(f, "rebase of '{}'", branch),
+            Operation::Rebase { branch: None } => write!(f, "rebase"),
+            Operation::CherryPick => write!(f, "cherry-pick"),
+            Operation::Revert => write!(f, "revert"),
+            Operation::ApplyMailbox => write!(f, "patch application"),
+            Operation::Bisect => write!(f, "bisect"),
+        }
+    }
+}
+
+/// Where HEAD points and whether an operation is in progress, so commands
+/// can explain what they cannot do instead of failing on the first lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoState {
+    pub head: HeadState,
+    pub operation: Option<Operation>,
+}
+
+impl RepoState {
+    /// The checked out branch, even if it has no commits yet.
+    pub fn branch(&self) -> Option<&str> {
+        match &self.head {
+            HeadState::Branch(name) | HeadState::Unborn(name) => Some(name),
+            HeadState::Detached(_) => None,
+        }
+    }
+
+    pub fn is_unborn(&self) -> bool {
+        matches!(self.head, HeadState::Unborn(_))
+    }
+
+    /// Fails with [`GitError::OperationInProgress`] while an operation waits
+    /// to be finished, for commands that would get in its way.
+    pub fn ensure_idle(&self) -> Result<()> {
+        match &self.operation {
+            Some(operation) => Err(GitError::OperationInProgress(operation.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl GitRepo {
+    /// Reads where HEAD points and which operation, if any, is in progress.
+    pub fn state(&self) -> Result<RepoState> {
+        let operation = match self.repo.state() {
+            RepositoryState::Clean => None,
+            RepositoryState::Merge => Some(Operation::Merge),
+            RepositoryState::Rebase
+            | RepositoryState::RebaseInteractive
+            | RepositoryState::RebaseMerge
+            | RepositoryState::ApplyMailboxOrRebase => Some(Operation::Rebase {
+                branch: self.rebase_branch(),
+            }),
+            RepositoryState::ApplyMailbox => Some(Operation::ApplyMailbox),
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                Some(Operation::CherryPick)
+            }
+            RepositoryState::Revert | RepositoryState::RevertSequence => Some(Operation::Revert),
+            RepositoryState::Bisect => Some(Operation::Bisect),
+        };
+
+        Ok(RepoState {
+            head: self.head_state()?,
+            operation,
+        })
+    }
+
+    pub(super) fn head_state(&self) -> Result<HeadState> {
+        match self.repo.head() {
+            Ok(head) if head.is_branch() => Ok(HeadState::Branch(
+                String::from_utf8_lossy(head.shorthand_bytes()).into_owned(),
+            )),
+            Ok(head) => head
+                .target()
+                .map(HeadState::Detached)
+                .ok_or(GitError::DetachedHead),
+            Err(error) if error.code() == ErrorCode::UnbornBranch => {
+                let head = self
+                    .repo
+                    .find_reference("HEAD")
+                    .git_context("Failed to get HEAD reference")?;
+                let target =
+                    String::from_utf8_lossy(head.symbolic_target_bytes().unwrap_or_default())
+                        .into_owned();
+                let branch = target.strip_prefix("refs/heads/").unwrap_or(&target);
+                Ok(HeadState::Unborn(branch.to_string()))
+            }
+            Err(error) => Err(error).git_context("Failed to get HEAD reference"),
+        }
+    }
+
+    /// The branch an interrupted `git rebase` is rebasing, from the
+    /// `head-name` file git keeps in its state directory.
+    fn rebase_branch(&self) -> Option<String> {
+        ["rebase-merge", "rebase-apply"].iter().find_map(|dir| {
+            let name = fs::read_to_string(self.repo.path().join(dir).join("head-name")).ok()?;
+            name.trim().strip_prefix("refs/heads/").map(String::from)
+        })
+    }
+}
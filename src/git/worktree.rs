@@ -0,0 +1,175 @@
+use git2::{Repository, WorktreeAddOptions, WorktreeLockStatus, WorktreePruneOptions};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::error::{GitContext, GitError, Result};
+use super::GitRepo;
+
+/// A linked worktree, as listed by `git worktree list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorktreeInfo {
+    /// Name of the worktree's administrative directory in `.git/worktrees`.
+    pub name: String,
+    pub path: PathBuf,
+    /// The branch checked out in the worktree; `None` when HEAD is detached
+    /// or the worktree is missing.
+    pub branch: Option<String>,
+    pub locked: bool,
+    /// The worktree directory no longer exists, so `prune_worktrees` will
+    /// remove its administrative files.
+    pub prunable: bool,
+}
+
+impl GitRepo {
+    /// Checks out the existing `branch` in a new linked worktree at `path`,
+    /// like `git worktree add <path> <branch>`.
+    pub fn add_worktree(&self, branch: &str, path: &Path) -> Result<WorktreeInfo> {
+        let reference = self
+            .repo
+            .find_branch(branch, git2::BranchType::Local)
+            .map_err(|_| GitError::BranchNotFound(branch.to_string()))?
+            .into_reference();
+        if path.exists() {
+            return Err(GitError::WorktreePathExists(path.to_path_buf()));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut options = WorktreeAddOptions::new();
+        options.reference(Some(&reference));
+        let worktree = self
+            .repo
+            .worktree(&worktree_name(branch), path, Some(&options))
+            .git_context(format!("Failed to add a worktree for '{}'", branch))?;
+
+        self.worktree_info(&worktree)
+    }
+
+    /// Linked worktrees of the repository. The main working tree is not
+    /// included.
+    pub fn worktrees(&self) -> Result<Vec<WorktreeInfo>> {
+        let names = self
+            .repo
+            .worktrees()
+            .git_context("Failed to list worktrees")?;
+
+        let mut worktrees = Vec::new();
+        for name in names.iter().flatten() {
+            let worktree = self.repo.find_worktree(name)?;
+            worktrees.push(self.worktree_info(&worktree)?);
+        }
+        Ok(worktrees)
+    }
+
+    /// Deletes the worktree named `target`, checked out on branch `target`
+    /// or located at path `target`, together with its directory. Worktrees
+    /// with uncommitted changes are kept unless `force` is set.
+    pub fn remove_worktree(&self, target: &str, force: bool) -> Result<WorktreeInfo> {
+        let info = self
+            .worktrees()?
+            .into_iter()
+            .find(|info| info.matches(target))
+            .ok_or_else(|| GitError::WorktreeNotFound(target.to_string()))?;
+
+        if info.locked {
+            return Err(GitError::WorktreeLocked(info.path));
+        }
+        if !force && !info.prunable {
+            let repo = GitRepo {
+                repo: Repository::open(&info.path).map_err(GitError::NotARepository)?,
+            };
+            if repo.has_uncommitted_changes()? {
+                return Err(GitError::WorktreeDirty(info.path));
+            }
+        }
+
+        let worktree = self.repo.find_worktree(&info.name)?;
+        worktree
+            .prune(Some(
+                WorktreePruneOptions::new().valid(true).working_tree(true),
+            ))
+            .git_context(format!("Failed to remove worktree '{}'", info.name))?;
+        Ok(info)
+    }
+
+    /// Removes the administrative files of worktrees whose directory was
+    /// deleted, like `git worktree prune`. Returns the pruned worktrees.
+    pub fn prune_worktrees(&self) -> Result<Vec<WorktreeInfo>> {
+        let mut pruned = Vec::new();
+        for info in self.worktrees()? {
+            if !info.prunable || info.locked {
+                continue;
+            }
+            self.repo
+                .find_worktree(&info.name)?
+                .prune(None)
+                .git_context(format!("Failed to prune worktree '{}'", info.name))?;
+            pruned.push(info);
+        }
+        Ok(pruned)
+    }
+
+    fn worktree_info(&self, worktree: &git2::Worktree) -> Result<WorktreeInfo> {
+        let branch = Repository::open_from_worktree(worktree)
+            .ok()
+            .and_then(|repo| {
+                let head = repo.head().ok()?;
+                head.is_branch()
+                    .then(|| String::from_utf8_lossy(head.shorthand_bytes()).into_owned())
+            });
+
+        Ok(WorktreeInfo {
+            name: worktree.name().unwrap_or_default().to_string(),
+            path: worktree.path().to_path_buf(),
+            branch,
+            locked: !matches!(worktree.is_locked()?, WorktreeLockStatus::Unlocked),
+            prunable: worktree.validate().is_err(),
+        })
+    }
+}
+
+impl WorktreeInfo {
+    /// Whether `target` names this worktree, its branch or its directory.
+    pub fn matches(&self, target: &str) -> bool {
+        if self.name == target || self.branch.as_deref() == Some(target) {
+            return true;
+        }
+
+        let target = Path::new(target);
+        let target = target
+            .canonicalize()
+            .unwrap_or_else(|_| normalize_path(target));
+        let path = self
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| normalize_path(&self.path));
+        path == target
+    }
+}
+
+/// Worktree names become directory names, so branch separators are
+/// replaced: `feature/login` gets the worktree `feature-login`.
+pub fn worktree_name(branch: &str) -> String {
+    branch.replace('/', "-")
+}
+
+/// Resolves `.` and `..` in `path` without touching the filesystem, so
+/// paths like `repo/../repo-feature` read naturally before they exist.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
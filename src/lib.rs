@@ -1,3 +1,4 @@
+pub mod api;
 pub mod config;
 pub mod git;
 pub mod utils;
\ No newline at end of file
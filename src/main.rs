@@ -1,11 +1,12 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
 use colored::*;
+use std::path::PathBuf;
+
+use gwf::git::GitRepo;
+use gwf::{api, config, git, utils};
 
 mod commands;
-mod config;
-mod git;
-mod utils;
 mod workflows;
 
 #[derive(Parser)]
@@ -27,6 +28,10 @@ pub struct Cli {
     /// Suppress all output except errors
     #[arg(short, long)]
     quiet: bool,
+
+    /// Configuration profile to use instead of GWF_PROFILE or remote matching
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -36,6 +41,15 @@ enum Commands {
         /// Force initialization even if already initialized
         #[arg(short, long)]
         force: bool,
+
+        /// Accept the detected settings without prompting
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Start from a preset (git-flow, github-flow, gitlab-flow, trunk-based)
+        /// or a configuration file instead of detecting settings
+        #[arg(short, long, value_name = "NAME|PATH")]
+        template: Option<String>,
     },
 
     /// Create and manage feature branches
@@ -50,6 +64,11 @@ enum Commands {
         /// Push to remote after creation
         #[arg(short, long)]
         push: bool,
+
+        /// Check the branch out in a linked worktree, at PATH or at
+        /// `workflows.worktree_path`
+        #[arg(short, long, value_name = "PATH", num_args = 0..=1)]
+        worktree: Option<Option<PathBuf>>,
     },
 
     /// Create and manage hotfix branches
@@ -60,6 +79,11 @@ enum Commands {
         /// Target branch for the hotfix
         #[arg(short, long)]
         target: Option<String>,
+
+        /// Check the branch out in a linked worktree, at PATH or at
+        /// `workflows.worktree_path`
+        #[arg(short, long, value_name = "PATH", num_args = 0..=1)]
+        worktree: Option<Option<PathBuf>>,
     },
 
     /// Create and manage releases
@@ -74,6 +98,15 @@ enum Commands {
         /// Tag the release
         #[arg(short, long)]
         tag: bool,
+
+        /// Push the release branch and tag to the remote
+        #[arg(short, long)]
+        push: bool,
+
+        /// Check the branch out in a linked worktree, at PATH or at
+        /// `workflows.worktree_path`
+        #[arg(short, long, value_name = "PATH", num_args = 0..=1)]
+        worktree: Option<Option<PathBuf>>,
     },
 
     /// Synchronize branches with upstream
@@ -107,8 +140,8 @@ enum Commands {
         /// Commit message
         message: Option<String>,
 
-        /// Use AI to generate commit message
-        #[arg(short, long)]
+        /// Generate the message from the staged diff with the [ai] provider
+        #[arg(short, long, conflicts_with = "message")]
         ai: bool,
 
         /// Amend the last commit
@@ -131,6 +164,16 @@ enum Commands {
         draft: bool,
     },
 
+    /// Check commit messages against the Conventional Commits rules in [lint]
+    LintCommits {
+        /// Revision range to check [default: <main_branch>..HEAD]
+        range: Option<String>,
+
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Generate standup report
     Standup {
         /// Number of days to look back
@@ -143,7 +186,11 @@ enum Commands {
     },
 
     /// Manage GWF configuration
+    #[command(args_conflicts_with_subcommands = true)]
     Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+
         /// Show current configuration
         #[arg(short, long)]
         show: bool,
@@ -177,12 +224,132 @@ enum Commands {
 
     /// Find commits using binary search
     Bisect,
+
+    /// Manage linked worktrees
+    Worktree {
+        #[command(subcommand)]
+        action: WorktreeAction,
+    },
+
+    /// Inspect stashed changes
+    Stash {
+        #[command(subcommand)]
+        action: StashAction,
+    },
+
+    /// Manage the git hooks configured in [hooks]
+    Hooks {
+        #[command(subcommand)]
+        action: HooksAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum StashAction {
+    /// List stashes grouped by the branch they were made on
+    List,
+}
+
+#[derive(Subcommand)]
+enum HooksAction {
+    /// Install hooks that run the commands in [hooks], keeping existing hooks
+    Install,
+
+    /// Remove gwf's hooks and restore the ones they replaced
+    Uninstall,
+
+    /// Run a hook's commands; called by the installed hooks
+    Run {
+        /// Name of the git hook, e.g. commit-msg
+        hook: String,
+
+        /// Arguments git passed to the hook
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorktreeAction {
+    /// List linked worktrees and the branches checked out in them
+    List,
+
+    /// Remove a worktree and its directory
+    Remove {
+        /// Branch, name or path of the worktree
+        target: String,
+
+        /// Remove the worktree even if it has uncommitted changes
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Forget worktrees whose directory was deleted
+    Prune,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Check the configuration for unknown keys, type errors and invalid settings
+    Validate,
+
+    /// Upgrade configuration files to the current schema version
+    Migrate {
+        /// Show the changes as a diff without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Print the JSON Schema for .gwf.toml, for editor completion and validation
+    Schema,
+
+    /// Print the effective value of a key or section, e.g. `sync.strategy`
+    Get {
+        /// Dotted key
+        key: String,
+    },
+
+    /// Set a key in a configuration file, keeping its comments and formatting
+    Set {
+        /// Dotted key
+        key: String,
+
+        /// New value, as a TOML literal or a plain string
+        value: String,
+
+        #[command(flatten)]
+        scope: ConfigScope,
+    },
+
+    /// Remove a key from a configuration file
+    Unset {
+        /// Dotted key
+        key: String,
+
+        #[command(flatten)]
+        scope: ConfigScope,
+    },
+}
+
+#[derive(Args)]
+struct ConfigScope {
+    /// Edit the user-wide ~/.config/gwf/config.toml
+    #[arg(long, conflicts_with = "local")]
+    global: bool,
+
+    /// Edit the personal, git-ignored .gwf.local.toml
+    #[arg(long)]
+    local: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    if let Some(profile) = cli.profile.clone() {
+        config::select_profile(profile);
+    }
+
     // Set up logging
     let log_level = match cli.verbose {
         0 => "warn",
@@ -195,8 +362,20 @@ async fn main() -> Result<()> {
         tracing_subscriber::fmt().with_env_filter(log_level).init();
     }
 
-    // ASCII art banner
-    if !cli.quiet && cli.verbose == 0 {
+    // ASCII art banner, kept out of output meant for other programs and of
+    // git hooks
+    let machine_readable = matches!(
+        cli.command,
+        Commands::LintCommits { json: true, .. }
+            | Commands::Config {
+                action: Some(ConfigAction::Schema),
+                ..
+            }
+            | Commands::Hooks {
+                action: HooksAction::Run { .. }
+            }
+    );
+    if !cli.quiet && cli.verbose == 0 && !machine_readable {
         println!(
             "{}",
             r#"
@@ -214,67 +393,126 @@ async fn main() -> Result<()> {
     }
 
     match cli.command {
-        Commands::Init { force } => {
-            commands::init::execute(force).await?;
+        Commands::Init {
+            force,
+            yes,
+            template,
+        } => {
+            commands::init::execute(&open_repo()?, force, yes, template).await?;
         }
-        Commands::Feature { name, from, push } => {
-            commands::feature::execute(name, from, push).await?;
+        Commands::Feature {
+            name,
+            from,
+            push,
+            worktree,
+        } => {
+            commands::feature::execute(&mut open_repo()?, name, from, push, worktree).await?;
         }
-        Commands::Hotfix { name, target } => {
-            commands::hotfix::execute(name, target).await?;
+        Commands::Hotfix {
+            name,
+            target,
+            worktree,
+        } => {
+            commands::hotfix::execute(&mut open_repo()?, name, target, worktree).await?;
         }
         Commands::Release {
             version,
             changelog,
             tag,
+            push,
+            worktree,
         } => {
-            commands::release::execute(version, changelog, tag).await?;
+            commands::release::execute(&mut open_repo()?, version, changelog, tag, push, worktree)
+                .await?;
         }
         Commands::Sync { all, branch } => {
-            commands::sync::execute(all, branch).await?;
+            commands::sync::execute(&mut open_repo()?, all, branch).await?;
         }
         Commands::Cleanup {
             yes,
             dry_run,
             remote,
         } => {
-            commands::cleanup::execute(yes, dry_run, remote).await?;
+            commands::cleanup::execute(&open_repo()?, yes, dry_run, remote).await?;
         }
         Commands::Commit { message, ai, amend } => {
-            commands::commit::execute(message, ai, amend).await?;
+            commands::commit::execute(&open_repo()?, message, ai, amend).await?;
         }
         Commands::PullRequest {
             title,
             target,
             draft,
         } => {
-            commands::pr::execute(title, target, draft).await?;
+            commands::pr::execute(&open_repo()?, title, target, draft).await?;
         }
-        Commands::Standup { days, all } => {
-            commands::standup::execute(days, all).await?;
+        Commands::LintCommits { range, json } => {
+            commands::lint::execute(&open_repo()?, range, json).await?;
         }
-        Commands::Config { show, edit, reset } => {
-            commands::config::execute(show, edit, reset).await?;
+        Commands::Standup { days, all } => {
+            commands::standup::execute(&open_repo()?, days, all).await?;
         }
+        Commands::Config {
+            action,
+            show,
+            edit,
+            reset,
+        } => match action {
+            Some(ConfigAction::Validate) => {
+                let repo = GitRepo::open_current().ok();
+                commands::config::validate(repo.as_ref().map(|repo| repo as _)).await?
+            }
+            Some(ConfigAction::Migrate { dry_run }) => commands::config::migrate(dry_run).await?,
+            Some(ConfigAction::Schema) => commands::config::schema()?,
+            Some(ConfigAction::Get { key }) => commands::config::get(key).await?,
+            Some(ConfigAction::Set { key, value, scope }) => {
+                commands::config::set(key, value, scope.global, scope.local).await?
+            }
+            Some(ConfigAction::Unset { key, scope }) => {
+                commands::config::unset(key, scope.global, scope.local).await?
+            }
+            None => commands::config::execute(show, edit, reset).await?,
+        },
         Commands::Completions { shell } => {
             commands::completions::execute(shell);
         }
         Commands::Status => {
-            commands::status::run().await?;
+            commands::status::run(&open_repo()?).await?;
         }
         Commands::Undo => {
-            commands::undo::run().await?;
+            commands::undo::run(&open_repo()?).await?;
         }
         Commands::Stats => {
-            commands::stats::run().await?;
+            commands::stats::run(&open_repo()?).await?;
         }
         Commands::Tag => {
-            commands::tag::run().await?;
+            commands::tag::run(&open_repo()?).await?;
         }
         Commands::Bisect => {
-            commands::bisect::run().await?;
+            commands::bisect::run(&open_repo()?).await?;
         }
+        Commands::Worktree { action } => match action {
+            WorktreeAction::List => commands::worktree::list(&open_repo()?).await?,
+            WorktreeAction::Remove { target, force } => {
+                commands::worktree::remove(&open_repo()?, target, force).await?
+            }
+            WorktreeAction::Prune => commands::worktree::prune(&open_repo()?).await?,
+        },
+        Commands::Stash { action } => match action {
+            StashAction::List => commands::stash::list(&mut open_repo()?).await?,
+        },
+        Commands::Hooks { action } => match action {
+            HooksAction::Install => commands::hooks::install(&open_repo()?).await?,
+            HooksAction::Uninstall => commands::hooks::uninstall(&open_repo()?).await?,
+            HooksAction::Run { hook, args } => {
+                commands::hooks::run(&open_repo()?, hook, args).await?
+            }
+        },
     }
 
     Ok(())
 }
+
+/// Opens the repository commands run against.
+fn open_repo() -> Result<GitRepo> {
+    GitRepo::open_current().context("Not in a git repository. Run 'git init' first.")
+}
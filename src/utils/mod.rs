@@ -1,10 +1,11 @@
 use colored::*;
 
+pub mod progress;
+
 pub fn print_success(message: &str) {
     println!("{} {}", "✓".green().bold(), message);
 }
 
-#[allow(dead_code)]
 pub fn print_error(message: &str) {
     eprintln!("{} {}", "✗".red().bold(), message);
 }
@@ -17,11 +18,58 @@ pub fn print_info(message: &str) {
     println!("{} {}", "ℹ".blue().bold(), message);
 }
 
+/// Formats `error` followed by its causes, like anyhow's `{:#}`.
+pub fn format_error(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+/// Formats a Unix timestamp relative to now, like git's `%cr`.
+pub fn format_relative_time(timestamp: i64) -> String {
+    relative_time(chrono::Utc::now().timestamp() - timestamp)
+}
+
+fn relative_time(seconds: i64) -> String {
+    const UNITS: &[(i64, &str)] = &[
+        (60 * 60 * 24 * 365, "year"),
+        (60 * 60 * 24 * 30, "month"),
+        (60 * 60 * 24 * 7, "week"),
+        (60 * 60 * 24, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ];
+
+    let seconds = seconds.max(0);
+    for (size, unit) in UNITS {
+        let count = seconds / size;
+        if count > 0 {
+            let plural = if count == 1 { "" } else { "s" };
+            return format!("{} {}{} ago", count, unit, plural);
+        }
+    }
+    format!("{} seconds ago", seconds)
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn test_utils_module() {
         // Just verify the module compiles and functions exist
         assert_eq!(1 + 1, 2);
     }
+
+    #[test]
+    fn test_relative_time() {
+        assert_eq!(relative_time(5), "5 seconds ago");
+        assert_eq!(relative_time(60), "1 minute ago");
+        assert_eq!(relative_time(3 * 60 * 60 + 5), "3 hours ago");
+        assert_eq!(relative_time(15 * 24 * 60 * 60), "2 weeks ago");
+    }
 }
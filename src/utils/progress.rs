@@ -0,0 +1,126 @@
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
+use std::time::Duration;
+
+use crate::api::FetchReporter;
+use crate::git::{FetchProgress, Result, TransferProgress};
+
+fn waiting_style() -> ProgressStyle {
+    ProgressStyle::default_spinner()
+        .template("{spinner:.green} {prefix:.bold} {wide_msg}")
+        .unwrap()
+}
+
+fn transfer_style() -> ProgressStyle {
+    ProgressStyle::default_bar()
+        .template("{spinner:.green} {prefix:.bold} [{bar:30.cyan/blue}] {pos}/{len} {wide_msg}")
+        .unwrap()
+        .progress_chars("=> ")
+}
+
+/// A spinner for a step with no measurable progress, such as waiting on a
+/// server. Call `finish_and_clear` when the step is done.
+pub fn spinner(message: &str) -> ProgressBar {
+    let bar = ProgressBar::new_spinner();
+    bar.set_style(waiting_style());
+    bar.set_message(message.to_string());
+    bar.enable_steady_tick(Duration::from_millis(100));
+    bar
+}
+
+/// Draws a progress bar per fetched remote showing the objects, bytes and
+/// deltas received so far. The bars are stacked so earlier fetches stay
+/// visible while later ones run.
+#[derive(Default)]
+pub struct FetchBars {
+    bars: MultiProgress,
+    current: Option<(ProgressBar, TransferProgress)>,
+}
+
+impl FetchBars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FetchReporter for FetchBars {
+    fn started(&mut self, remote: &str) {
+        let bar = self.bars.add(ProgressBar::new_spinner());
+        bar.set_style(waiting_style());
+        bar.set_prefix(remote.to_string());
+        bar.set_message("connecting...");
+        bar.enable_steady_tick(Duration::from_millis(100));
+        self.current = Some((bar, TransferProgress::default()));
+    }
+
+    fn progress(&mut self, _remote: &str, progress: FetchProgress) {
+        let Some((bar, last)) = &mut self.current else {
+            return;
+        };
+        match progress {
+            FetchProgress::Transfer(transfer) => {
+                if last.total_objects == 0 && transfer.total_objects > 0 {
+                    bar.set_style(transfer_style());
+                }
+                show_transfer(bar, &transfer);
+                *last = transfer;
+            }
+            // Once objects arrive our own counters say more than the
+            // remote's, which is still counting and compressing.
+            FetchProgress::Remote(line) if last.total_objects == 0 => bar.set_message(line),
+            FetchProgress::Remote(_) => {}
+        }
+    }
+
+    fn finished(&mut self, _remote: &str, result: &Result<()>) {
+        let Some((bar, last)) = self.current.take() else {
+            return;
+        };
+        match result {
+            Ok(()) => bar.finish_with_message(summary(&last)),
+            Err(_) => bar.abandon_with_message("failed"),
+        }
+    }
+}
+
+fn show_transfer(bar: &ProgressBar, transfer: &TransferProgress) {
+    if transfer.resolving_deltas() {
+        bar.set_length(transfer.total_deltas as u64);
+        bar.set_position(transfer.indexed_deltas as u64);
+        bar.set_message("resolving deltas");
+    } else {
+        bar.set_length(transfer.total_objects as u64);
+        bar.set_position(transfer.received_objects as u64);
+        bar.set_message(format!(
+            "objects, {}",
+            HumanBytes(transfer.received_bytes as u64)
+        ));
+    }
+}
+
+fn summary(transfer: &TransferProgress) -> String {
+    if transfer.total_objects == 0 {
+        return "up to date".to_string();
+    }
+    format!(
+        "done, {} objects ({})",
+        transfer.total_objects,
+        HumanBytes(transfer.received_bytes as u64)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(summary(&TransferProgress::default()), "up to date");
+        let transfer = TransferProgress {
+            received_objects: 120,
+            total_objects: 120,
+            received_bytes: 2048,
+            ..Default::default()
+        };
+        assert_eq!(summary(&transfer), "done, 120 objects (2.00 KiB)");
+    }
+}
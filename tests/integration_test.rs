@@ -12,4 +12,22 @@ fn test_crate_name() {
 fn test_authors() {
     let authors = env!("CARGO_PKG_AUTHORS");
     assert!(authors.contains("Nonanti"));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_library_api_returns_reports() {
+    use gwf::api::{self, CleanupOptions};
+    use gwf::config::Config;
+    use gwf::git::{GitBackend, MemoryRepo};
+
+    let repo = MemoryRepo::new();
+    repo.commit_on("main", "Initial commit");
+    repo.create_branch("feature/done", None).unwrap();
+
+    let options = CleanupOptions::default();
+    let report = api::cleanup(&repo, &Config::default(), &options, &mut ()).unwrap();
+
+    assert_eq!(report.deleted, ["feature/done"]);
+    let branches = repo.list_branches(false).unwrap();
+    assert!(!branches.contains(&"feature/done".to_string()));
+}